@@ -0,0 +1,524 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::model::{Alliance, Match, Ranking, Round};
+use crate::predict::Predictor;
+use crate::storage;
+
+/// An elimination alliance, captain first
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SeededAlliance
+{
+    pub seed: u8,
+    pub teams: Vec<String>
+}
+
+impl SeededAlliance
+{
+    /// Whether every team on the field for `alliance` is on this alliance
+    fn fielded(&self, alliance: &Alliance) -> bool
+    {
+        let mut playing = alliance.playing().peekable();
+        playing.peek().is_some() && playing.all(|team| self.teams.iter().any(|other_team| other_team.eq_ignore_ascii_case(team)))
+    }
+}
+
+impl fmt::Display for SeededAlliance
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "#{} {}", self.seed, self.teams.join(" "))
+    }
+}
+
+/// A competition's elimination alliances
+///
+/// Kept in `vexscout/brackets` under the config directory, one file for the
+/// competition or for each of its divisions, rewritten whenever it changes
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bracket
+{
+    pub sku: String,
+    /// Each division of a multi-division competition has its own bracket
+    #[serde(default)]
+    pub division: Option<String>,
+    alliances: Vec<SeededAlliance>,
+    /// Matches in each series; the first alliance to win a majority advances
+    #[serde(default = "default_series_length")]
+    pub series_length: u8
+}
+
+/// The most alliances a bracket holds, starting from the round of 16
+pub const MAX_SEED: u8 = 16;
+
+fn default_series_length() -> u8
+{
+    1
+}
+
+impl Bracket
+{
+    pub fn new(sku: &str, division: Option<&str>) -> Bracket
+    {
+        Bracket
+        {
+            sku: sku.to_ascii_uppercase(),
+            division: division.map(str::to_owned),
+            alliances: Vec::new(),
+            series_length: default_series_length()
+        }
+    }
+
+    pub fn default_path(sku: &str, division: Option<&str>) -> Option<PathBuf>
+    {
+        let mut name = sku.to_ascii_uppercase();
+        if let Some(division) = division
+        {
+            // division names may hold spaces or slashes
+            name.push('-');
+            name.extend(division.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }));
+        }
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("brackets").join(format!("{}.json", name)))
+    }
+
+    /// The saved bracket for the competition or one of its divisions, or an empty one if there is none yet
+    pub fn load_default(sku: &str, division: Option<&str>) -> Bracket
+    {
+        Bracket::default_path(sku, division)
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+            .unwrap_or_else(|| Bracket::new(sku, division))
+    }
+
+    pub fn save_default(&self) -> Result<(), String>
+    {
+        let path = Bracket::default_path(&self.sku, self.division.as_deref()).ok_or_else(|| String::from("no config directory for brackets"))?;
+        storage::save_json(&path, self)
+    }
+
+    /// Loads alliances from a JSON list like `[{"seed": 1, "teams": ["1A", "2A"]}]`
+    pub fn import_file(&mut self, path: &Path) -> Result<(), String>
+    {
+        let file = File::open(path).map_err(|error| format!("could not open {}: {}", path.display(), error))?;
+        let alliances: Vec<SeededAlliance> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        if let Some(alliance) = alliances.iter().find(|alliance| !(1..=MAX_SEED).contains(&alliance.seed))
+        {
+            return Err(format!("{} has seed {}, but seeds go from 1 to {}", path.display(), alliance.seed, MAX_SEED));
+        }
+        self.alliances.clear();
+        for alliance in alliances
+        {
+            self.set_alliance(alliance.seed, alliance.teams)?;
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the alliances from elimination matches already scheduled
+    ///
+    /// Seeds follow the best qualification rank on each alliance, which is the
+    /// captain's unless a higher ranked team accepted an invitation; `matches`
+    /// and `rankings` should only cover this bracket's division
+    pub fn import_matches(&mut self, matches: &[Match], rankings: &[Ranking])
+    {
+        let mut alliances: Vec<Vec<String>> = Vec::new();
+        for alliance in matches.iter().filter(|match_struct| match_struct.round.is_elimination()).flat_map(|match_struct| vec![&match_struct.red, &match_struct.blue])
+        {
+            let teams = alliance.teams.iter().map(|team| team.to_ascii_uppercase()).collect::<Vec<String>>();
+            if let Some(existing) = alliances.iter_mut().find(|existing| teams.iter().any(|team| existing.contains(team)))
+            {
+                // a third team only shows up in the matches it plays
+                for team in teams
+                {
+                    if !existing.contains(&team)
+                    {
+                        existing.push(team);
+                    }
+                }
+            }
+            else if !teams.is_empty()
+            {
+                alliances.push(teams);
+            }
+        }
+
+        let rank_of = |team: &String| rankings.iter()
+            .find(|ranking| ranking.team.eq_ignore_ascii_case(team))
+            .map(|ranking| ranking.rank)
+            .unwrap_or(u16::MAX);
+        for teams in alliances.iter_mut()
+        {
+            teams.sort_by_key(rank_of);
+        }
+        alliances.sort_by_key(|teams| rank_of(&teams[0]));
+
+        self.alliances = alliances.into_iter()
+            .enumerate()
+            .map(|(index, teams)| SeededAlliance { seed: index as u8 + 1, teams })
+            .collect();
+    }
+
+    pub fn get_alliances(&self) -> &[SeededAlliance]
+    {
+        &self.alliances
+    }
+
+    /// Adds or replaces the alliance with the given seed, which must be from 1 to `MAX_SEED`
+    pub fn set_alliance(&mut self, seed: u8, teams: Vec<String>) -> Result<(), String>
+    {
+        if !(1..=MAX_SEED).contains(&seed)
+        {
+            return Err(format!("Seeds go from 1 to {}", MAX_SEED));
+        }
+        let teams = teams.iter().map(|team| team.to_ascii_uppercase()).collect();
+        self.alliances.retain(|alliance| alliance.seed != seed);
+        self.alliances.push(SeededAlliance { seed, teams });
+        self.alliances.sort_by_key(|alliance| alliance.seed);
+        Ok(())
+    }
+
+    pub fn remove_alliance(&mut self, seed: u8) -> bool
+    {
+        let count = self.alliances.len();
+        self.alliances.retain(|alliance| alliance.seed != seed);
+        self.alliances.len() != count
+    }
+}
+
+/// Chances this close to one are treated as certain, so rounding does not hide a decided result
+const CERTAIN: f64 = 1.0 - 1e-9;
+
+/// One series of the bracket, as far as it can be told yet
+pub struct Series
+{
+    pub round: Round,
+    /// Chance of each alliance, by position in the bracket's list, reaching
+    /// each side of the series
+    sides: [Vec<f64>; 2],
+    /// Chance of each alliance winning the series
+    winner: Vec<f64>,
+    /// Wins so far when both sides are known
+    wins: Option<(u8, u8)>
+}
+
+/// The whole bracket with each alliance's chances
+pub struct Projection
+{
+    alliances: Vec<SeededAlliance>,
+    pub rounds: Vec<Vec<Series>>
+}
+
+impl Projection
+{
+    /// The most likely alliance for a distribution, with its chance
+    fn favourite(&self, distribution: &[f64]) -> Option<(&SeededAlliance, f64)>
+    {
+        distribution.iter()
+            .enumerate()
+            .filter(|(_, probability)| **probability > 0.0)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(index, probability)| (&self.alliances[index], *probability))
+    }
+
+    /// Each alliance's chance of winning the tournament, best first
+    pub fn get_title_chances(&self) -> Vec<(&SeededAlliance, f64)>
+    {
+        let mut chances = match self.rounds.last().and_then(|series| series.first())
+        {
+            Some(final_series) => self.alliances.iter().zip(final_series.winner.iter().copied()).collect::<Vec<(&SeededAlliance, f64)>>(),
+            None => Vec::new()
+        };
+        chances.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.seed.cmp(&b.0.seed)));
+        chances
+    }
+
+    /// One series on a line, e.g. `#1 1A 2A vs #8 7A 8A [1-0] -> #1 87.5%`
+    pub fn describe(&self, series: &Series) -> String
+    {
+        let side = |distribution: &[f64]| match self.favourite(distribution)
+        {
+            Some((alliance, probability)) if probability >= CERTAIN => format!("{}", alliance),
+            Some((alliance, probability)) => format!("{} ({:.0}% to get here)", alliance, 100.0 * probability),
+            None => String::from("bye")
+        };
+        let wins = series.wins.map(|(a, b)| format!(" [{}-{}]", a, b)).unwrap_or_default();
+        let winner = match self.favourite(&series.winner)
+        {
+            Some((alliance, probability)) => format!("#{} {:.1}%", alliance.seed, 100.0 * probability),
+            None => String::from("-")
+        };
+        format!("{:<30} vs {:<30}{} -> {}", side(&series.sides[0]), side(&series.sides[1]), wins, winner)
+    }
+}
+
+/// Seeds in bracket order, e.g. 1 8 4 5 2 7 3 6 for eight alliances
+fn seed_order(size: usize) -> Vec<usize>
+{
+    let mut order = vec![1];
+    while order.len() < size
+    {
+        let next_size = order.len() * 2;
+        order = order.iter().flat_map(|seed| vec![*seed, next_size + 1 - *seed]).collect();
+    }
+    order
+}
+
+/// Chance of winning a series still needing `needed` wins against an
+/// opponent needing `opponent_needed`, winning each match with `probability`
+fn series_probability(probability: f64, needed: u8, opponent_needed: u8) -> f64
+{
+    if needed == 0
+    {
+        return 1.0;
+    }
+    if opponent_needed == 0
+    {
+        return 0.0;
+    }
+
+    // win the last match after any number of losses the opponent can afford
+    let mut total = 0.0;
+    let mut ways = 1.0;
+    for losses in 0..opponent_needed as i32
+    {
+        if losses > 0
+        {
+            ways *= (needed as i32 - 1 + losses) as f64 / losses as f64;
+        }
+        total += ways * probability.powi(needed as i32) * (1.0 - probability).powi(losses);
+    }
+    total
+}
+
+/// A made up match between two alliances for the predictor; a third team sits out
+fn hypothetical_match(sku: &str, round: Round, red: &SeededAlliance, blue: &SeededAlliance) -> Match
+{
+    let alliance = |alliance: &SeededAlliance| Alliance { teams: alliance.teams.clone(), sitting: alliance.teams.get(2).cloned(), score: 0 };
+    Match
+    {
+        sku: sku.to_owned(),
+        division: String::new(),
+        round,
+        instance: 0,
+        match_num: 0,
+        field: String::new(),
+        scheduled: None,
+        started: None,
+        red: alliance(red),
+        blue: alliance(blue),
+        scored: false
+    }
+}
+
+/// Projects the bracket from the alliances and any elimination results so far
+///
+/// Series already decided are fixed; the rest use the predictor, which should
+/// already know every scored match. With fewer alliances than the bracket
+/// holds, the top seeds get byes. Fails with more alliances than the round of 16 holds.
+pub fn project(bracket: &Bracket, matches: &[Match], predictor: &Predictor) -> Result<Projection, String>
+{
+    let alliances = bracket.alliances.clone();
+    let count = alliances.len();
+    let mut projection = Projection { alliances, rounds: Vec::new() };
+    if count < 2
+    {
+        return Ok(projection);
+    }
+
+    let size = count.next_power_of_two();
+    let rounds = [Round::RoundOf16, Round::QuarterFinal, Round::SemiFinal, Round::Final];
+    let rounds = rounds.len().checked_sub(size.trailing_zeros() as usize)
+        .map(|first| &rounds[first..])
+        .ok_or_else(|| format!("A bracket holds at most {} alliances, but there are {}", MAX_SEED, count))?;
+    let needed = bracket.series_length / 2 + 1;
+
+    // the chance of each alliance filling each spot in the current round
+    let mut spots = seed_order(size).into_iter()
+        .map(|seed|
+        {
+            // alliances are kept in seed order, so gaps in the seeds close up
+            let mut distribution = vec![0.0; count];
+            if seed <= count
+            {
+                distribution[seed - 1] = 1.0;
+            }
+            distribution
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    for round in rounds
+    {
+        let mut round_series = Vec::new();
+        for pair in spots.chunks(2)
+        {
+            let (left, right) = (&pair[0], &pair[1]);
+            let mut winner = vec![0.0; count];
+            let left_empty = left.iter().all(|probability| *probability == 0.0);
+            let right_empty = right.iter().all(|probability| *probability == 0.0);
+            let mut wins = None;
+
+            if left_empty || right_empty
+            {
+                // a bye, or an empty corner of the bracket
+                for (index, probability) in winner.iter_mut().enumerate()
+                {
+                    *probability = left[index] + right[index];
+                }
+            }
+            else
+            {
+                for a in (0..count).filter(|a| left[*a] > 0.0)
+                {
+                    for b in (0..count).filter(|b| right[*b] > 0.0)
+                    {
+                        let (red, blue) = (&projection.alliances[a], &projection.alliances[b]);
+                        let (red_wins, blue_wins) = count_wins(matches, *round, red, blue);
+                        if left[a] >= CERTAIN && right[b] >= CERTAIN
+                        {
+                            wins = Some((red_wins, blue_wins));
+                        }
+
+                        let probability = predictor.predict(&hypothetical_match(&bracket.sku, *round, red, blue)).red_win_probability;
+                        let red_series = series_probability(probability, needed.saturating_sub(red_wins), needed.saturating_sub(blue_wins));
+                        let weight = left[a] * right[b];
+                        winner[a] += weight * red_series;
+                        winner[b] += weight * (1.0 - red_series);
+                    }
+                }
+            }
+
+            round_series.push(Series { round: *round, sides: [left.clone(), right.clone()], winner, wins });
+        }
+
+        spots = round_series.iter().map(|series| series.winner.clone()).collect();
+        projection.rounds.push(round_series);
+    }
+    Ok(projection)
+}
+
+/// Scored wins for each alliance in matches of `round` between the two
+fn count_wins(matches: &[Match], round: Round, first: &SeededAlliance, second: &SeededAlliance) -> (u8, u8)
+{
+    let mut wins = (0, 0);
+    for match_struct in matches.iter().filter(|match_struct| match_struct.round == round)
+    {
+        // ties are replayed, so they count for neither
+        if let Some(color) = match_struct.winner()
+        {
+            let (winning, losing) = (match_struct.alliance(color), match_struct.alliance(color.opposite()));
+            if first.fielded(winning) && second.fielded(losing)
+            {
+                wins.0 += 1;
+            }
+            else if second.fielded(winning) && first.fielded(losing)
+            {
+                wins.1 += 1;
+            }
+        }
+    }
+    wins
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::rating::RatingModel;
+    use crate::testing;
+
+    fn bracket(count: u8) -> Bracket
+    {
+        let mut bracket = Bracket::new(testing::SKU, None);
+        bracket.series_length = 3;
+        for seed in 1..=count
+        {
+            bracket.set_alliance(seed, vec![format!("{}A", seed), format!("{}B", seed)]).unwrap();
+        }
+        bracket
+    }
+
+    fn title_chances(projection: &Projection) -> Vec<(u8, f64)>
+    {
+        projection.get_title_chances().into_iter().map(|(alliance, probability)| (alliance.seed, probability)).collect()
+    }
+
+    #[test]
+    fn series_probability_known_answers()
+    {
+        // best of three at 60% a match: 0.6² + 2 · 0.6² · 0.4
+        assert!((series_probability(0.6, 2, 2) - 0.648).abs() < 1e-12);
+        assert!((series_probability(0.6, 1, 1) - 0.6).abs() < 1e-12);
+        // one win from taking it, or one loss from losing it
+        assert!((series_probability(0.6, 1, 2) - 0.84).abs() < 1e-12);
+        assert!((series_probability(0.6, 2, 1) - 0.36).abs() < 1e-12);
+        assert_eq!(series_probability(0.3, 0, 2), 1.0);
+        assert_eq!(series_probability(0.9, 2, 0), 0.0);
+    }
+
+    #[test]
+    fn project_fixes_decided_series()
+    {
+        // a fresh Elo predictor makes every undecided match a coin flip
+        let predictor = Predictor::new(RatingModel::Elo, &[]);
+        let projection = project(&bracket(4), &[], &predictor).unwrap();
+        assert_eq!(projection.rounds.len(), 2);
+        assert!(title_chances(&projection).iter().all(|(_, probability)| (probability - 0.25).abs() < 1e-12));
+
+        // #1 has beaten #4 twice in its semifinal
+        let semifinal = |number|
+        {
+            let mut match_struct = testing::qualification(number, &["1A", "1B"], &["4A", "4B"], Some((30, 10)));
+            match_struct.round = Round::SemiFinal;
+            match_struct
+        };
+        let projection = project(&bracket(4), &[semifinal(1), semifinal(2)], &predictor).unwrap();
+        let chances = title_chances(&projection);
+        assert_eq!(chances[0].0, 1);
+        assert!((chances[0].1 - 0.5).abs() < 1e-12);
+        assert_eq!(chances[3], (4, 0.0));
+        assert_eq!(projection.rounds[0][0].wins, Some((2, 0)));
+    }
+
+    #[test]
+    fn top_seeds_get_byes()
+    {
+        let predictor = Predictor::new(RatingModel::Elo, &[]);
+        let projection = project(&bracket(3), &[], &predictor).unwrap();
+        let chances = title_chances(&projection);
+        assert_eq!(chances[0].0, 1);
+        assert!((chances[0].1 - 0.5).abs() < 1e-12);
+        assert!((chances[1].1 - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_more_alliances_than_the_round_of_16()
+    {
+        let mut bracket = bracket(16);
+        assert!(bracket.set_alliance(0, vec![String::from("1C")]).is_err());
+        assert!(bracket.set_alliance(MAX_SEED + 1, vec![String::from("1C")]).is_err());
+
+        let predictor = Predictor::new(RatingModel::Elo, &[]);
+        assert_eq!(project(&bracket, &[], &predictor).unwrap().rounds.len(), 4);
+        // alliances rebuilt from scheduled matches are not checked on the way in
+        bracket.alliances.push(SeededAlliance { seed: MAX_SEED + 1, teams: vec![String::from("17A")] });
+        assert!(project(&bracket, &[], &predictor).is_err());
+    }
+
+    #[test]
+    fn divisions_are_saved_apart()
+    {
+        let file_name = |division| Bracket::default_path("re-test", division)
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()));
+        if file_name(None).is_none()
+        {
+            // no config directory to save to
+            return;
+        }
+        assert_eq!(file_name(None).unwrap(), "RE-TEST.json");
+        assert_eq!(file_name(Some("Science")).unwrap(), "RE-TEST-Science.json");
+        assert_eq!(file_name(Some("Math / Tech")).unwrap(), "RE-TEST-Math___Tech.json");
+    }
+}
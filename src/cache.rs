@@ -0,0 +1,566 @@
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::model::{Award, Event, Match, Ranking, SeasonRanking, Skill, Team};
+use crate::source::{DataSource, DataSourceKind, Query, SourceError};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DataKind
+{
+    Teams,
+    Events,
+    Matches,
+    Rankings,
+    Awards,
+    Skills,
+    SeasonRankings
+}
+
+pub const DATA_KINDS: [DataKind; 7] = [
+    DataKind::Teams,
+    DataKind::Events,
+    DataKind::Matches,
+    DataKind::Rankings,
+    DataKind::Awards,
+    DataKind::Skills,
+    DataKind::SeasonRankings
+];
+
+impl DataKind
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DataKind::Teams => "teams",
+            DataKind::Events => "events",
+            DataKind::Matches => "matches",
+            DataKind::Rankings => "rankings",
+            DataKind::Awards => "awards",
+            DataKind::Skills => "skills",
+            DataKind::SeasonRankings => "season_rankings"
+        }
+    }
+
+    /// Seconds before a cached entry is refetched
+    ///
+    /// Team info almost never changes, while match scores and rankings
+    /// change every few minutes during an event
+    pub fn time_to_live(&self) -> u64
+    {
+        match self
+        {
+            DataKind::Teams => 7 * 24 * 60 * 60,
+            DataKind::Events => 24 * 60 * 60,
+            DataKind::Matches => 60,
+            DataKind::Rankings => 2 * 60,
+            DataKind::Awards => 10 * 60,
+            DataKind::Skills => 10 * 60,
+            DataKind::SeasonRankings => 24 * 60 * 60
+        }
+    }
+}
+
+pub fn now() -> u64
+{
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+/// Formats a number of seconds as a short human readable age, e.g. `5m`
+pub fn format_age(seconds: u64) -> String
+{
+    if seconds < 60
+    {
+        format!("{}s", seconds)
+    }
+    else if seconds < 60 * 60
+    {
+        format!("{}m", seconds / 60)
+    }
+    else if seconds < 24 * 60 * 60
+    {
+        format!("{}h", seconds / (60 * 60))
+    }
+    else
+    {
+        format!("{}d", seconds / (24 * 60 * 60))
+    }
+}
+
+/// Keys start with the data source, so switching sources never serves the other one's rows
+pub fn query_key(source: DataSourceKind, kind: DataKind, query: Query) -> String
+{
+    let mut key = format!("{}|{}|sku={}|team={}|",
+        source.name(),
+        kind.name(),
+        query.sku.unwrap_or("").to_ascii_uppercase(),
+        query.team.unwrap_or("").to_ascii_uppercase());
+    if let Some(region) = query.region
+    {
+        key.push_str(&format!("region={}|", region.to_ascii_uppercase()));
+    }
+    key
+}
+
+pub fn team_key(source: DataSourceKind, number: &str) -> String
+{
+    format!("{}|{}|team={}|", source.name(), DataKind::Teams.name(), number.to_ascii_uppercase())
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T>
+{
+    fetched_at: u64,
+    /// Set by `expire_event`; the value is kept in case refetching it fails
+    #[serde(default)]
+    expired: bool,
+    value: T
+}
+
+impl<T> Entry<T>
+{
+    fn is_fresh(&self, kind: DataKind, current_time: u64) -> bool
+    {
+        !self.expired && current_time.saturating_sub(self.fetched_at) <= kind.time_to_live()
+    }
+}
+
+pub struct KindStatus
+{
+    pub kind: DataKind,
+    pub entries: usize,
+    pub stale: usize,
+    pub oldest: Option<u64>
+}
+
+/// On-disk store of everything fetched from a data source, keyed by query
+#[derive(Clone)]
+pub struct Cache
+{
+    db: sled::Db
+}
+
+impl Cache
+{
+    /// `vexscout/cache` under the platform's config directory
+    pub fn default_path() -> Option<PathBuf>
+    {
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("cache"))
+    }
+
+    pub fn open(path: &Path) -> Result<Cache, SourceError>
+    {
+        let db = sled::open(path)
+            .map_err(|error| SourceError::Api(format!("could not open cache at {}: {}", path.display(), error)))?;
+        Ok(Cache { db })
+    }
+
+    /// Returns the cached value along with when it was fetched
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<(u64, T)>
+    {
+        self.get_entry(key).map(|entry| (entry.fetched_at, entry.value))
+    }
+
+    fn get_entry<T: DeserializeOwned>(&self, key: &str) -> Option<Entry<T>>
+    {
+        let bytes = self.db.get(key).ok()??;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn put<T: Serialize>(&self, key: &str, value: &T)
+    {
+        let entry = Entry { fetched_at: now(), expired: false, value };
+        if let Ok(bytes) = serde_json::to_vec(&entry)
+        {
+            // a failed write only costs us a refetch later
+            let _ = self.db.insert(key, bytes);
+        }
+    }
+
+    pub fn status(&self) -> Vec<KindStatus>
+    {
+        let current_time = now();
+        let mut statuses = DATA_KINDS.iter()
+            .map(|kind| KindStatus { kind: *kind, entries: 0, stale: 0, oldest: None })
+            .collect::<Vec<KindStatus>>();
+
+        for (key, value) in self.db.iter().flatten()
+        {
+            let key = String::from_utf8_lossy(&key);
+            let kind = key.split('|').nth(1).unwrap_or_default();
+            let status = statuses.iter_mut()
+                .find(|status| status.kind.name() == kind);
+            let entry = serde_json::from_slice::<Entry<serde::de::IgnoredAny>>(&value);
+
+            if let (Some(status), Ok(entry)) = (status, entry)
+            {
+                let age = current_time.saturating_sub(entry.fetched_at);
+                status.entries += 1;
+                if !entry.is_fresh(status.kind, current_time)
+                {
+                    status.stale += 1;
+                }
+                status.oldest = Some(status.oldest.map_or(age, |oldest| oldest.max(age)));
+            }
+        }
+
+        statuses
+    }
+
+    pub fn size_on_disk(&self) -> u64
+    {
+        self.db.size_on_disk().unwrap_or(0)
+    }
+
+    pub fn clear(&self) -> Result<(), SourceError>
+    {
+        self.db.clear().map_err(|error| SourceError::Api(error.to_string()))?;
+        let _ = self.db.flush();
+        Ok(())
+    }
+
+    /// Every distinct value of a query field across the cached keys,
+    /// e.g. each team number for `team`
+    pub fn get_key_values(&self, field: &str) -> Vec<String>
+    {
+        let needle = format!("|{}=", field);
+        let mut values = self.db.iter().keys().flatten()
+            .filter_map(|key|
+            {
+                let key = String::from_utf8_lossy(&key).into_owned();
+                let start = key.find(&needle)? + needle.len();
+                let value = key[start..].split('|').next()?;
+                Some(value.to_owned()).filter(|value| !value.is_empty())
+            })
+            .collect::<Vec<String>>();
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    /// Marks every entry holding the given event as stale, returning how many were marked
+    ///
+    /// Besides the event's own queries this covers team queries with rows from
+    /// the event, e.g. a team's matches. Values are kept, so they are still
+    /// served if refetching them fails
+    pub fn expire_event(&self, sku: &str) -> usize
+    {
+        let needle = format!("|sku={}|", sku.to_ascii_uppercase());
+        let has_event = |row: &serde_json::Value| row.get("sku")
+            .and_then(serde_json::Value::as_str)
+            .is_some_and(|row_sku| row_sku.eq_ignore_ascii_case(sku));
+
+        let expired = self.db.iter().flatten()
+            .filter_map(|(key, bytes)|
+            {
+                let mut entry = serde_json::from_slice::<Entry<serde_json::Value>>(&bytes).ok()?;
+                let holds_event = String::from_utf8_lossy(&key).contains(&needle)
+                    || entry.value.as_array().is_some_and(|rows| rows.iter().any(has_event));
+                if entry.expired || !holds_event
+                {
+                    return None;
+                }
+                entry.expired = true;
+                Some((key, serde_json::to_vec(&entry).ok()?))
+            })
+            .collect::<Vec<(sled::IVec, Vec<u8>)>>();
+
+        for (key, bytes) in &expired
+        {
+            let _ = self.db.insert(key, bytes.as_slice());
+        }
+        expired.len()
+    }
+}
+
+/// Wraps another data source, answering from the cache while entries are fresh
+///
+/// When the wrapped source fails (e.g. the venue Wi-Fi is down), stale entries
+/// are served instead of an error.
+pub struct CachedSource
+{
+    cache: Cache,
+    /// Which source `inner` is, for the cache keys
+    kind: DataSourceKind,
+    inner: Box<dyn DataSource>
+}
+
+impl CachedSource
+{
+    pub fn new(cache: Cache, kind: DataSourceKind, inner: Box<dyn DataSource>) -> CachedSource
+    {
+        CachedSource { cache, kind, inner }
+    }
+
+    fn fetch<T, F>(&self, kind: DataKind, key: &str, fetch: F) -> Result<T, SourceError>
+        where T: Serialize + DeserializeOwned,
+              F: FnOnce(&dyn DataSource) -> Result<T, SourceError>
+    {
+        let cached = match self.cache.get_entry::<T>(key)
+        {
+            Some(entry) if entry.is_fresh(kind, now()) => return Ok(entry.value),
+            cached => cached
+        };
+
+        match fetch(self.inner.as_ref())
+        {
+            Ok(value) => {
+                self.cache.put(key, &value);
+                Ok(value)
+            },
+            Err(error) => {
+                if let Some(entry) = cached
+                {
+                    println!("({}; using {} cached {} ago)", error, kind.name(), format_age(now().saturating_sub(entry.fetched_at)));
+                    Ok(entry.value)
+                }
+                else
+                {
+                    Err(error)
+                }
+            }
+        }
+    }
+}
+
+impl DataSource for CachedSource
+{
+    /// Teams are cached one number at a time so organization lookups can
+    /// reuse entries from single team lookups; missing numbers are fetched together
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        let current_time = now();
+        let mut teams = Vec::new();
+        let mut missing = Vec::new();
+        // entries too old to use unless the wrapped source fails
+        let mut stale = Vec::new();
+
+        for number in numbers
+        {
+            match self.cache.get_entry::<Option<Team>>(&team_key(self.kind, number))
+            {
+                Some(entry) if entry.is_fresh(DataKind::Teams, current_time) => {
+                    teams.extend(entry.value);
+                },
+                cached => {
+                    missing.push(number.clone());
+                    stale.extend(cached);
+                }
+            }
+        }
+
+        if missing.is_empty()
+        {
+            return Ok(teams);
+        }
+
+        match self.inner.get_teams(&missing)
+        {
+            Ok(fetched) => {
+                for number in &missing
+                {
+                    let team = fetched.iter().find(|team| team.number.eq_ignore_ascii_case(number)).cloned();
+                    self.cache.put(&team_key(self.kind, number), &team);
+                }
+                teams.extend(fetched);
+                Ok(teams)
+            },
+            Err(error) => {
+                if stale.len() < missing.len()
+                {
+                    return Err(error);
+                }
+                let oldest = stale.iter().map(|entry| entry.fetched_at).min().unwrap_or(current_time);
+                println!("({}; using {} cached {} ago)", error, DataKind::Teams.name(), format_age(current_time.saturating_sub(oldest)));
+                teams.extend(stale.into_iter().flat_map(|entry| entry.value));
+                Ok(teams)
+            }
+        }
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        self.fetch(DataKind::Events, &query_key(self.kind, DataKind::Events, query), |source| source.get_events(query))
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        self.fetch(DataKind::Matches, &query_key(self.kind, DataKind::Matches, query), |source| source.get_matches(query))
+    }
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        self.fetch(DataKind::Rankings, &query_key(self.kind, DataKind::Rankings, query), |source| source.get_rankings(query))
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        self.fetch(DataKind::Awards, &query_key(self.kind, DataKind::Awards, query), |source| source.get_awards(query))
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        self.fetch(DataKind::Skills, &query_key(self.kind, DataKind::Skills, query), |source| source.get_skills(query))
+    }
+
+    fn get_season_rankings(&self, team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        let key = query_key(self.kind, DataKind::SeasonRankings, Query::team(team));
+        self.fetch(DataKind::SeasonRankings, &key, |source| source.get_season_rankings(team))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::source::FixtureSource;
+    use crate::testing::{self, SKU};
+
+    /// A fixture that can be taken offline, counting what reaches it
+    struct FlakySource
+    {
+        fixture: FixtureSource,
+        online: Rc<Cell<bool>>,
+        calls: Rc<Cell<usize>>
+    }
+
+    impl FlakySource
+    {
+        fn answer<T, F>(&self, fetch: F) -> Result<T, SourceError>
+            where F: FnOnce(&FixtureSource) -> Result<T, SourceError>
+        {
+            self.calls.set(self.calls.get() + 1);
+            if self.online.get() { fetch(&self.fixture) } else { Err(SourceError::Network(String::from("offline"))) }
+        }
+    }
+
+    impl DataSource for FlakySource
+    {
+        fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_teams(numbers))
+        }
+
+        fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_events(query))
+        }
+
+        fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_matches(query))
+        }
+
+        fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_rankings(query))
+        }
+
+        fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_awards(query))
+        }
+
+        fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+        {
+            self.answer(|fixture| fixture.get_skills(query))
+        }
+    }
+
+    fn cached_source() -> (Cache, CachedSource, Rc<Cell<bool>>, Rc<Cell<usize>>)
+    {
+        let cache = Cache { db: sled::Config::new().temporary(true).open().unwrap() };
+        let online = Rc::new(Cell::new(true));
+        let calls = Rc::new(Cell::new(0));
+        let fixture = FixtureSource
+        {
+            teams: vec![testing::team("1A"), testing::team("2A")],
+            matches: vec![
+                testing::qualification(1, &["1A"], &["2A"], Some((10, 5))),
+                testing::qualification(2, &["2A"], &["1A"], None)
+            ],
+            ..FixtureSource::default()
+        };
+        let source = FlakySource { fixture, online: Rc::clone(&online), calls: Rc::clone(&calls) };
+        (cache.clone(), CachedSource::new(cache, DataSourceKind::Fixture, Box::new(source)), online, calls)
+    }
+
+    /// Stores `value` as if it had been fetched `age` seconds ago
+    fn put_aged<T: Serialize>(cache: &Cache, key: &str, value: &T, age: u64)
+    {
+        let entry = Entry { fetched_at: now() - age, expired: false, value };
+        cache.db.insert(key, serde_json::to_vec(&entry).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn entries_expire_by_kind()
+    {
+        let (cache, source, _, calls) = cached_source();
+        let hour = 60 * 60;
+        put_aged(&cache, &team_key(DataSourceKind::Fixture, "1A"), &Some(testing::team("1A")), hour);
+        put_aged(&cache, &query_key(DataSourceKind::Fixture, DataKind::Matches, Query::event(SKU)), &Vec::<Match>::new(), hour);
+
+        // an hour old team is still fresh; only the missing one is fetched
+        assert_eq!(source.get_teams(&[String::from("1A"), String::from("2A")]).unwrap().len(), 2);
+        assert_eq!(calls.get(), 1);
+        // an hour old schedule is not
+        assert_eq!(source.get_matches(Query::event(SKU)).unwrap().len(), 2);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(source.get_matches(Query::event(SKU)).unwrap().len(), 2);
+        assert_eq!(calls.get(), 2);
+
+        let matches = cache.status().into_iter().find(|status| status.kind == DataKind::Matches).unwrap();
+        assert_eq!((matches.entries, matches.stale), (1, 0));
+    }
+
+    #[test]
+    fn serves_stale_entries_when_the_source_fails()
+    {
+        let (cache, source, online, _) = cached_source();
+        let day = 24 * 60 * 60;
+        let old_match = testing::qualification(1, &["1A"], &["2A"], None);
+        put_aged(&cache, &query_key(DataSourceKind::Fixture, DataKind::Matches, Query::event(SKU)), &vec![old_match], day);
+        put_aged(&cache, &team_key(DataSourceKind::Fixture, "1A"), &Some(testing::team("1A")), 30 * day);
+
+        online.set(false);
+        let matches = source.get_matches(Query::event(SKU)).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(!matches[0].scored);
+        assert_eq!(source.get_teams(&[String::from("1A")]).unwrap().len(), 1);
+
+        // nothing to fall back to
+        assert!(source.get_matches(Query::team("1A")).is_err());
+        assert!(source.get_teams(&[String::from("1A"), String::from("2A")]).is_err());
+    }
+
+    #[test]
+    fn expired_events_are_kept_until_refetched()
+    {
+        let (cache, source, online, calls) = cached_source();
+        source.get_matches(Query::event(SKU)).unwrap();
+        source.get_matches(Query::team("2A")).unwrap();
+        source.get_teams(&[String::from("1A")]).unwrap();
+        assert_eq!(calls.get(), 3);
+
+        // the team's matches hold the event's rows; the team itself does not
+        assert_eq!(cache.expire_event(&SKU.to_ascii_lowercase()), 2);
+        let matches = cache.status().into_iter().find(|status| status.kind == DataKind::Matches).unwrap();
+        assert_eq!((matches.entries, matches.stale), (2, 2));
+
+        online.set(false);
+        assert_eq!(source.get_matches(Query::event(SKU)).unwrap().len(), 2);
+        assert_eq!(source.get_matches(Query::team("2A")).unwrap().len(), 2);
+        assert_eq!(calls.get(), 5);
+
+        online.set(true);
+        source.get_matches(Query::event(SKU)).unwrap();
+        source.get_matches(Query::event(SKU)).unwrap();
+        assert_eq!(calls.get(), 6);
+    }
+}
@@ -0,0 +1,1592 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use lazy_static::{lazy_static};
+
+use crate::match_list::MatchSort;
+use crate::model::Round;
+use crate::picklist::Metric;
+use crate::rating::RatingModel;
+use crate::repl::ReplConfiguration;
+use crate::stats::PowerStat;
+
+pub fn team_name_is_valid(name: &str) -> bool
+{
+    lazy_static! {
+        static ref REGEX_MATCHER: Regex = Regex::new(r"^[0-9]+[a-zA-Z]$").unwrap();
+    }
+    REGEX_MATCHER.is_match(name)
+}
+
+fn sku_is_valid(sku: &str) -> bool
+{
+    lazy_static! {
+        static ref REGEX_MATCHER: Regex = Regex::new(r"^[a-zA-Z]+-[a-zA-Z0-9-]+$").unwrap();
+    }
+    REGEX_MATCHER.is_match(sku)
+}
+
+/// Splits input into words; single or double quotes at the start of a word keep spaces in it
+pub fn tokenize(input: &str) -> Result<Vec<String>, ParseError>
+{
+    let mut tokens = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for character in input.chars()
+    {
+        match quote
+        {
+            Some(open) if character == open => quote = None,
+            Some(_) => current.get_or_insert_with(String::new).push(character),
+            None if (character == '"' || character == '\'') && current.is_none() => {
+                quote = Some(character);
+                current = Some(String::new());
+            },
+            None if character.is_whitespace() => tokens.extend(current.take()),
+            None => current.get_or_insert_with(String::new).push(character)
+        }
+    }
+
+    if quote.is_some()
+    {
+        return Err(ParseError::UnterminatedQuote);
+    }
+    tokens.extend(current);
+    Ok(tokens)
+}
+
+/// What a value has to look like; checked before a command runs
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArgumentKind
+{
+    Word,
+    Team,
+    TeamOrOrganization,
+    Sku,
+    Number,
+    Decimal,
+    Model,
+    Stat,
+    Round,
+    Metric,
+    Sort,
+    /// Every remaining word, e.g. a state name
+    Text,
+    /// Every remaining word, a division of the competition
+    Division,
+    /// A key of the configuration file
+    ConfigKey,
+    /// Contexts separated by slashes, e.g. `/comp/RE-VRC-23-1234/team/1234A`
+    Path,
+    /// Every remaining word, each a team name
+    Teams,
+    /// Every remaining word, run as another command
+    Command
+}
+
+impl ArgumentKind
+{
+    pub fn describe(&self) -> &'static str
+    {
+        match self
+        {
+            ArgumentKind::Word => "a word",
+            ArgumentKind::Team => "a team name like 1234A",
+            ArgumentKind::TeamOrOrganization => "a team name like 1234A or an organization like 1234",
+            ArgumentKind::Sku => "a competition sku like RE-VRC-23-1234",
+            ArgumentKind::Number => "a whole number",
+            ArgumentKind::Decimal => "a number",
+            ArgumentKind::Model => "opr, elo or trueskill",
+            ArgumentKind::Stat => "opr, dpr or ccwm",
+            ArgumentKind::Round => "qual, r16, qf, sf or f",
+            ArgumentKind::Metric => "opr, auton, skills, scouting or complement",
+            ArgumentKind::Sort => "schedule, time, score or margin",
+            ArgumentKind::Text => "some text",
+            ArgumentKind::Division => "a division name or the start of one",
+            ArgumentKind::ConfigKey => "a configuration key",
+            ArgumentKind::Path => "a context path like /comp/RE-VRC-23-1234/team/1234A",
+            ArgumentKind::Teams => "team names like 1234A",
+            ArgumentKind::Command => "a command"
+        }
+    }
+
+    /// Whether one word is a valid value
+    pub fn accepts(&self, word: &str) -> bool
+    {
+        match self
+        {
+            ArgumentKind::Word | ArgumentKind::Text | ArgumentKind::Division | ArgumentKind::Path | ArgumentKind::Command => true,
+            ArgumentKind::Team | ArgumentKind::Teams => team_name_is_valid(word),
+            ArgumentKind::TeamOrOrganization => {
+                team_name_is_valid(word) || (!word.is_empty() && word.chars().all(|character| character.is_ascii_digit()))
+            },
+            ArgumentKind::Sku => sku_is_valid(word),
+            ArgumentKind::Number => word.parse::<u64>().is_ok(),
+            ArgumentKind::Decimal => word.parse::<f64>().is_ok_and(f64::is_finite),
+            ArgumentKind::Model => RatingModel::parse(word).is_some(),
+            ArgumentKind::Stat => PowerStat::parse(word).is_some(),
+            ArgumentKind::Round => Round::parse(word).is_some(),
+            ArgumentKind::Metric => Metric::parse(word).is_some(),
+            ArgumentKind::Sort => MatchSort::parse(word).is_some(),
+            ArgumentKind::ConfigKey => ReplConfiguration::get_keys().iter().any(|key| key.eq_ignore_ascii_case(word))
+        }
+    }
+
+    /// Every value of a kind with a fixed set of them, offered by tab completion
+    pub fn get_values(&self) -> &'static [&'static str]
+    {
+        match self
+        {
+            ArgumentKind::Model => &["opr", "elo", "trueskill"],
+            ArgumentKind::Stat => &["opr", "dpr", "ccwm"],
+            ArgumentKind::Round => &["qual", "r16", "qf", "sf", "f"],
+            ArgumentKind::Metric => &["opr", "auton", "skills", "scouting", "complement"],
+            ArgumentKind::Sort => &["schedule", "time", "score", "margin"],
+            _ => &[]
+        }
+    }
+
+    /// Whether the argument takes every remaining word
+    fn is_variadic(&self) -> bool
+    {
+        matches!(self, ArgumentKind::Text | ArgumentKind::Division | ArgumentKind::Teams | ArgumentKind::Command)
+    }
+}
+
+/// A positional argument, or a keyword argument given as `<name> <value>` anywhere after the command
+pub struct Argument
+{
+    pub name: &'static str,
+    pub kind: ArgumentKind,
+    pub required: bool
+}
+
+const fn required(name: &'static str, kind: ArgumentKind) -> Argument
+{
+    Argument { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: ArgumentKind) -> Argument
+{
+    Argument { name, kind, required: false }
+}
+
+impl fmt::Display for Argument
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let ellipsis = if self.kind.is_variadic() { "..." } else { "" };
+        if self.required
+        {
+            write!(f, "<{}{}>", self.name, ellipsis)
+        }
+        else
+        {
+            write!(f, "({}{})", self.name, ellipsis)
+        }
+    }
+}
+
+/// Everything a command can do; `ReplInterface` runs each one
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action
+{
+    Help,
+    Exit,
+    Global,
+    ChangeContext,
+    Up,
+    Top,
+    PrintContexts,
+    Back,
+    At,
+    Config,
+    ConfigGet,
+    CacheStatus,
+    CacheClear,
+    CacheRefresh,
+    OfflinePrepare,
+    OfflineList,
+    Competition,
+    Stats,
+    Calibrate,
+    Team,
+    TeamInfo,
+    TeamSkills,
+    TeamStats,
+    TeamHistory,
+    Graph,
+    TeamCompetitionRankings,
+    SeasonRankings,
+    TeamRating,
+    TeamEvents,
+    TeamMatches,
+    TeamAwards,
+    TeamSkillRuns,
+    TeamEventMatches,
+    OrganizationList,
+    OrganizationCompetition,
+    RegionRatings,
+    RegionSkills,
+    PowerRatings,
+    CompetitionTeam,
+    MatchLoad,
+    MatchNext,
+    MatchPrev,
+    MatchLookup,
+    Rankings,
+    ComputedRankings,
+    RankingsDiff,
+    CompetitionStats,
+    ScheduleStrength,
+    Simulate,
+    Bracket,
+    PickList,
+    Division,
+    Round,
+    Divisions,
+    Awards,
+    Wait,
+    MatchTeams,
+    Predict,
+    MatchHistory,
+    Notes,
+    StepNext,
+    StepPrev,
+    Filters,
+    FilterTeam,
+    FilterRound,
+    FilterUnscored,
+    FilterScored,
+    FilterClear,
+    Sort,
+    Show,
+    Select,
+    Candidates,
+    BestCandidates,
+    SetPickStatus,
+    Rate,
+    Weights,
+    Weight,
+    SavePickList,
+    LoadPickList,
+    ProjectBracket,
+    Alliances,
+    SetAlliance,
+    RemoveAlliance,
+    ImportBracket,
+    SeriesLength
+}
+
+impl Action
+{
+    /// Whether the action enters a context, so it can be a step of a `cd` path
+    pub fn enters_context(&self) -> bool
+    {
+        matches!(self,
+            Action::Config | Action::Competition | Action::Stats | Action::Team | Action::TeamStats | Action::TeamHistory
+                | Action::CompetitionTeam | Action::MatchLoad | Action::MatchNext | Action::MatchPrev | Action::MatchLookup
+                | Action::Bracket | Action::PickList | Action::Division | Action::Round | Action::Select)
+    }
+
+    /// Whether the action can change the context stack, so `back` has to remember the old one
+    pub fn changes_context(&self) -> bool
+    {
+        self.enters_context() || matches!(self,
+            Action::Exit | Action::Global | Action::ChangeContext | Action::Up | Action::Top | Action::Back
+                | Action::StepNext | Action::StepPrev)
+    }
+}
+
+/// One node of the command tree
+///
+/// Letters in parentheses in `name` may be left off, e.g. `comp(etition)`;
+/// any unambiguous prefix of the full name works as well
+pub struct Command
+{
+    name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// One line for `help`
+    pub summary: &'static str,
+    /// Sample inputs shown by `help <command>`
+    pub examples: &'static [&'static str],
+    /// `None` when a subcommand has to be given
+    pub action: Option<Action>,
+    pub arguments: &'static [Argument],
+    pub keywords: &'static [Argument],
+    pub subcommands: &'static [Command],
+    /// Subcommand used when none is given, e.g. `match` means `match next`
+    pub default_subcommand: Option<&'static str>
+}
+
+const fn command(name: &'static str, action: Action, summary: &'static str) -> Command
+{
+    Command
+    {
+        name,
+        aliases: &[],
+        summary,
+        examples: &[],
+        action: Some(action),
+        arguments: &[],
+        keywords: &[],
+        subcommands: &[],
+        default_subcommand: None
+    }
+}
+
+const fn group(name: &'static str, summary: &'static str, subcommands: &'static [Command]) -> Command
+{
+    Command
+    {
+        name,
+        aliases: &[],
+        summary,
+        examples: &[],
+        action: None,
+        arguments: &[],
+        keywords: &[],
+        subcommands,
+        default_subcommand: None
+    }
+}
+
+impl Command
+{
+    /// The name with its optional letters, e.g. `competition`
+    pub fn get_name(&self) -> String
+    {
+        self.name.replace(['(', ')'], "")
+    }
+
+    /// The shortest spelling, e.g. `comp`
+    fn get_short_name(&self) -> &'static str
+    {
+        self.name.split('(').next().unwrap_or(self.name)
+    }
+
+    /// Exact name, alias, or the name with some of its optional letters
+    fn is_named(&self, word: &str) -> bool
+    {
+        let word = word.to_ascii_lowercase();
+        let name = self.get_name();
+        self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(&word))
+            || (word.starts_with(self.get_short_name()) && name.starts_with(&word))
+    }
+
+    fn has_prefix(&self, word: &str) -> bool
+    {
+        !word.is_empty() && self.get_name().starts_with(&word.to_ascii_lowercase())
+    }
+
+    /// Usage line, e.g. `simulate ||runs|| <n> ||seed|| <n> ||model|| <model>`
+    pub fn get_usage(&self) -> String
+    {
+        let mut usage = vec![self.name.to_owned()];
+        if !self.subcommands.is_empty()
+        {
+            let names = self.subcommands.iter().map(|subcommand| subcommand.name).collect::<Vec<&str>>().join("|");
+            usage.push(if self.action.is_some() { format!("({})", names) } else { names });
+        }
+        usage.extend(self.arguments.iter().map(|argument| argument.to_string()));
+        usage.extend(self.keywords.iter().map(|keyword| format!("||{}|| <{}>", keyword.name, keyword.name)));
+        usage.join(" ")
+    }
+}
+
+const MODEL: Argument = optional("model", ArgumentKind::Model);
+
+const MATCH: Command = Command
+{
+    subcommands: &[
+        command("next", Action::MatchNext, "The next unplayed match"),
+        command("prev", Action::MatchPrev, "The last scored match"),
+        command("load", Action::MatchLoad, "Every match, to filter, sort and page through"),
+        Command
+        {
+            arguments: &[required("number", ArgumentKind::Number)],
+            keywords: &[optional("round", ArgumentKind::Round), optional("series", ArgumentKind::Number)],
+            examples: &["match lookup 12", "match lookup 2 round qf series 3"],
+            ..command("lookup", Action::MatchLookup, "The nth match of the selected round, or of qualifications")
+        }
+    ],
+    default_subcommand: Some("next"),
+    examples: &["match", "match load"],
+    ..group("match", "Open a match; next when no subcommand is given", &[])
+};
+
+const COMPETITION_STATS: Command = Command
+{
+    arguments: &[optional("stat", ArgumentKind::Stat)],
+    subcommands: &[
+        Command
+        {
+            arguments: &[MODEL],
+            examples: &["stats schedule elo"],
+            ..command("schedule", Action::ScheduleStrength, "Strength of schedule, expected against actual wins and luck")
+        }
+    ],
+    examples: &["stats", "stats ccwm"],
+    ..command("stats", Action::CompetitionStats, "Power ratings from scored qualifications")
+};
+
+const SIMULATE: Command = Command
+{
+    keywords: &[optional("runs", ArgumentKind::Number), optional("seed", ArgumentKind::Number), MODEL],
+    examples: &["simulate", "simulate runs 500 seed 3 model elo"],
+    ..command("simulate", Action::Simulate, "Play out the remaining qualifications for likely final ranks")
+};
+
+const REGION: Argument = required("state", ArgumentKind::Text);
+const SPOTS: Argument = optional("spots", ArgumentKind::Number);
+
+static GLOBAL: &[Command] = &[
+    Command
+    {
+        aliases: &["?"],
+        arguments: &[optional("command", ArgumentKind::Command)],
+        examples: &["help", "help simulate", "? stats schedule"],
+        ..command("help", Action::Help, "List the commands here, or explain one")
+    },
+    command("exit", Action::Exit, "Leave the current context"),
+    Command
+    {
+        arguments: &[required("command", ArgumentKind::Command)],
+        examples: &["global stats TX elo", "global comp RE-VRC-23-1234"],
+        ..command("global", Action::Global, "Run a command from the top; a context it enters replaces the current ones")
+    },
+    Command
+    {
+        arguments: &[optional("path", ArgumentKind::Path)],
+        examples: &["cd /comp/RE-VRC-23-1234/team/1234A", "cd ../division/Science", "cd"],
+        ..command("cd", Action::ChangeContext, "Go to a context path; .. leaves a context and no path goes to the top")
+    },
+    Command
+    {
+        arguments: &[optional("count", ArgumentKind::Number)],
+        examples: &["up", "up 2"],
+        ..command("up", Action::Up, "Leave one context, or several")
+    },
+    command("top", Action::Top, "Leave every context"),
+    command("pwd", Action::PrintContexts, "The context stack as a path"),
+    command("back", Action::Back, "Return to the contexts before the last change"),
+    Command
+    {
+        arguments: &[required("path", ArgumentKind::Path), required("command", ArgumentKind::Command)],
+        examples: &["at /comp/RE-VRC-23-1234 rankings", "at .. stats"],
+        ..command("at", Action::At, "Run a command in another context without leaving this one")
+    }
+];
+
+static BASE: &[Command] = &[
+    command("config", Action::Config, "Enter the configuration"),
+    group("cache", "Inspect or clear cached data", &[
+        command("status", Action::CacheStatus, "What is cached and how old it is"),
+        command("clear", Action::CacheClear, "Drop everything cached"),
+        Command
+        {
+            arguments: &[required("sku", ArgumentKind::Sku)],
+            ..command("refresh", Action::CacheRefresh, "Refetch everything cached for a competition, keeping it if the fetch fails")
+        }
+    ]),
+    group("offline", "Event packs for use without a connection", &[
+        Command
+        {
+            arguments: &[required("sku", ArgumentKind::Sku)],
+            examples: &["offline prepare RE-VRC-23-1234"],
+            ..command("prepare", Action::OfflinePrepare, "Save every team, match and ranking of a competition to an event pack")
+        },
+        command("list", Action::OfflineList, "The saved event packs")
+    ]),
+    Command
+    {
+        arguments: &[required("sku", ArgumentKind::Sku)],
+        examples: &["comp RE-VRC-23-1234"],
+        ..command("comp(etition)", Action::Competition, "Enter a competition")
+    },
+    Command
+    {
+        arguments: &[optional("target", ArgumentKind::Text)],
+        keywords: &[SPOTS],
+        subcommands: &[
+            Command
+            {
+                arguments: &[required("sku", ArgumentKind::Sku), MODEL],
+                examples: &["stats calibrate RE-VRC-23-1234 elo"],
+                ..command("calibrate", Action::Calibrate, "Replay a competition to score each model's predictions")
+            }
+        ],
+        examples: &["stats", "stats 1234A", "stats TX elo", "stats \"New Mexico\" skills spots 3"],
+        ..command("stats", Action::Stats, "Enter stats, for a team or a state's leaderboards when one is given")
+    },
+    Command
+    {
+        arguments: &[required("name", ArgumentKind::TeamOrOrganization), optional("command", ArgumentKind::Command)],
+        examples: &["team 1234A", "team 1234", "team 1234A stats elo"],
+        ..command("team", Action::Team, "Enter a team, or an organization, and run a command there")
+    }
+];
+
+static TEAM: &[Command] = &[
+    command("info", Action::TeamInfo, "Name, organization and location"),
+    command("skills", Action::TeamSkills, "Best skills scores this season and the runs at each event"),
+    Command
+    {
+        arguments: &[optional("command", ArgumentKind::Command)],
+        examples: &["stats", "stats elo"],
+        ..command("stats", Action::TeamStats, "Enter the team's stats, and run a command there")
+    },
+    command("history", Action::TeamHistory, "Enter the team's history")
+];
+
+static TEAM_STATS: &[Command] = &[
+    command("graph", Action::Graph, "Graph the team's stats"),
+    command("competition", Action::TeamCompetitionRankings, "The team's rank at each competition"),
+    command("season", Action::SeasonRankings, "The team's season rankings"),
+    command("elo", Action::TeamRating, "Season Elo and how it moved at each event"),
+    Command
+    {
+        aliases: &["bayes", "bayesian"],
+        ..command("trueskill", Action::TeamRating, "Season skill estimate with a 95% interval")
+    }
+];
+
+static TEAM_HISTORY: &[Command] = &[
+    command("events", Action::TeamEvents, "Every event the team attended"),
+    command("matches", Action::TeamMatches, "Every match the team played"),
+    command("awards", Action::TeamAwards, "Every award the team won"),
+    command("skills", Action::TeamSkillRuns, "Every skills run")
+];
+
+static ORGANIZATION: &[Command] = &[
+    command("list", Action::OrganizationList, "Every team in the organization"),
+    command("graph", Action::Graph, "Graph the organization's stats"),
+    command("competition", Action::OrganizationCompetition, "The organization's competition stats")
+];
+
+static STATS: &[Command] = &[
+    Command
+    {
+        arguments: &[required("sku", ArgumentKind::Sku), MODEL],
+        examples: &["calibrate RE-VRC-23-1234"],
+        ..command("calibrate", Action::Calibrate, "Replay a competition to score each model's predictions")
+    },
+    Command
+    {
+        arguments: &[REGION],
+        examples: &["elo TX"],
+        ..command("elo", Action::RegionRatings, "Season Elo leaderboard for teams at the state's events")
+    },
+    Command
+    {
+        aliases: &["bayes", "bayesian"],
+        arguments: &[REGION],
+        ..command("trueskill", Action::RegionRatings, "Season skill leaderboard, ranked by mean - 3 deviations")
+    },
+    Command
+    {
+        arguments: &[REGION],
+        keywords: &[SPOTS],
+        examples: &["skills TX", "skills \"New Mexico\" spots 3"],
+        ..command("skills", Action::RegionSkills, "Season skills leaderboard with the World Championship cutoff")
+    },
+    Command
+    {
+        arguments: &[required("sku", ArgumentKind::Sku)],
+        ..command("opr", Action::PowerRatings, "Offensive power rating leaderboard for a competition")
+    },
+    Command
+    {
+        arguments: &[required("sku", ArgumentKind::Sku)],
+        ..command("dpr", Action::PowerRatings, "Defensive power rating leaderboard for a competition")
+    },
+    Command
+    {
+        arguments: &[required("sku", ArgumentKind::Sku)],
+        ..command("ccwm", Action::PowerRatings, "Winning margin contribution leaderboard for a competition")
+    },
+    SEASON_RANKINGS
+];
+
+/// `stats> 1234A` shows the team's season rankings
+const SEASON_RANKINGS: Command = Command
+{
+    arguments: &[required("team", ArgumentKind::Team)],
+    examples: &["1234A"],
+    ..command("season", Action::SeasonRankings, "A team's season rankings; the team name alone works too")
+};
+
+static COMPETITION: &[Command] = &[
+    Command
+    {
+        arguments: &[required("team", ArgumentKind::Team)],
+        examples: &["team 1234A"],
+        ..command("team", Action::CompetitionTeam, "Narrow the competition to one team")
+    },
+    MATCH,
+    Command
+    {
+        subcommands: &[
+            command("computed", Action::ComputedRankings, "Rank teams from scored qualifications under the ruleset"),
+            command("diff", Action::RankingsDiff, "Where computed and official rankings disagree")
+        ],
+        ..command("rankings", Action::Rankings, "Official qualification rankings")
+    },
+    COMPETITION_STATS,
+    SIMULATE,
+    command("bracket", Action::Bracket, "Enter the elimination bracket"),
+    command("picklist", Action::PickList, "Enter the alliance selection pick list"),
+    Command
+    {
+        arguments: &[required("name", ArgumentKind::Division)],
+        examples: &["division Science"],
+        ..command("division", Action::Division, "Narrow the competition to a division, by name or prefix")
+    },
+    Command
+    {
+        arguments: &[required("round", ArgumentKind::Round)],
+        examples: &["round qf"],
+        ..command("round", Action::Round, "Narrow the competition to one round")
+    },
+    command("divisions", Action::Divisions, "The competition's divisions"),
+    command("awards", Action::Awards, "The competition's awards"),
+    command("wait", Action::Wait, "Wait for the next match")
+];
+
+static COMPETITION_TEAM: &[Command] = &[
+    MATCH,
+    SIMULATE,
+    COMPETITION_STATS,
+    command("matches", Action::TeamEventMatches, "The team's matches at the competition")
+];
+
+static MATCH_CONTEXT: &[Command] = &[
+    command("teams", Action::MatchTeams, "Both alliances with rank, record and OPR"),
+    Command
+    {
+        arguments: &[MODEL],
+        examples: &["predict", "predict trueskill"],
+        ..command("predict", Action::Predict, "Expected scores and win probability from earlier matches only")
+    },
+    command("history", Action::MatchHistory, "Each team's earlier results at the competition"),
+    Command
+    {
+        arguments: &[optional("team", ArgumentKind::Team), optional("note", ArgumentKind::Text)],
+        examples: &["notes", "notes 1234A \"fast intake, weak auton\""],
+        ..command("notes", Action::Notes, "Show the notes for the match's teams, or add one")
+    },
+    command("next", Action::StepNext, "Step to the next match in the schedule"),
+    command("prev", Action::StepPrev, "Step to the previous match in the schedule")
+];
+
+static MATCH_LIST: &[Command] = &[
+    Command
+    {
+        subcommands: &[
+            Command
+            {
+                arguments: &[required("team", ArgumentKind::Team)],
+                ..command("team", Action::FilterTeam, "Only matches with a team")
+            },
+            Command
+            {
+                arguments: &[required("round", ArgumentKind::Round)],
+                ..command("round", Action::FilterRound, "Only matches in a round")
+            },
+            command("unscored", Action::FilterUnscored, "Only matches not yet scored"),
+            command("scored", Action::FilterScored, "Only scored matches"),
+            command("clear", Action::FilterClear, "Drop every filter")
+        ],
+        examples: &["filter", "filter team 1234A", "filter round qf"],
+        ..command("filter", Action::Filters, "Show the filters, or add one")
+    },
+    Command
+    {
+        arguments: &[required("order", ArgumentKind::Sort)],
+        subcommands: &[
+            Command { arguments: &[required("order", ArgumentKind::Sort)], ..command("by", Action::Sort, "Order the matches") }
+        ],
+        examples: &["sort by margin", "sort time"],
+        ..command("sort", Action::Sort, "Order the matches")
+    },
+    command("show", Action::Show, "The next page of matches"),
+    Command
+    {
+        arguments: &[required("row", ArgumentKind::Number)],
+        examples: &["select 3"],
+        ..command("select", Action::Select, "Open the match in a row")
+    }
+];
+
+static PICK_LIST: &[Command] = &[
+    Command { aliases: &["list"], ..command("show", Action::Candidates, "Every other team, best score first") },
+    Command
+    {
+        arguments: &[optional("count", ArgumentKind::Number)],
+        examples: &["best", "best 5"],
+        ..command("best", Action::BestCandidates, "The best teams still available, 3 unless a count is given")
+    },
+    Command { arguments: &[required("team", ArgumentKind::Team)], ..command("pick", Action::SetPickStatus, "Mark a team as picked") },
+    Command { arguments: &[required("team", ArgumentKind::Team)], ..command("decline", Action::SetPickStatus, "Mark a team as having declined") },
+    Command { arguments: &[required("team", ArgumentKind::Team)], ..command("undo", Action::SetPickStatus, "Make a team available again") },
+    Command
+    {
+        arguments: &[required("team", ArgumentKind::Team), required("rating", ArgumentKind::Decimal)],
+        examples: &["rate 1234A 8.5"],
+        ..command("rate", Action::Rate, "Give a team a scouting rating")
+    },
+    command("weights", Action::Weights, "The weight of each metric in the score"),
+    Command
+    {
+        arguments: &[required("metric", ArgumentKind::Metric), required("weight", ArgumentKind::Decimal)],
+        examples: &["weight complement 0.5"],
+        ..command("weight", Action::Weight, "Weigh a metric; metrics are in standard deviations from the field")
+    },
+    Command
+    {
+        arguments: &[required("path", ArgumentKind::Word)],
+        examples: &["save picks.json"],
+        ..command("save", Action::SavePickList, "Write the pick list to a file")
+    },
+    Command
+    {
+        arguments: &[optional("path", ArgumentKind::Word)],
+        examples: &["load", "load picks.json"],
+        ..command("load", Action::LoadPickList, "Read a pick list from a file, or reread the saved one")
+    }
+];
+
+static BRACKET: &[Command] = &[
+    Command
+    {
+        arguments: &[MODEL],
+        examples: &["show", "show elo"],
+        ..command("show", Action::ProjectBracket, "Each series with its predicted winner, then every alliance's title odds")
+    },
+    command("alliances", Action::Alliances, "The alliances by seed"),
+    Command
+    {
+        arguments: &[required("seed", ArgumentKind::Number), required("teams", ArgumentKind::Teams)],
+        examples: &["alliance 1 1234A 5678B"],
+        ..command("alliance", Action::SetAlliance, "Set the teams of a seed")
+    },
+    Command
+    {
+        arguments: &[required("seed", ArgumentKind::Number)],
+        ..command("remove", Action::RemoveAlliance, "Remove the alliance of a seed")
+    },
+    Command
+    {
+        arguments: &[optional("path", ArgumentKind::Word)],
+        examples: &["import", "import alliances.json"],
+        ..command("import", Action::ImportBracket, "Alliances from the elimination matches, or a JSON file")
+    },
+    Command
+    {
+        arguments: &[required("length", ArgumentKind::Number)],
+        examples: &["series 3"],
+        ..command("series", Action::SeriesLength, "Matches per series")
+    }
+];
+
+static CONFIG: &[Command] = &[
+    Command
+    {
+        arguments: &[required("key", ArgumentKind::ConfigKey)],
+        examples: &["get ruleset"],
+        ..command("get", Action::ConfigGet, "The value of a configuration key")
+    }
+];
+
+/// The commands a context stack accepts, chosen by `ReplInterface`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CommandSet
+{
+    Base,
+    Config,
+    Team,
+    TeamStats,
+    TeamHistory,
+    Organization,
+    /// Stats and history inside an organization have no commands yet
+    OrganizationSubcontext,
+    Stats,
+    Competition,
+    CompetitionTeam,
+    Match,
+    MatchList,
+    PickList,
+    Bracket
+}
+
+impl CommandSet
+{
+    fn get_own_commands(&self) -> &'static [Command]
+    {
+        match self
+        {
+            CommandSet::Base => BASE,
+            CommandSet::Config => CONFIG,
+            CommandSet::OrganizationSubcontext => &[],
+            CommandSet::Team => TEAM,
+            CommandSet::TeamStats => TEAM_STATS,
+            CommandSet::TeamHistory => TEAM_HISTORY,
+            CommandSet::Organization => ORGANIZATION,
+            CommandSet::Stats => STATS,
+            CommandSet::Competition => COMPETITION,
+            CommandSet::CompetitionTeam => COMPETITION_TEAM,
+            CommandSet::Match => MATCH_CONTEXT,
+            CommandSet::MatchList => MATCH_LIST,
+            CommandSet::PickList => PICK_LIST,
+            CommandSet::Bracket => BRACKET
+        }
+    }
+
+    /// Every command valid in the set, including `exit` and `global`
+    pub fn get_commands(&self) -> Vec<&'static Command>
+    {
+        self.get_own_commands().iter().chain(GLOBAL).collect()
+    }
+
+    /// Parsed when the first word is not a command
+    fn get_fallback(&self) -> Option<&'static Command>
+    {
+        match self
+        {
+            CommandSet::Stats => self.get_own_commands().iter().find(|command| command.action == Some(Action::SeasonRankings)),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError
+{
+    UnterminatedQuote,
+    UnknownCommand { word: String, suggestion: Option<String> },
+    UnknownSubcommand { command: String, word: String, suggestion: Option<String>, expected: Vec<&'static str> },
+    Ambiguous { word: String, candidates: Vec<String> },
+    MissingSubcommand { command: String, expected: Vec<&'static str> },
+    MissingArgument { command: String, argument: &'static str, kind: ArgumentKind, usage: String },
+    InvalidArgument { argument: &'static str, value: String, kind: ArgumentKind, usage: String },
+    UnexpectedArgument { command: String, word: String, usage: String }
+}
+
+impl fmt::Display for ParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            ParseError::UnterminatedQuote => write!(f, "Unterminated quote"),
+            ParseError::UnknownCommand { word, .. } => write!(f, "Unknown command: {}", word),
+            ParseError::UnknownSubcommand { command, word, .. } => write!(f, "Unknown subcommand of {}: {}", command, word),
+            ParseError::Ambiguous { word, candidates } => write!(f, "{} is ambiguous: {}", word, candidates.join(", ")),
+            ParseError::MissingSubcommand { command, expected } => write!(f, "{} needs a subcommand: {}", command, expected.join(", ")),
+            ParseError::MissingArgument { command, argument, kind, .. } => write!(f, "{} needs <{}>, {}", command, argument, kind.describe()),
+            ParseError::InvalidArgument { argument, value, kind, .. } => write!(f, "Invalid <{}>: {} (expected {})", argument, value, kind.describe()),
+            ParseError::UnexpectedArgument { command, word, .. } => write!(f, "Unexpected argument for {}: {}", command, word)
+        }
+    }
+}
+
+impl ParseError
+{
+    /// Usage line of the command the error is about
+    pub fn get_usage(&self) -> Option<&str>
+    {
+        match self
+        {
+            ParseError::MissingArgument { usage, .. }
+                | ParseError::InvalidArgument { usage, .. }
+                | ParseError::UnexpectedArgument { usage, .. } => Some(usage),
+            _ => None
+        }
+    }
+}
+
+/// A parsed command with its validated arguments
+pub struct Invocation
+{
+    pub action: Action,
+    command: &'static Command,
+    values: HashMap<&'static str, Vec<String>>
+}
+
+impl Invocation
+{
+    /// The command's full name, e.g. `trueskill` even when `bayes` was typed
+    pub fn get_name(&self) -> String
+    {
+        self.command.get_name()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str>
+    {
+        self.values.get(name).and_then(|words| words.first()).map(String::as_str)
+    }
+
+    /// Every word given to a variadic argument
+    pub fn get_words(&self, name: &str) -> &[String]
+    {
+        self.values.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// A variadic argument's words joined with spaces
+    pub fn get_text(&self, name: &str) -> Option<String>
+    {
+        self.values.get(name).map(|words| words.join(" "))
+    }
+
+    pub fn get_team(&self, name: &str) -> Option<String>
+    {
+        self.get(name).map(str::to_ascii_uppercase)
+    }
+
+    pub fn get_sku(&self, name: &str) -> Option<String>
+    {
+        self.get(name).map(str::to_ascii_uppercase)
+    }
+
+    pub fn get_number<T: FromStr>(&self, name: &str) -> Option<T>
+    {
+        self.get(name).and_then(|value| value.parse().ok())
+    }
+
+    pub fn get_model(&self, name: &str) -> Option<RatingModel>
+    {
+        self.get(name).and_then(RatingModel::parse)
+    }
+
+    pub fn get_stat(&self, name: &str) -> Option<PowerStat>
+    {
+        self.get(name).and_then(PowerStat::parse)
+    }
+
+    pub fn get_round(&self, name: &str) -> Option<Round>
+    {
+        self.get(name).and_then(Round::parse)
+    }
+
+    pub fn get_metric(&self, name: &str) -> Option<Metric>
+    {
+        self.get(name).and_then(Metric::parse)
+    }
+
+    pub fn get_sort(&self, name: &str) -> Option<MatchSort>
+    {
+        self.get(name).and_then(MatchSort::parse)
+    }
+}
+
+/// Finds the command a word names
+///
+/// Exact names and aliases win, then a value the fallback accepts, then
+/// an unambiguous prefix of a full name
+fn find_command(commands: &[&'static Command], word: &str, accepts_value: bool) -> Result<Option<&'static Command>, ParseError>
+{
+    if let Some(command) = commands.iter().find(|command| command.is_named(word))
+    {
+        return Ok(Some(command));
+    }
+    if accepts_value
+    {
+        return Ok(None);
+    }
+
+    let candidates = commands.iter().filter(|command| command.has_prefix(word)).collect::<Vec<&&Command>>();
+    match candidates.as_slice()
+    {
+        [] => Ok(None),
+        [command] => Ok(Some(command)),
+        _ => Err(ParseError::Ambiguous
+        {
+            word: word.to_owned(),
+            candidates: candidates.iter().map(|command| command.get_name()).collect()
+        })
+    }
+}
+
+/// Edit distance where swapping two neighbouring letters counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize
+{
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate()
+    {
+        row[0] = i;
+    }
+
+    for i in 1..=a.len()
+    {
+        for j in 1..=b.len()
+        {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1]
+            {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The closest command to a mistyped word, if any is close enough to be a typo
+fn suggest(commands: &[&'static Command], word: &str) -> Option<String>
+{
+    let lowercase = word.to_ascii_lowercase();
+    let word = lowercase.as_str();
+    let allowed = if word.chars().count() <= 3 { 1 } else { 2 };
+    commands.iter()
+        .flat_map(|command|
+        {
+            let spellings = std::iter::once(command.get_name())
+                .chain(std::iter::once(command.get_short_name().to_owned()))
+                .chain(command.aliases.iter().map(|alias| alias.to_string()));
+            spellings.map(move |spelling| (edit_distance(word, &spelling), command.get_short_name()))
+        })
+        .filter(|(distance, _)| *distance <= allowed)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_owned())
+}
+
+fn check_value(argument: &Argument, value: &str, usage: &str) -> Result<(), ParseError>
+{
+    if argument.kind.accepts(value)
+    {
+        Ok(())
+    }
+    else
+    {
+        Err(ParseError::InvalidArgument { argument: argument.name, value: value.to_owned(), kind: argument.kind, usage: usage.to_owned() })
+    }
+}
+
+/// Parses a tokenized line against the commands of a set
+pub fn parse(set: CommandSet, tokens: &[String]) -> Result<Invocation, ParseError>
+{
+    let first = match tokens.first()
+    {
+        Some(first) => first,
+        None => return Err(ParseError::UnknownCommand { word: String::new(), suggestion: None })
+    };
+
+    let fallback = set.get_fallback();
+    let accepts_value = fallback.and_then(|fallback| fallback.arguments.first()).is_some_and(|argument| argument.kind.accepts(first));
+    let mut command = match (find_command(&set.get_commands(), first, accepts_value)?, fallback)
+    {
+        (Some(command), _) => command,
+        (None, Some(fallback)) if accepts_value => return parse_arguments(fallback, &[], tokens),
+        (None, _) => return Err(ParseError::UnknownCommand { word: first.to_owned(), suggestion: suggest(&set.get_commands(), first) })
+    };
+
+    let mut path = Vec::new();
+    let mut index = 1;
+    while !command.subcommands.is_empty()
+    {
+        let command_name = || path.iter().chain(std::iter::once(&command.get_name())).cloned().collect::<Vec<String>>().join(" ");
+        let expected = || command.subcommands.iter().map(|subcommand| subcommand.name).collect::<Vec<&'static str>>();
+        let subcommands = command.subcommands.iter().collect::<Vec<&'static Command>>();
+        let subcommand = match tokens.get(index)
+        {
+            Some(word) => {
+                let accepts_value = command.arguments.first().is_some_and(|argument| argument.kind.accepts(word))
+                    || command.keywords.iter().any(|keyword| keyword.name.eq_ignore_ascii_case(word));
+                match find_command(&subcommands, word, accepts_value)?
+                {
+                    Some(subcommand) => {
+                        index += 1;
+                        subcommand
+                    },
+                    // a word the command has no argument for can only be a mistyped subcommand
+                    None if command.action.is_some() && (accepts_value || !command.arguments.is_empty()) => break,
+                    None => return Err(ParseError::UnknownSubcommand
+                    {
+                        command: command_name(),
+                        word: word.to_owned(),
+                        suggestion: suggest(&subcommands, word),
+                        expected: expected()
+                    })
+                }
+            },
+            None if command.action.is_some() => break,
+            None => match command.default_subcommand.and_then(|name| subcommands.iter().find(|subcommand| subcommand.name == name))
+            {
+                Some(subcommand) => subcommand,
+                None => return Err(ParseError::MissingSubcommand { command: command_name(), expected: expected() })
+            }
+        };
+        path.push(command.get_name());
+        command = subcommand;
+    }
+
+    parse_arguments(command, &path, &tokens[index..])
+}
+
+/// Splits the words after a command into keyword and positional arguments and checks them
+///
+/// `parents` are the full names of the commands above it, e.g. `cache` for `cache refresh`
+fn parse_arguments(command: &'static Command, parents: &[String], tokens: &[String]) -> Result<Invocation, ParseError>
+{
+    let path = parents.iter().cloned().chain(std::iter::once(command.get_name())).collect::<Vec<String>>().join(" ");
+    let usage = parents.iter().cloned().chain(std::iter::once(command.get_usage())).collect::<Vec<String>>().join(" ");
+    let action = match command.action
+    {
+        Some(action) => action,
+        None => return Err(ParseError::MissingSubcommand { command: path, expected: Vec::new() })
+    };
+
+    let mut values = HashMap::new();
+    let mut positional = Vec::new();
+    let mut words = tokens.iter();
+    while let Some(word) = words.next()
+    {
+        match command.keywords.iter().find(|keyword| keyword.name.eq_ignore_ascii_case(word))
+        {
+            Some(keyword) => {
+                let value = words.next().ok_or_else(|| ParseError::MissingArgument
+                {
+                    command: path.clone(),
+                    argument: keyword.name,
+                    kind: keyword.kind,
+                    usage: usage.clone()
+                })?;
+                check_value(keyword, value, &usage)?;
+                values.insert(keyword.name, vec![value.clone()]);
+            },
+            None => positional.push(word.clone())
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    for argument in command.arguments
+    {
+        let given = if argument.kind.is_variadic()
+        {
+            positional.by_ref().collect::<Vec<String>>()
+        }
+        else
+        {
+            positional.next().into_iter().collect()
+        };
+
+        if given.is_empty()
+        {
+            if argument.required
+            {
+                return Err(ParseError::MissingArgument { command: path, argument: argument.name, kind: argument.kind, usage });
+            }
+            continue;
+        }
+        if argument.kind != ArgumentKind::Command
+        {
+            for value in &given
+            {
+                check_value(argument, value, &usage)?;
+            }
+        }
+        values.insert(argument.name, given);
+    }
+
+    if let Some(word) = positional.next()
+    {
+        return Err(ParseError::UnexpectedArgument { command: path, word, usage });
+    }
+    Ok(Invocation { action, command, values })
+}
+
+/// How many segments of a context path make up the next command along it,
+/// e.g. 2 for `comp/RE-VRC-23-1234/team/1234A`
+///
+/// Those are the command, its subcommands and its required arguments
+pub fn count_path_words(set: CommandSet, segments: &[String]) -> Result<usize, ParseError>
+{
+    let first = match segments.first()
+    {
+        Some(first) => first,
+        None => return Ok(0)
+    };
+
+    let commands = set.get_commands();
+    let mut command = find_command(&commands, first, false)?
+        .ok_or_else(|| ParseError::UnknownCommand { word: first.to_owned(), suggestion: suggest(&commands, first) })?;
+    let mut count = 1;
+    while let Some(word) = segments.get(count)
+    {
+        let subcommands = command.subcommands.iter().collect::<Vec<&'static Command>>();
+        match find_command(&subcommands, word, false)?
+        {
+            Some(subcommand) => {
+                command = subcommand;
+                count += 1;
+            },
+            None => break
+        }
+    }
+    count += command.arguments.iter().filter(|argument| argument.required).count();
+
+    // keywords and their values follow, e.g. `lookup/2/round/qf/series/3`
+    while let Some(word) = segments.get(count)
+    {
+        if !command.keywords.iter().any(|keyword| keyword.name.eq_ignore_ascii_case(word))
+        {
+            break;
+        }
+        count += 2;
+    }
+    Ok(count)
+}
+
+/// Rows of usage and summary, with the summaries lined up
+fn format_commands(commands: &[&Command], parents: &[String]) -> Vec<String>
+{
+    let rows = commands.iter()
+        .map(|command|
+        {
+            let usage = parents.iter().cloned().chain(std::iter::once(command.get_usage())).collect::<Vec<String>>().join(" ");
+            (usage, command.summary)
+        })
+        .collect::<Vec<(String, &str)>>();
+    // a usage longer than the column puts its summary on the next line
+    let width = rows.iter().map(|(usage, _)| usage.len()).filter(|length| *length <= 36).max().unwrap_or(0);
+    rows.into_iter()
+        .map(|(usage, summary)| if usage.len() > width
+        {
+            format!("  {}\n  {:<width$}  {}", usage, "", summary, width = width)
+        }
+        else
+        {
+            format!("  {:<width$}  {}", usage, summary, width = width)
+        })
+        .collect()
+}
+
+/// Everything `help <command>` says about one command
+fn describe_command(command: &Command, parents: &[String]) -> Vec<String>
+{
+    let usage = parents.iter().cloned().chain(std::iter::once(command.get_usage())).collect::<Vec<String>>().join(" ");
+    let mut lines = vec![usage, format!("  {}", command.summary)];
+
+    let mut aliases = command.aliases.to_vec();
+    if command.get_short_name() != command.get_name()
+    {
+        aliases.insert(0, command.get_short_name());
+    }
+    if !aliases.is_empty()
+    {
+        lines.push(format!("  also: {}", aliases.join(", ")));
+    }
+
+    if !command.arguments.is_empty() || !command.keywords.is_empty()
+    {
+        lines.push(String::from("arguments:"));
+        let width = command.arguments.iter().chain(command.keywords).map(|argument| argument.to_string().len()).max().unwrap_or(0);
+        for argument in command.arguments
+        {
+            lines.push(format!("  {:<width$}  {}", argument.to_string(), argument.kind.describe(), width = width));
+        }
+        for keyword in command.keywords
+        {
+            lines.push(format!("  {:<width$}  {}, given as `{} <value>`", keyword.to_string(), keyword.kind.describe(), keyword.name, width = width));
+        }
+    }
+
+    if !command.subcommands.is_empty()
+    {
+        lines.push(String::from("subcommands:"));
+        let parents = parents.iter().cloned().chain(std::iter::once(command.get_name())).collect::<Vec<String>>();
+        lines.extend(format_commands(&command.subcommands.iter().collect::<Vec<&Command>>(), &parents));
+        if let Some(default) = command.default_subcommand
+        {
+            lines.push(format!("  (`{}` alone means `{} {}`)", command.get_name(), command.get_name(), default));
+        }
+    }
+
+    if !command.examples.is_empty()
+    {
+        lines.push(String::from("examples:"));
+        lines.extend(command.examples.iter().map(|example| format!("  {}", example)));
+    }
+    lines
+}
+
+/// `help` text for a set, or for the command `words` name in it
+///
+/// Words past the last command found are left for the command itself,
+/// so `help stats TX` explains `stats`
+pub fn help(set: CommandSet, words: &[String]) -> Result<String, ParseError>
+{
+    let mut commands = set.get_commands();
+    let mut parents: Vec<String> = Vec::new();
+    let mut found: Option<&'static Command> = None;
+    for word in words
+    {
+        let command = match find_command(&commands, word, false)?
+        {
+            Some(command) => command,
+            None => match found
+            {
+                Some(parent) if parent.action.is_some() => break,
+                Some(parent) => return Err(ParseError::UnknownSubcommand
+                {
+                    command: parents.iter().cloned().chain(std::iter::once(parent.get_name())).collect::<Vec<String>>().join(" "),
+                    word: word.to_owned(),
+                    suggestion: suggest(&commands, word),
+                    expected: parent.subcommands.iter().map(|subcommand| subcommand.name).collect()
+                }),
+                None => return Err(ParseError::UnknownCommand { word: word.to_owned(), suggestion: suggest(&commands, word) })
+            }
+        };
+        if let Some(parent) = found
+        {
+            parents.push(parent.get_name());
+        }
+        found = Some(command);
+        if command.subcommands.is_empty()
+        {
+            break;
+        }
+        commands = command.subcommands.iter().collect();
+    }
+
+    let lines = match found
+    {
+        Some(command) => describe_command(command, &parents),
+        None => {
+            let mut lines = vec![String::from("Commands here:")];
+            lines.extend(format_commands(&commands, &[]));
+            lines.push(format!("help <command> for details, e.g. help {}", commands[0].get_short_name()));
+            lines
+        }
+    };
+    Ok(lines.join("\n"))
+}
+
+/// What tab completion can offer after some complete words
+pub struct Completion
+{
+    /// Command, subcommand and keyword names
+    pub words: Vec<String>,
+    /// The argument whose value comes next
+    pub argument: Option<&'static Argument>
+}
+
+/// The set the words of a command-kind argument are parsed against
+fn get_nested_set(action: Action, set: CommandSet) -> CommandSet
+{
+    match action
+    {
+        Action::Global => CommandSet::Base,
+        Action::Team => CommandSet::Team,
+        Action::TeamStats => CommandSet::TeamStats,
+        _ => set
+    }
+}
+
+/// Walks the command tree along `words` to find what can follow them
+pub fn complete(set: CommandSet, words: &[String]) -> Completion
+{
+    let commands = set.get_commands();
+    let first = match words.first()
+    {
+        Some(first) => first,
+        None => return Completion { words: commands.iter().map(|command| command.get_name()).collect(), argument: None }
+    };
+
+    let (mut command, mut rest) = match (find_command(&commands, first, false).ok().flatten(), set.get_fallback())
+    {
+        (Some(command), _) => (command, &words[1..]),
+        (None, Some(fallback)) => (fallback, words),
+        (None, None) => return Completion { words: Vec::new(), argument: None }
+    };
+    while let Some(word) = rest.first()
+    {
+        let subcommands = command.subcommands.iter().collect::<Vec<&'static Command>>();
+        match find_command(&subcommands, word, false).ok().flatten()
+        {
+            Some(subcommand) => {
+                command = subcommand;
+                rest = &rest[1..];
+            },
+            None => break
+        }
+    }
+
+    let mut positional = Vec::new();
+    let mut index = 0;
+    while index < rest.len()
+    {
+        match command.keywords.iter().find(|keyword| keyword.name.eq_ignore_ascii_case(&rest[index]))
+        {
+            Some(keyword) if index + 1 == rest.len() => return Completion { words: Vec::new(), argument: Some(keyword) },
+            Some(_) => index += 2,
+            None => {
+                positional.push(rest[index].clone());
+                index += 1;
+            }
+        }
+    }
+
+    let mut given = positional.len();
+    let mut argument = None;
+    for next in command.arguments
+    {
+        if next.kind == ArgumentKind::Command
+        {
+            let start = positional.len() - given;
+            return complete(get_nested_set(command.action.unwrap_or(Action::Help), set), &positional[start..]);
+        }
+        if next.kind.is_variadic() || given == 0
+        {
+            argument = Some(next);
+            break;
+        }
+        given -= 1;
+    }
+
+    let mut words = command.keywords.iter().map(|keyword| keyword.name.to_owned()).collect::<Vec<String>>();
+    if positional.is_empty()
+    {
+        words.extend(command.subcommands.iter().map(|subcommand| subcommand.get_name()));
+    }
+    Completion { words, argument }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn words(input: &str) -> Vec<String>
+    {
+        tokenize(input).unwrap()
+    }
+
+    fn parse_line(set: CommandSet, input: &str) -> Result<Invocation, ParseError>
+    {
+        parse(set, &words(input))
+    }
+
+    #[test]
+    fn quotes_keep_spaces_in_a_word()
+    {
+        assert_eq!(words("stats \"New Mexico\" skills"), vec!["stats", "New Mexico", "skills"]);
+        assert_eq!(words("notes 1A 'fast intake'  "), vec!["notes", "1A", "fast intake"]);
+        // a quote inside a word is just a letter
+        assert_eq!(words("notes it's fast"), vec!["notes", "it's", "fast"]);
+        assert_eq!(words("save \"\""), vec!["save", ""]);
+        assert!(matches!(tokenize("notes 1A \"fast intake"), Err(ParseError::UnterminatedQuote)));
+    }
+
+    #[test]
+    fn keywords_go_anywhere_after_the_command()
+    {
+        for input in &["simulate runs 500 seed 3 model elo", "simulate model elo seed 3 runs 500"]
+        {
+            let invocation = parse_line(CommandSet::Competition, input).unwrap();
+            assert_eq!(invocation.action, Action::Simulate);
+            assert_eq!(invocation.get_number::<u32>("runs"), Some(500));
+            assert_eq!(invocation.get_number::<u64>("seed"), Some(3));
+            assert!(invocation.get_model("model") == Some(RatingModel::Elo));
+        }
+
+        let invocation = parse_line(CommandSet::Competition, "match lookup round qf 2 series 3").unwrap();
+        assert_eq!(invocation.action, Action::MatchLookup);
+        assert_eq!(invocation.get("number"), Some("2"));
+        assert_eq!(invocation.get_round("round"), Some(Round::QuarterFinal));
+        assert_eq!(invocation.get("series"), Some("3"));
+        assert!(matches!(parse_line(CommandSet::Competition, "simulate runs"), Err(ParseError::MissingArgument { argument: "runs", .. })));
+    }
+
+    #[test]
+    fn prefixes_must_name_one_command()
+    {
+        match parse_line(CommandSet::Base, "co RE-VRC-23-1234")
+        {
+            Err(ParseError::Ambiguous { word, candidates }) => {
+                assert_eq!(word, "co");
+                assert_eq!(candidates, vec!["config", "competition"]);
+            },
+            _ => panic!("co should be ambiguous")
+        }
+        assert_eq!(parse_line(CommandSet::Base, "cach stat").unwrap().action, Action::CacheStatus);
+        assert!(matches!(parse_line(CommandSet::Base, "tema 1234A"), Err(ParseError::UnknownCommand { suggestion: Some(suggestion), .. }) if suggestion == "team"));
+    }
+
+    #[test]
+    fn optional_letters_may_be_left_off()
+    {
+        for name in &["comp", "compet", "competition", "COMP"]
+        {
+            let invocation = parse_line(CommandSet::Base, &format!("{} re-vrc-23-1234", name)).unwrap();
+            assert_eq!(invocation.action, Action::Competition);
+            assert_eq!(invocation.get_name(), "competition");
+            assert_eq!(invocation.get_sku("sku").as_deref(), Some("RE-VRC-23-1234"));
+        }
+        assert!(matches!(parse_line(CommandSet::Base, "competitions RE-VRC-23-1234"), Err(ParseError::UnknownCommand { .. })));
+    }
+
+    #[test]
+    fn text_takes_the_remaining_words()
+    {
+        let invocation = parse_line(CommandSet::Base, "stats New Mexico spots 3").unwrap();
+        assert_eq!(invocation.action, Action::Stats);
+        assert_eq!(invocation.get_text("target").as_deref(), Some("New Mexico"));
+        assert_eq!(invocation.get_words("target").len(), 2);
+        assert_eq!(invocation.get_number::<usize>("spots"), Some(3));
+
+        let invocation = parse_line(CommandSet::Bracket, "alliance 1 1234a 5678B").unwrap();
+        assert_eq!(invocation.get_words("teams"), ["1234a", "5678B"]);
+        assert!(matches!(parse_line(CommandSet::Stats, "elo"), Err(ParseError::MissingArgument { argument: "state", .. })));
+    }
+
+    #[test]
+    fn values_are_checked_by_kind()
+    {
+        let invalid = |set, input| match parse_line(set, input)
+        {
+            Err(ParseError::InvalidArgument { argument, kind, .. }) => Some((argument, kind)),
+            _ => None
+        };
+        assert_eq!(invalid(CommandSet::Base, "comp 1234A"), Some(("sku", ArgumentKind::Sku)));
+        assert_eq!(invalid(CommandSet::Base, "team x9z!!"), Some(("name", ArgumentKind::TeamOrOrganization)));
+        assert_eq!(invalid(CommandSet::Competition, "team 1234A!"), Some(("team", ArgumentKind::Team)));
+        assert_eq!(invalid(CommandSet::Competition, "simulate runs many"), Some(("runs", ArgumentKind::Number)));
+        assert_eq!(invalid(CommandSet::PickList, "rate 1234A NaN"), Some(("rating", ArgumentKind::Decimal)));
+        assert_eq!(invalid(CommandSet::Bracket, "alliance 1 1234A x"), Some(("teams", ArgumentKind::Teams)));
+
+        assert!(ArgumentKind::Team.accepts("1234a"));
+        assert!(!ArgumentKind::Team.accepts("1234"));
+        assert!(ArgumentKind::TeamOrOrganization.accepts("1234"));
+        assert!(matches!(parse_line(CommandSet::MatchList, "select 2 3"), Err(ParseError::UnexpectedArgument { .. })));
+    }
+
+    #[test]
+    fn path_commands_take_their_keywords()
+    {
+        let segments = words("comp RE-VRC-23-1234 team 1234A");
+        assert_eq!(count_path_words(CommandSet::Base, &segments).unwrap(), 2);
+        let segments = words("match lookup 2 round qf series 3 teams");
+        assert_eq!(count_path_words(CommandSet::Competition, &segments).unwrap(), 7);
+    }
+
+    #[test]
+    fn completes_keyword_values()
+    {
+        let completion = complete(CommandSet::Competition, &words("simulate runs"));
+        assert_eq!(completion.argument.map(|argument| argument.kind), Some(ArgumentKind::Number));
+        let completion = complete(CommandSet::Competition, &words("simulate runs 500"));
+        assert!(completion.words.contains(&String::from("seed")));
+        let completion = complete(CommandSet::Base, &words("team 1234A"));
+        assert!(completion.words.contains(&String::from("stats")));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::command::{ArgumentKind, ParseError};
+use crate::source::SourceError;
+
+/// Why a command did not run
+#[derive(Debug)]
+pub enum ReplError
+{
+    /// Input the tokenizer or parser could not make sense of
+    Parse(ParseError),
+    /// `command` is everything typed up to the unknown word
+    UnknownCommand { command: String, suggestion: Option<String>, expected: Vec<&'static str> },
+    InvalidTeamName(String),
+    MissingArgument { message: String, usage: Option<String> },
+    /// Input that parsed but does not fit the data, e.g. an unknown competition
+    InvalidInput(String),
+    /// The data source or the network failed
+    Source(SourceError),
+    /// Offline, and the data is neither cached nor in an event pack
+    CacheMiss(String),
+    /// A notes, pick list or bracket file could not be read or written
+    File(String),
+    /// The command needs a context that is not on the stack
+    NotInContext(&'static str),
+    /// `exit` with no context left to leave
+    Exit
+}
+
+impl ReplError
+{
+    pub fn missing(argument: &str) -> ReplError
+    {
+        ReplError::MissingArgument { message: format!("Please enter <{}>!", argument), usage: None }
+    }
+
+    /// A second line pointing the user somewhere, e.g. did you mean `comp`?
+    pub fn get_hint(&self) -> Option<String>
+    {
+        match self
+        {
+            ReplError::Parse(error) => error.get_usage().map(|usage| format!("usage: {}", usage)),
+            ReplError::UnknownCommand { suggestion: Some(suggestion), .. } => Some(format!("did you mean `{}`?", suggestion)),
+            ReplError::UnknownCommand { expected, .. } if !expected.is_empty() => Some(format!("expected one of: {}", expected.join(", "))),
+            ReplError::InvalidTeamName(_) => Some(String::from("team names are a number and a letter, e.g. 1234A")),
+            ReplError::MissingArgument { usage: Some(usage), .. } => Some(format!("usage: {}", usage)),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ReplError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            ReplError::Parse(error) => {
+                write!(f, "{}", error)?;
+            },
+            ReplError::UnknownCommand { command, .. } => {
+                write!(f, "Unknown command: {}", command)?;
+            },
+            ReplError::InvalidTeamName(name) => {
+                write!(f, "Invalid team name: {}", name)?;
+            },
+            ReplError::MissingArgument { message, .. } | ReplError::InvalidInput(message) | ReplError::File(message) => {
+                write!(f, "{}", message)?;
+            },
+            ReplError::Source(error) => {
+                write!(f, "{}", error)?;
+            },
+            ReplError::CacheMiss(what) => {
+                write!(f, "{} is not cached or in an event pack; run `offline prepare <sku>` while connected", what)?;
+            },
+            ReplError::NotInContext(context) => {
+                write!(f, "Enter a {} first", context)?;
+            },
+            ReplError::Exit => {
+                write!(f, "exit")?;
+            }
+        }
+
+        if let Some(hint) = self.get_hint()
+        {
+            write!(f, "\n  {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<SourceError> for ReplError
+{
+    fn from(error: SourceError) -> ReplError
+    {
+        match error
+        {
+            SourceError::NotAvailableOffline(what) => ReplError::CacheMiss(what),
+            error => ReplError::Source(error)
+        }
+    }
+}
+
+impl From<ParseError> for ReplError
+{
+    fn from(error: ParseError) -> ReplError
+    {
+        match error
+        {
+            ParseError::UnknownCommand { word, suggestion } => {
+                ReplError::UnknownCommand { command: word, suggestion, expected: Vec::new() }
+            },
+            ParseError::UnknownSubcommand { command, word, suggestion, expected } => ReplError::UnknownCommand
+            {
+                suggestion: suggestion.map(|suggestion| format!("{} {}", command, suggestion)),
+                command: format!("{} {}", command, word),
+                expected
+            },
+            ParseError::MissingSubcommand { command, expected } => ReplError::MissingArgument
+            {
+                message: format!("{} needs a subcommand", command),
+                usage: Some(format!("{} {}", command, expected.join("|")))
+            },
+            ParseError::MissingArgument { command, argument, kind, usage } => ReplError::MissingArgument
+            {
+                message: format!("{} needs <{}>, {}", command, argument, kind.describe()),
+                usage: Some(usage)
+            },
+            ParseError::InvalidArgument { value, kind: ArgumentKind::Team, .. }
+                | ParseError::InvalidArgument { value, kind: ArgumentKind::Teams, .. } => ReplError::InvalidTeamName(value),
+            error => ReplError::Parse(error)
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+use crate::cache::Cache;
+use crate::command::{self, ArgumentKind, CommandSet};
+use crate::offline::EventPack;
+use crate::repl::ReplConfiguration;
+
+/// Offers completions for the current context stack; kept up to date by `ReplInterface` before each line
+pub struct ReplHelper
+{
+    pub command_set: CommandSet,
+    pub cache: Option<Cache>,
+    /// The competition `divisions` were fetched for
+    pub division_sku: Option<String>,
+    pub divisions: Vec<String>
+}
+
+impl ReplHelper
+{
+    /// Values for an argument beyond the fixed ones of its kind
+    fn get_values(&self, kind: ArgumentKind) -> Vec<String>
+    {
+        match kind
+        {
+            ArgumentKind::Team | ArgumentKind::Teams | ArgumentKind::TeamOrOrganization => {
+                self.cache.as_ref().map(|cache| cache.get_key_values("team")).unwrap_or_default()
+            },
+            ArgumentKind::Sku => {
+                let mut skus = self.cache.as_ref().map(|cache| cache.get_key_values("sku")).unwrap_or_default();
+                skus.extend(EventPack::default_directory().map(|directory| EventPack::list_skus(&directory)).unwrap_or_default());
+                skus
+            },
+            ArgumentKind::Division => self.divisions.clone(),
+            ArgumentKind::ConfigKey => ReplConfiguration::get_keys(),
+            kind => kind.get_values().iter().map(|value| value.to_string()).collect()
+        }
+    }
+}
+
+impl Completer for ReplHelper
+{
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, position: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)>
+    {
+        let line = &line[..position];
+        let start = line.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+        // a word still inside quotes is left alone
+        let words = match command::tokenize(&line[..start])
+        {
+            Ok(words) => words,
+            Err(_) => return Ok((start, Vec::new()))
+        };
+
+        let partial = line[start..].to_ascii_lowercase();
+        let completion = command::complete(self.command_set, &words);
+        let mut candidates = completion.words;
+        if let Some(argument) = completion.argument
+        {
+            candidates.extend(self.get_values(argument.kind));
+        }
+
+        candidates.retain(|candidate| candidate.to_ascii_lowercase().starts_with(&partial));
+        candidates.sort();
+        candidates.dedup();
+        let pairs = candidates.into_iter()
+            .map(|candidate|
+            {
+                let replacement = if candidate.contains(' ') { format!("\"{}\" ", candidate) } else { format!("{} ", candidate) };
+                Pair { display: candidate, replacement }
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper
+{
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Reads lines with editing, history and tab completion
+///
+/// History is kept in `vexscout/history.txt` under the config directory;
+/// Ctrl-R searches it
+pub struct LineEditor
+{
+    editor: Editor<ReplHelper, DefaultHistory>,
+    history_path: Option<PathBuf>
+}
+
+impl LineEditor
+{
+    /// `None` when input is not a terminal, e.g. a piped script
+    pub fn new(cache: Option<Cache>) -> Option<LineEditor>
+    {
+        if !std::io::stdin().is_terminal()
+        {
+            return None;
+        }
+
+        let config = Config::builder()
+            .auto_add_history(true)
+            .history_ignore_dups(true).ok()?
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config).map_err(|error| println!("{}", error)).ok()?;
+        editor.set_helper(Some(ReplHelper { command_set: CommandSet::Base, cache, division_sku: None, divisions: Vec::new() }));
+
+        let history_path = dirs::config_dir().map(|directory| directory.join("vexscout").join("history.txt"));
+        if let Some(path) = &history_path
+        {
+            // there is no history on the first run
+            let _ = editor.load_history(path);
+        }
+        Some(LineEditor { editor, history_path })
+    }
+
+    pub fn get_helper(&self) -> &ReplHelper
+    {
+        self.editor.helper().expect("line editor is created with a helper")
+    }
+
+    pub fn get_helper_mut(&mut self) -> &mut ReplHelper
+    {
+        self.editor.helper_mut().expect("line editor is created with a helper")
+    }
+
+    /// The next line, or `None` at the end of input; Ctrl-C gives an empty line
+    pub fn read_line(&mut self, prompt: &str) -> Option<String>
+    {
+        match self.editor.readline(prompt)
+        {
+            Ok(line) => {
+                if let Some(path) = &self.history_path
+                {
+                    if let Some(directory) = path.parent()
+                    {
+                        let _ = std::fs::create_dir_all(directory);
+                    }
+                    // losing a history entry is not worth interrupting the user for
+                    let _ = self.editor.append_history(path);
+                }
+                Some(line)
+            },
+            Err(ReadlineError::Interrupted) => Some(String::new()),
+            Err(_) => None
+        }
+    }
+}
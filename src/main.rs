@@ -1,83 +1,233 @@
-/// ( ) in commands denote optional letters
-/// ( ) in other contexts denotes comments
-/// < > denotes an argument
-/// Tabbing denotes subcommands a/o related comments
-/// | (single) at the beginning of a line denotes a group
-///     Groups can be broken
-/// Double tabbing OR
-/// Single tabbed groups
-///     denote subcommands a/o related comments that apply
-///     to the last (latest) group
-/// || (double) surrounding a word denotes a keyword non-positional argument
-///     These must come after the base command, but order does not matter
-/// * at the beginning of a line means that the command creates a subcontext
-///     Exit out from subcontext with 'exit'
-///     Preface input with 'global' to issue commands from the global context
-///         This will usually exit out from subcontext by default
-/// command list
-///     * config;       ConfigContext
-///         set <key> <value>
-///             (throws error if invalid type)
-///         get <key>
-///             (throws error if invalid key)
-///         update
-///             (updates config file if anything changed)
-/// 
-///     * team <team name>  TeamContext | OrganizationContext
-///         ==== OrganizationContext ====
-///            list
-///               (lists all teams in organization)
-///         stats (default [current_team]);     StatsContext
-///             | * <team name>
-///             | * <organization name>
-///                 (loads stats)
-///                     (750B vs 750)
-///             rank
-///                     <team name>
-///         history
-/// 
-///     * comp(etition) <sku>   CompetitionContext
-///         team <team name>
-///     
-///         match (default next);   MatchContext
-///             * load      MatchListContext
-///                 (if team name supplied)
-///                     (load organization's match list)
-///                         (!! CONFIGURATION: organization or team)
-///             | * next
-///             | * prev
-///                 (if team name supplied)
-///                     (return team's last played match)
-///                 (else)
-///                     (return last updated match)
-///             * lookup <n>
-///                 (n would be nth match)
-///         wait
-///     * stats     StatsContext
-///         | * <team name> | <organization name>
-///         | * <competition sku>       
-///         | * <state abbreviation>    StatsListContext
-///     
+//! ```text
+//! ( ) in commands denote optional letters
+//! ( ) in other contexts denotes comments
+//! < > denotes an argument
+//! Tabbing denotes subcommands a/o related comments
+//! | (single) at the beginning of a line denotes a group
+//!     Groups can be broken
+//! Double tabbing OR
+//! Single tabbed groups
+//!     denote subcommands a/o related comments that apply
+//!     to the last (latest) group
+//! || (double) surrounding a word denotes a keyword non-positional argument
+//!     These must come after the base command, but order does not matter
+//!     Give the keyword then its value, e.g. simulate seed 3 runs 100
+//! Any unambiguous prefix of a command also works, e.g. sim for simulate
+//! Quote arguments with spaces, e.g. stats "New Mexico" elo
+//! help (or ?) lists the commands of the current context; help <command> explains one
+//! Tab completes commands, team numbers, skus and division names; Ctrl-R searches
+//!     the history, which is kept in vexscout/history.txt under the config directory
+//! * at the beginning of a line means that the command creates a subcontext
+//!     Exit out from subcontext with 'exit'
+//!     Preface input with 'global' to issue commands from the global context
+//!         A context it enters replaces the current ones; 'back' returns to them
+//!     'cd /comp/<sku>/team/<team name>' goes to a path of contexts; '..' leaves one
+//!     'up <n>' leaves n contexts, 'top' leaves all of them, 'pwd' prints the path
+//!     'at <path> <command>' runs a command in another context without leaving this one
+//! command list
+//!     * config;       ConfigContext
+//!         set <key> <value>
+//!             (throws error if invalid type)
+//!         get <key>
+//!             (throws error if invalid key)
+//!         update
+//!             (updates config file if anything changed)
+//!
+//!     * team <team name>  TeamContext | OrganizationContext
+//!         ==== OrganizationContext ====
+//!            list
+//!               (lists all teams in organization)
+//!         skills
+//!             (best driver, programming and combined scores this season,
+//!              attempts, and the runs at each event)
+//!         stats (default [current_team]);     StatsContext
+//!             | * <team name>
+//!             | * <organization name>
+//!                 (loads stats)
+//!                     (750B vs 750)
+//!                 elo
+//!                     (team's season Elo and how it moved at each event)
+//!                 trueskill
+//!                     (team's season skill estimate with a 95% interval)
+//!             rank
+//!                     <team name>
+//!             opr|dpr|ccwm <sku>
+//!                 (power rating leaderboard for a competition)
+//!             elo|trueskill <state>
+//!                 (season leaderboard for teams at the state's events)
+//!                 (trueskill ranks by mean - 3 deviations)
+//!             skills <state> ||spots|| <n>
+//!                 (season skills leaderboard for the state's teams; marks teams
+//!                  qualified by award and those in line for the n (default 2)
+//!                  World Championship skills spots, with the current cutoff)
+//!         stats <state> elo|trueskill
+//!         stats <state> skills ||spots|| <n>
+//!             (same as above, without entering the context)
+//!         stats calibrate <sku> (opr|elo|trueskill)
+//!             (replays a competition predicting each match; accuracy, Brier score
+//!              and calibration buckets for every model unless one is given)
+//!         history
+//!
+//!     * comp(etition) <sku>   CompetitionContext
+//!         (lists the divisions when there is more than one)
+//!         * division <name>   DivisionContext
+//!             (name or unambiguous prefix)
+//!         * round <round>     RoundContext
+//!             (qual, r16, qf, sf or f)
+//!         (every command below is narrowed to the division a/o round)
+//!         team <team name>
+//!             stats (opr|dpr|ccwm)
+//!                 (the team's ratings and place at the competition)
+//!             stats schedule (opr|elo|trueskill)
+//!                 (the team's schedule match by match with its chance to win each)
+//!         stats (opr|dpr|ccwm)
+//!             (least squares power ratings from scored qualifications)
+//!         stats schedule (opr|elo|trueskill)
+//!             (average partner and opponent rating, expected vs actual wins
+//!              and luck, the wins above expected; each match is predicted
+//!              from the ones before it; hardest schedule first)
+//!         rankings
+//!             computed
+//!                 (ranks teams from scored qualifications under the ruleset)
+//!             diff
+//!                 (where computed and official rankings disagree)
+//!             (the ruleset is the `ruleset` config key, or picked by season;
+//!              add rulesets in vexscout/rulesets.json under the config directory)
+//!         simulate ||runs|| <n> ||seed|| <n> ||model|| <opr|elo|trueskill>
+//!             (plays out the remaining qualifications; each team's likely
+//!              final rank, chance of top 8 and of being an alliance captain)
+//!             (inside a team, shows that team's whole rank distribution)
+//!         * bracket       BracketContext
+//!             (saved to vexscout/brackets/<sku>.json under the config directory,
+//!              or <sku>-<division>.json inside a division; starts from the
+//!              division's scheduled elimination matches if nothing is saved)
+//!             show (opr|elo|trueskill)
+//!                 (each series with its likely teams, wins so far and predicted
+//!                  winner, then every alliance's chance of winning the tournament;
+//!                  scored elimination matches are picked up each time)
+//!             alliances
+//!             alliance <seed> <team name> <team name> (team name)
+//!             remove <seed>
+//!             import (path)
+//!                 (from the elimination matches, or a JSON list of
+//!                  {"seed": 1, "teams": ["1234A", "5678B"]})
+//!             series <n>
+//!                 (matches per series, default 1)
+//!         * picklist      PickListContext
+//!             (saved to vexscout/picklists/<sku>.json under the config directory
+//!              after every change)
+//!             show
+//!                 (every team but [current_team], best score first)
+//!             best (default 3)
+//!                 (best teams still available)
+//!             | pick <team name>
+//!             | decline <team name>
+//!             | undo <team name>
+//!             rate <team name> <n>
+//!                 (scout rating)
+//!             weight opr|auton|skills|scouting|complement <n>
+//!                 (each metric is scored in standard deviations from the field;
+//!                  complement favours strength where [current_team] is weak)
+//!             weights
+//!             save <path>
+//!             load (path)
+//!                 (shares picks between laptops; without a path, rereads the saved list)
+//!
+//!         match (default next);   MatchContext
+//!             * load      MatchListContext
+//!                 (if team name supplied)
+//!                     (load organization's match list)
+//!                         (!! CONFIGURATION: organization or team)
+//!             | * next
+//!             | * prev
+//!                 (if team name supplied)
+//!                     (return team's last played match)
+//!                 (else)
+//!                     (return last updated match)
+//!             * lookup <n> [round <round>] [series <series>]
+//!                 (n would be nth match of the round; qualifications unless a round is selected)
+//!                 (series picks e.g. QF 3-n when more than one is played)
+//!         ==== MatchListContext ====
+//!             filter
+//!                 | team <team name>
+//!                 | round <round>
+//!                 | unscored
+//!                 | clear
+//!             sort by time|score|margin|schedule
+//!             show
+//!                 (shows the next page)
+//!             * select <n>    MatchContext
+//!         ==== MatchContext ====
+//!             teams
+//!                 (both alliances with rank, record and OPR)
+//!             predict (opr|elo|trueskill)
+//!                 (expected scores and win probability from earlier matches only)
+//!             history
+//!                 (each team's earlier results at this competition)
+//!             notes
+//!                 <team name> <note>
+//!             next
+//!             prev
+//!                 (step through the schedule)
+//!         wait
+//!     cache
+//!         status
+//!         clear
+//!         refresh <sku>
+//!             (refetches everything cached for the competition; entries that
+//!              fail to refetch are kept and served as stale)
+//!     offline
+//!         prepare <sku>
+//!             (saves every team, match and ranking for the competition to an event pack)
+//!         list
+//!     (run with --offline to answer everything from event packs and the cache)
+//!     * stats     StatsContext
+//!         | * <team name> | <organization name>
+//!         | * <competition sku>
+//!         | * <state abbreviation>    StatsListContext
+//! ```
 
-use std::io::{self, Write};
 use std::path::Path;
+
+use error::ReplError;
+
+mod bracket;
+mod cache;
+mod command;
+mod error;
+mod line_editor;
+mod match_list;
+mod model;
+mod notes;
+mod offline;
+mod picklist;
+mod predict;
+mod ranking;
+mod rating;
 mod repl;
+mod robotevents;
+mod schedule;
+mod simulate;
+mod skills;
+mod source;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod testing;
+mod vexdb;
 
 fn main() {
-    println!("Hello, world!");
-
-    let mut user_input = String::new();
+    let offline = std::env::args().skip(1).any(|argument| argument == "--offline");
 
-    let mut repl_interface = repl::ReplInterface::new(&Some(Path::new("testing.json")));
-    let repl_configuration = repl_interface.get_configuration();
-    println!("{}\n{}", repl_configuration.get_current_team(), repl_configuration.is_match_load_default_to_organization());
+    let mut repl_interface = repl::ReplInterface::new(&Some(Path::new("testing.json")), offline);
 
-    loop
+    while let Some(input) = repl_interface.prompt()
     {
-        let input = repl_interface.prompt();
-        if let Err(9) = repl_interface.eval(input)
+        match repl_interface.eval(input)
         {
-            break;
+            Ok(()) => (),
+            Err(ReplError::Exit) => break,
+            Err(error) => println!("{}", error)
         }
     }
 }
@@ -0,0 +1,272 @@
+use std::fmt;
+
+use crate::model::{Match, Round};
+
+/// Rows shown by each `show`
+pub const PAGE_SIZE: usize = 15;
+
+#[derive(Clone, PartialEq)]
+pub enum MatchFilter
+{
+    Team(String),
+    Round(Round),
+    Unscored,
+    Scored
+}
+
+impl MatchFilter
+{
+    fn accepts(&self, match_struct: &Match) -> bool
+    {
+        match self
+        {
+            MatchFilter::Team(team_name) => match_struct.alliance_of(team_name).is_some(),
+            MatchFilter::Round(round) => match_struct.round == *round,
+            MatchFilter::Unscored => !match_struct.scored,
+            MatchFilter::Scored => match_struct.scored
+        }
+    }
+}
+
+impl fmt::Display for MatchFilter
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            MatchFilter::Team(team_name) => write!(f, "team {}", team_name),
+            MatchFilter::Round(round) => write!(f, "round {}", round.abbreviation()),
+            MatchFilter::Unscored => write!(f, "unscored"),
+            MatchFilter::Scored => write!(f, "scored")
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MatchSort
+{
+    Schedule,
+    Time,
+    Score,
+    Margin
+}
+
+impl MatchSort
+{
+    pub fn parse(name: &str) -> Option<MatchSort>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "schedule" => Some(MatchSort::Schedule),
+            "time" => Some(MatchSort::Time),
+            "score" => Some(MatchSort::Score),
+            "margin" => Some(MatchSort::Margin),
+            _ => None
+        }
+    }
+}
+
+/// A loaded list of matches along with the filters, ordering and page being viewed
+#[derive(Clone)]
+pub struct MatchList
+{
+    matches: Vec<Match>,
+    filters: Vec<MatchFilter>,
+    sort: MatchSort,
+    page: usize
+}
+
+impl MatchList
+{
+    pub fn new(matches: Vec<Match>) -> MatchList
+    {
+        MatchList { matches, filters: Vec::new(), sort: MatchSort::Schedule, page: 0 }
+    }
+
+    pub fn get_filters(&self) -> &[MatchFilter]
+    {
+        &self.filters
+    }
+
+    /// Replaces any filter of the same kind, so `filter round qf` after
+    /// `filter round qual` narrows to quarterfinals instead of nothing
+    pub fn add_filter(&mut self, filter: MatchFilter)
+    {
+        self.filters.retain(|existing| std::mem::discriminant(existing) != std::mem::discriminant(&filter));
+        self.filters.push(filter);
+        self.page = 0;
+    }
+
+    pub fn clear_filters(&mut self)
+    {
+        self.filters.clear();
+        self.page = 0;
+    }
+
+    pub fn set_sort(&mut self, sort: MatchSort)
+    {
+        self.sort = sort;
+        self.page = 0;
+    }
+
+    /// The filtered, sorted matches that `show` pages through
+    pub fn visible(&self) -> Vec<&Match>
+    {
+        let mut visible = self.matches.iter()
+            .filter(|match_struct| self.filters.iter().all(|filter| filter.accepts(match_struct)))
+            .collect::<Vec<&Match>>();
+
+        match self.sort
+        {
+            MatchSort::Schedule => {
+                visible.sort_by_key(|match_struct| match_struct.schedule_order());
+            },
+            MatchSort::Time => {
+                visible.sort_by(|a, b|
+                {
+                    let time_of = |match_struct: &Match| match_struct.started.clone().or_else(|| match_struct.scheduled.clone());
+                    time_of(a).cmp(&time_of(b)).then(a.schedule_order().cmp(&b.schedule_order()))
+                });
+            },
+            MatchSort::Score => {
+                // highest scoring first
+                visible.sort_by_key(|match_struct| std::cmp::Reverse(match_struct.red.score + match_struct.blue.score));
+            },
+            MatchSort::Margin => {
+                // blowouts first
+                visible.sort_by_key(|match_struct| std::cmp::Reverse((match_struct.red.score as i32 - match_struct.blue.score as i32).abs()));
+            }
+        }
+
+        visible
+    }
+
+    pub fn page_count(&self) -> usize
+    {
+        self.visible().len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    /// Returns the 0-based page to show next and advances past it, wrapping
+    /// around after the last page
+    pub fn next_page(&mut self) -> usize
+    {
+        let page = self.page % self.page_count();
+        self.page = page + 1;
+        page
+    }
+
+    /// The row `select` finds the match in before any filter or sort is applied
+    pub fn get_loaded_row(&self, match_struct: &Match) -> Option<usize>
+    {
+        let mut matches = self.matches.iter().collect::<Vec<&Match>>();
+        matches.sort_by_key(|other_match| other_match.schedule_order());
+        matches.iter()
+            .position(|other_match| other_match.sku == match_struct.sku
+                && other_match.division == match_struct.division
+                && other_match.schedule_order() == match_struct.schedule_order())
+            .map(|index| index + 1)
+    }
+
+    /// Looks up a match by the row number printed by `show`
+    pub fn select(&self, row: usize) -> Option<&Match>
+    {
+        row.checked_sub(1).and_then(|index| self.visible().get(index).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    /// Q1 scores 50 by 10, Q2 35 by 15 and Q3 is unplayed; by the clock Q2, Q3, Q1
+    fn match_list() -> MatchList
+    {
+        let mut first = testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 20)));
+        first.scheduled = Some(String::from("2024-01-20T09:00:00"));
+        first.started = Some(String::from("2024-01-20T09:25:00"));
+        let mut second = testing::qualification(2, &["1A", "3A"], &["2A", "4A"], Some((10, 25)));
+        second.scheduled = Some(String::from("2024-01-20T09:10:00"));
+        let mut third = testing::qualification(3, &["1A", "4A"], &["2A", "3A"], None);
+        third.scheduled = Some(String::from("2024-01-20T09:20:00"));
+        let mut final_match = testing::qualification(1, &["1A", "2A"], &["3A", "4A"], None);
+        final_match.round = Round::Final;
+        MatchList::new(vec![third, final_match, first, second])
+    }
+
+    fn names(match_list: &MatchList) -> Vec<String>
+    {
+        match_list.visible().iter().map(|match_struct| match_struct.name()).collect()
+    }
+
+    #[test]
+    fn filters_replace_their_own_kind()
+    {
+        let mut match_list = match_list();
+        match_list.add_filter(MatchFilter::Round(Round::Qualification));
+        match_list.add_filter(MatchFilter::Round(Round::Final));
+        assert!(match_list.get_filters() == [MatchFilter::Round(Round::Final)]);
+        assert_eq!(match_list.visible().len(), 1);
+
+        match_list.add_filter(MatchFilter::Round(Round::Qualification));
+        match_list.add_filter(MatchFilter::Scored);
+        match_list.add_filter(MatchFilter::Team(String::from("3a")));
+        assert_eq!(match_list.get_filters().len(), 3);
+        assert_eq!(match_list.visible().len(), 2);
+
+        match_list.clear_filters();
+        assert_eq!(match_list.visible().len(), 4);
+    }
+
+    #[test]
+    fn sorts_by_each_order()
+    {
+        let mut match_list = match_list();
+        match_list.add_filter(MatchFilter::Round(Round::Qualification));
+        assert_eq!(names(&match_list), ["Q1", "Q2", "Q3"]);
+        // Q1 started late, so by the clock it comes last
+        match_list.set_sort(MatchSort::Time);
+        assert_eq!(names(&match_list), ["Q2", "Q3", "Q1"]);
+        match_list.set_sort(MatchSort::Score);
+        assert_eq!(names(&match_list), ["Q1", "Q2", "Q3"]);
+        match_list.set_sort(MatchSort::Margin);
+        assert_eq!(names(&match_list), ["Q2", "Q1", "Q3"]);
+        match_list.set_sort(MatchSort::Schedule);
+        assert_eq!(names(&match_list), ["Q1", "Q2", "Q3"]);
+    }
+
+    #[test]
+    fn pages_wrap_around()
+    {
+        let matches = (1..=PAGE_SIZE as u16 + 1).map(|number| testing::qualification(number, &["1A"], &["2A"], None)).collect();
+        let mut match_list = MatchList::new(matches);
+        assert_eq!(match_list.page_count(), 2);
+        assert_eq!((match_list.next_page(), match_list.next_page(), match_list.next_page()), (0, 1, 0));
+
+        // narrowing the list starts over from the first page
+        match_list.add_filter(MatchFilter::Team(String::from("1A")));
+        assert_eq!(match_list.next_page(), 0);
+        match_list.add_filter(MatchFilter::Scored);
+        assert_eq!(match_list.page_count(), 1);
+        assert_eq!((match_list.next_page(), match_list.next_page()), (0, 0));
+    }
+
+    #[test]
+    fn rows_follow_the_filters_and_order_shown()
+    {
+        let mut match_list = match_list();
+        assert_eq!(match_list.select(4).map(Match::name).as_deref(), Some("F 1-1"));
+
+        match_list.add_filter(MatchFilter::Scored);
+        match_list.set_sort(MatchSort::Margin);
+        assert_eq!(match_list.select(1).map(Match::name).as_deref(), Some("Q2"));
+        assert_eq!(match_list.select(2).map(Match::name).as_deref(), Some("Q1"));
+        assert!(match_list.select(0).is_none());
+        assert!(match_list.select(3).is_none());
+
+        // `pwd` names the row before any filter or sort
+        let selected = match_list.select(1).unwrap().clone();
+        assert_eq!(match_list.get_loaded_row(&selected), Some(2));
+    }
+}
@@ -0,0 +1,447 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Team
+{
+    pub number: String,
+    pub program: String,
+    pub team_name: String,
+    pub robot_name: String,
+    pub organisation: String,
+    pub city: String,
+    pub region: String,
+    pub country: String,
+    pub grade: String,
+    pub registered: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Event
+{
+    pub sku: String,
+    pub name: String,
+    pub program: String,
+    pub season: String,
+    pub venue: String,
+    pub city: String,
+    pub region: String,
+    pub country: String,
+    pub start: String,
+    pub end: String,
+    pub divisions: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::enum_variant_names)]
+pub enum Round
+{
+    Practice,
+    Qualification,
+    RoundOf16,
+    QuarterFinal,
+    SemiFinal,
+    Final
+}
+
+impl Round
+{
+    /// RobotEvents round numbers; 2 is qualification
+    pub fn from_robotevents_code(code: u8) -> Round
+    {
+        match code
+        {
+            1 => Round::Practice,
+            3 => Round::QuarterFinal,
+            4 => Round::SemiFinal,
+            5 => Round::Final,
+            6 => Round::RoundOf16,
+            _ => Round::Qualification
+        }
+    }
+
+    /// VexDB round numbers, which differ from RobotEvents'; 1 is qualification
+    pub fn from_vexdb_code(code: u8) -> Round
+    {
+        match code
+        {
+            0 => Round::Practice,
+            2 => Round::QuarterFinal,
+            3 => Round::SemiFinal,
+            4 => Round::Final,
+            16 => Round::RoundOf16,
+            _ => Round::Qualification
+        }
+    }
+
+    /// Accepts the abbreviation or a spelled out name, e.g. `qf` or `qual`
+    pub fn parse(name: &str) -> Option<Round>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "p" | "practice" => Some(Round::Practice),
+            "q" | "qual" | "quals" | "qualification" => Some(Round::Qualification),
+            "r16" | "round16" => Some(Round::RoundOf16),
+            "qf" | "quarterfinal" | "quarterfinals" => Some(Round::QuarterFinal),
+            "sf" | "semifinal" | "semifinals" => Some(Round::SemiFinal),
+            "f" | "final" | "finals" => Some(Round::Final),
+            _ => None
+        }
+    }
+
+    pub fn abbreviation(&self) -> &'static str
+    {
+        match self
+        {
+            Round::Practice => "P",
+            Round::Qualification => "Q",
+            Round::RoundOf16 => "R16",
+            Round::QuarterFinal => "QF",
+            Round::SemiFinal => "SF",
+            Round::Final => "F"
+        }
+    }
+
+    pub fn is_elimination(&self) -> bool
+    {
+        !matches!(self, Round::Practice | Round::Qualification)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color
+{
+    Red,
+    Blue
+}
+
+impl Color
+{
+    pub fn opposite(&self) -> Color
+    {
+        match self
+        {
+            Color::Red => Color::Blue,
+            Color::Blue => Color::Red
+        }
+    }
+}
+
+impl fmt::Display for Color
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Color::Red => f.pad("red"),
+            Color::Blue => f.pad("blue")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Alliance
+{
+    /// Every team on the alliance, including one sitting out
+    pub teams: Vec<String>,
+    /// Elimination alliances of three sit one team out each match
+    pub sitting: Option<String>,
+    pub score: u16
+}
+
+impl Alliance
+{
+    pub fn contains(&self, team: &str) -> bool
+    {
+        self.teams.iter().any(|other_team| other_team.eq_ignore_ascii_case(team))
+    }
+
+    /// The teams actually on the field
+    pub fn playing(&self) -> impl Iterator<Item = &String>
+    {
+        let sitting = self.sitting.clone();
+        self.teams.iter().filter(move |team| sitting.as_ref().is_none_or(|sitting| !sitting.eq_ignore_ascii_case(team)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Match
+{
+    pub sku: String,
+    pub division: String,
+
+    pub round: Round,
+    /// Which series of an elimination round, e.g. the 3 in QF 3-2
+    pub instance: u16,
+    pub match_num: u16,
+
+    pub field: String,
+    pub scheduled: Option<String>,
+    pub started: Option<String>,
+
+    pub red: Alliance,
+    pub blue: Alliance,
+
+    pub scored: bool
+}
+
+impl Match
+{
+    /// Short name as shown on the field display, e.g. `Q12` or `QF 3-2`
+    pub fn name(&self) -> String
+    {
+        if self.round.is_elimination()
+        {
+            format!("{} {}-{}", self.round.abbreviation(), self.instance, self.match_num)
+        }
+        else
+        {
+            format!("{}{}", self.round.abbreviation(), self.match_num)
+        }
+    }
+
+    /// Sort key matching the order matches are played in; elimination series
+    /// alternate, so QF 1-1 through QF 4-1 come before QF 1-2
+    pub fn schedule_order(&self) -> (Round, u16, u16)
+    {
+        (self.round, self.match_num, self.instance)
+    }
+
+    pub fn alliance(&self, color: Color) -> &Alliance
+    {
+        match color
+        {
+            Color::Red => &self.red,
+            Color::Blue => &self.blue
+        }
+    }
+
+    pub fn teams(&self) -> impl Iterator<Item = &String>
+    {
+        self.red.teams.iter().chain(self.blue.teams.iter())
+    }
+
+    pub fn alliance_of(&self, team: &str) -> Option<Color>
+    {
+        if self.red.contains(team)
+        {
+            Some(Color::Red)
+        }
+        else if self.blue.contains(team)
+        {
+            Some(Color::Blue)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub fn partner_of(&self, team: &str) -> Option<&String>
+    {
+        let color = self.alliance_of(team)?;
+        self.alliance(color).playing().find(|other_team| !other_team.eq_ignore_ascii_case(team))
+    }
+
+    pub fn opponents_of(&self, team: &str) -> Vec<&String>
+    {
+        match self.alliance_of(team)
+        {
+            Some(color) => self.alliance(color.opposite()).playing().collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// `None` until the match is scored, and for ties
+    pub fn winner(&self) -> Option<Color>
+    {
+        if !self.scored || self.red.score == self.blue.score
+        {
+            None
+        }
+        else if self.red.score > self.blue.score
+        {
+            Some(Color::Red)
+        }
+        else
+        {
+            Some(Color::Blue)
+        }
+    }
+
+    pub fn won_by(&self, team: &str) -> bool
+    {
+        self.alliance_of(team).is_some() && self.winner() == self.alliance_of(team)
+    }
+
+    pub fn is_tie(&self) -> bool
+    {
+        self.scored && self.red.score == self.blue.score
+    }
+
+    /// Score of the team's alliance minus its opponents' score
+    pub fn margin_for(&self, team: &str) -> Option<i32>
+    {
+        let color = self.alliance_of(team)?;
+        Some(self.alliance(color).score as i32 - self.alliance(color.opposite()).score as i32)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ranking
+{
+    pub sku: String,
+    pub division: String,
+    pub team: String,
+    pub rank: u16,
+    pub wins: u16,
+    pub losses: u16,
+    pub ties: u16,
+    pub wp: u16,
+    pub ap: u16,
+    pub sp: u16,
+    pub max_score: u16,
+    pub opr: f64,
+    pub dpr: f64,
+    pub ccwm: f64
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Award
+{
+    pub sku: String,
+    pub name: String,
+    pub team: String,
+    pub qualifies: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkillType
+{
+    Driver,
+    Programming,
+    Combined
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Skill
+{
+    pub sku: String,
+    pub team: String,
+    pub skill_type: SkillType,
+    pub rank: u16,
+    pub score: u16,
+    pub attempts: u16
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeasonRanking
+{
+    pub team: String,
+    pub program: String,
+    pub season: String,
+    pub vrating_rank: u32,
+    pub vrating: f64
+}
+
+impl fmt::Display for Team
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} {}", self.number, self.team_name)?;
+        if !self.robot_name.is_empty()
+        {
+            write!(f, " ({})", self.robot_name)?;
+        }
+        write!(f, " - {}, {}, {}, {}", self.organisation, self.city, self.region, self.country)
+    }
+}
+
+impl fmt::Display for Event
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} {} @ {}, {} ({} to {})", self.sku, self.name, self.venue, self.city, self.start, self.end)
+    }
+}
+
+impl fmt::Display for Alliance
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let teams = self.teams.iter()
+            .map(|team|
+            {
+                if self.sitting.as_ref() == Some(team)
+                {
+                    format!("({})", team)
+                }
+                else
+                {
+                    team.clone()
+                }
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", teams.join(" "))
+    }
+}
+
+impl fmt::Display for Match
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{:<8} {} vs {}", self.name(), self.red, self.blue)?;
+        if self.scored
+        {
+            write!(f, " [{}-{}]", self.red.score, self.blue.score)?;
+        }
+        else
+        {
+            write!(f, " [unscored]")?;
+        }
+
+        if !self.field.is_empty()
+        {
+            write!(f, " {}", self.field)?;
+        }
+        if let Some(time) = self.started.as_ref().or(self.scheduled.as_ref())
+        {
+            write!(f, " @ {}", time)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Ranking
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "#{} {} {}-{}-{} WP {} AP {} SP {} OPR {:.2} DPR {:.2} CCWM {:.2}",
+            self.rank, self.team, self.wins, self.losses, self.ties,
+            self.wp, self.ap, self.sp, self.opr, self.dpr, self.ccwm)
+    }
+}
+
+impl fmt::Display for Award
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}: {} ({})", self.sku, self.name, self.team)
+    }
+}
+
+impl fmt::Display for Skill
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}: {:?} #{} score {} ({} attempts)", self.sku, self.skill_type, self.rank, self.score, self.attempts)
+    }
+}
+
+impl fmt::Display for SeasonRanking
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} {}: vrating {:.2} (#{})", self.team, self.season, self.vrating, self.vrating_rank)
+    }
+}
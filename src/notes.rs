@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::fmt;
+
+use crate::cache;
+use crate::storage;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Note
+{
+    pub sku: String,
+    /// Name of the match the note was taken during, e.g. `Q12`
+    pub match_name: String,
+    pub created_at: u64,
+    pub text: String
+}
+
+impl fmt::Display for Note
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "[{} {}] {}", self.sku, self.match_name, self.text)
+    }
+}
+
+/// Scouting notes for every team, saved as `vexscout/notes.json` under the config directory
+#[derive(Serialize, Deserialize, Default)]
+pub struct NoteBook
+{
+    notes: BTreeMap<String, Vec<Note>>
+}
+
+impl NoteBook
+{
+    pub fn default_path() -> Option<PathBuf>
+    {
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("notes.json"))
+    }
+
+    /// A missing or unreadable file is treated as an empty notebook
+    pub fn load() -> NoteBook
+    {
+        NoteBook::default_path()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String>
+    {
+        let path = NoteBook::default_path().ok_or_else(|| String::from("no config directory for notes"))?;
+        storage::save_json(&path, self)
+    }
+
+    pub fn get_notes(&self, team_name: &str) -> &[Note]
+    {
+        self.notes.get(&team_name.to_ascii_uppercase()).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn add_note(&mut self, team_name: &str, sku: &str, match_name: &str, text: &str)
+    {
+        self.notes.entry(team_name.to_ascii_uppercase())
+            .or_default()
+            .push(Note
+            {
+                sku: sku.to_owned(),
+                match_name: match_name.to_owned(),
+                created_at: cache::now(),
+                text: text.to_owned()
+            });
+    }
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{self, Cache, DataKind};
+use crate::model::{Award, Event, Match, Ranking, SeasonRanking, Skill, Team};
+use crate::source::{DataSource, DataSourceKind, Query, SourceError};
+use crate::storage;
+
+/// Everything fetched while preparing for one event, keyed the same way as
+/// the on-disk cache so it can answer the exact queries the REPL makes
+#[derive(Serialize, Deserialize, Default)]
+pub struct EventPack
+{
+    pub sku: String,
+    pub created_at: u64,
+    pub entries: BTreeMap<String, serde_json::Value>
+}
+
+impl EventPack
+{
+    /// `vexscout/packs` under the platform's config directory
+    pub fn default_directory() -> Option<PathBuf>
+    {
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("packs"))
+    }
+
+    pub fn load(path: &Path) -> Result<EventPack, SourceError>
+    {
+        let file = File::open(path)
+            .map_err(|error| SourceError::Api(format!("could not open {}: {}", path.display(), error)))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| SourceError::Parse(format!("{}: {}", path.display(), error)))
+    }
+
+    /// Loads every `.json` pack in a directory, skipping files that fail to parse
+    pub fn load_directory(directory: &Path) -> Vec<EventPack>
+    {
+        let entries = match fs::read_dir(directory)
+        {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new()
+        };
+
+        let mut paths = entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+
+        paths.iter()
+            .filter_map(|path| EventPack::load(path).map_err(|error| println!("{}", error)).ok())
+            .collect()
+    }
+
+    /// The sku of each pack in a directory, from the file names `offline prepare` gives them
+    pub fn list_skus(directory: &Path) -> Vec<String>
+    {
+        fs::read_dir(directory).into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SourceError>
+    {
+        storage::save_json(path, self).map_err(SourceError::Api)
+    }
+}
+
+/// Passes queries through to another data source, remembering every answer
+pub struct RecordingSource<'a>
+{
+    inner: &'a dyn DataSource,
+    /// Which source `inner` is, for the entry keys
+    kind: DataSourceKind,
+    entries: RefCell<BTreeMap<String, serde_json::Value>>
+}
+
+impl<'a> RecordingSource<'a>
+{
+    pub fn new(inner: &'a dyn DataSource, kind: DataSourceKind) -> RecordingSource<'a>
+    {
+        RecordingSource { inner, kind, entries: RefCell::new(BTreeMap::new()) }
+    }
+
+    fn record<T: Serialize>(&self, key: String, result: Result<T, SourceError>) -> Result<T, SourceError>
+    {
+        if let Ok(value) = &result
+        {
+            if let Ok(json) = serde_json::to_value(value)
+            {
+                self.entries.borrow_mut().insert(key, json);
+            }
+        }
+        result
+    }
+
+    pub fn into_pack(self, sku: &str) -> EventPack
+    {
+        EventPack
+        {
+            sku: sku.to_ascii_uppercase(),
+            created_at: cache::now(),
+            entries: self.entries.into_inner()
+        }
+    }
+}
+
+impl<'a> DataSource for RecordingSource<'a>
+{
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        let teams = self.inner.get_teams(numbers)?;
+        for number in numbers
+        {
+            let team = teams.iter().find(|team| team.number.eq_ignore_ascii_case(number));
+            let _ = self.record(cache::team_key(self.kind, number), Ok(team));
+        }
+        Ok(teams)
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        self.record(cache::query_key(self.kind, DataKind::Events, query), self.inner.get_events(query))
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        self.record(cache::query_key(self.kind, DataKind::Matches, query), self.inner.get_matches(query))
+    }
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        self.record(cache::query_key(self.kind, DataKind::Rankings, query), self.inner.get_rankings(query))
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        self.record(cache::query_key(self.kind, DataKind::Awards, query), self.inner.get_awards(query))
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        self.record(cache::query_key(self.kind, DataKind::Skills, query), self.inner.get_skills(query))
+    }
+
+    fn get_season_rankings(&self, team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        let key = cache::query_key(self.kind, DataKind::SeasonRankings, Query::team(team));
+        self.record(key, self.inner.get_season_rankings(team))
+    }
+}
+
+/// Fetches everything VexScout could want about an event and its teams
+///
+/// Failures for individual teams are reported and skipped so one missing
+/// record does not sink the whole pack
+pub fn prepare(source: &dyn DataSource, kind: DataSourceKind, sku: &str) -> Result<EventPack, SourceError>
+{
+    let recorder = RecordingSource::new(source, kind);
+    let query = Query::event(sku);
+
+    let events = recorder.get_events(query)?;
+    if events.is_empty()
+    {
+        return Err(SourceError::Api(format!("no event {}", sku)));
+    }
+    let matches = recorder.get_matches(query)?;
+    let rankings = recorder.get_rankings(query)?;
+    let skills = recorder.get_skills(query).unwrap_or_default();
+    let _ = recorder.get_awards(query);
+
+    let mut teams = BTreeSet::new();
+    teams.extend(matches.iter().flat_map(|match_struct| match_struct.teams().cloned()));
+    teams.extend(rankings.iter().map(|ranking| ranking.team.clone()));
+    teams.extend(skills.iter().map(|skill| skill.team.clone()));
+    teams.retain(|team| !team.is_empty());
+
+    let team_list = teams.into_iter().collect::<Vec<String>>();
+    recorder.get_teams(&team_list)?;
+
+    // season ratings need every match at each event the teams went to
+    let mut recorded_events = BTreeSet::new();
+    recorded_events.insert(sku.to_ascii_uppercase());
+
+    for (index, team) in team_list.iter().enumerate()
+    {
+        println!("[{}/{}] {}", index + 1, team_list.len(), team);
+
+        let query = Query::team(team);
+        let events = recorder.get_events(query);
+        for event in events.iter().flatten()
+        {
+            if recorded_events.insert(event.sku.to_ascii_uppercase())
+            {
+                if let Err(error) = recorder.get_matches(Query::event(&event.sku))
+                {
+                    println!("  {}: {}", event.sku, error);
+                }
+            }
+        }
+
+        let results = [
+            events.err(),
+            recorder.get_matches(query).err(),
+            recorder.get_rankings(query).err(),
+            recorder.get_awards(query).err(),
+            recorder.get_skills(query).err(),
+            recorder.get_matches(Query::team_at_event(sku, team)).err(),
+            recorder.get_rankings(Query::team_at_event(sku, team)).err()
+        ];
+        for error in results.iter().flatten()
+        {
+            println!("  {}", error);
+        }
+        // not every data source has season rankings, which is fine
+        let _ = recorder.get_season_rankings(team);
+    }
+
+    Ok(recorder.into_pack(sku))
+}
+
+/// Data source that never touches the network
+///
+/// Answers come from event packs first, then from whatever the on-disk cache
+/// holds regardless of age, in both cases only what was fetched from `kind`
+pub struct OfflineSource
+{
+    entries: BTreeMap<String, serde_json::Value>,
+    cache: Option<Cache>,
+    kind: DataSourceKind
+}
+
+impl OfflineSource
+{
+    pub fn new(packs: Vec<EventPack>, cache: Option<Cache>, kind: DataSourceKind) -> OfflineSource
+    {
+        let mut entries = BTreeMap::new();
+        for pack in packs
+        {
+            entries.extend(pack.entries);
+        }
+        OfflineSource { entries, cache, kind }
+    }
+
+    fn lookup<T: DeserializeOwned>(&self, key: &str) -> Option<T>
+    {
+        if let Some(value) = self.entries.get(key)
+        {
+            if let Ok(value) = serde_json::from_value(value.clone())
+            {
+                return Some(value);
+            }
+        }
+
+        self.cache.as_ref()
+            .and_then(|cache| cache.get::<T>(key))
+            .map(|(_, value)| value)
+    }
+
+    fn fetch<T: DeserializeOwned>(&self, kind: DataKind, query: Query) -> Result<T, SourceError>
+    {
+        self.lookup(&cache::query_key(self.kind, kind, query))
+            .ok_or_else(||
+            {
+                let mut description = kind.name().replace('_', " ");
+                if let Some(team) = query.team
+                {
+                    description = format!("{} for {}", description, team);
+                }
+                if let Some(sku) = query.sku
+                {
+                    description = format!("{} at {}", description, sku);
+                }
+                if let Some(region) = query.region
+                {
+                    description = format!("{} in {}", description, region);
+                }
+                SourceError::NotAvailableOffline(description)
+            })
+    }
+}
+
+impl DataSource for OfflineSource
+{
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        let mut known = false;
+        let mut teams = Vec::new();
+        for number in numbers
+        {
+            if let Some(team) = self.lookup::<Option<Team>>(&cache::team_key(self.kind, number))
+            {
+                known = true;
+                teams.extend(team);
+            }
+        }
+
+        if known || numbers.is_empty()
+        {
+            Ok(teams)
+        }
+        else
+        {
+            Err(SourceError::NotAvailableOffline(format!("team {}", numbers.join(", "))))
+        }
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        self.fetch(DataKind::Events, query)
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        self.fetch(DataKind::Matches, query)
+    }
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        self.fetch(DataKind::Rankings, query)
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        self.fetch(DataKind::Awards, query)
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        self.fetch(DataKind::Skills, query)
+    }
+
+    fn get_season_rankings(&self, team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        self.fetch(DataKind::SeasonRankings, Query::team(team))
+    }
+}
@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::cache;
+use crate::stats;
+use crate::storage;
+
+/// What a candidate is ranked on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Metric
+{
+    Opr,
+    /// Autonomous points per match, from the official rankings
+    Auton,
+    /// Best driver plus best programming skills score at the event
+    Skills,
+    /// The scout rating given with `rate`
+    Scouting,
+    /// Strength where our own team is weak
+    Complement
+}
+
+pub const METRICS: [Metric; 5] = [Metric::Opr, Metric::Auton, Metric::Skills, Metric::Scouting, Metric::Complement];
+
+impl Metric
+{
+    pub fn parse(name: &str) -> Option<Metric>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "opr" => Some(Metric::Opr),
+            "auton" | "auto" | "ap" => Some(Metric::Auton),
+            "skills" => Some(Metric::Skills),
+            "scouting" | "scout" | "rating" => Some(Metric::Scouting),
+            "complement" | "complementarity" | "fit" => Some(Metric::Complement),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Metric::Opr => "opr",
+            Metric::Auton => "auton",
+            Metric::Skills => "skills",
+            Metric::Scouting => "scouting",
+            Metric::Complement => "complement"
+        }
+    }
+}
+
+/// How much each metric counts towards a candidate's score
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Weights
+{
+    pub opr: f64,
+    pub auton: f64,
+    pub skills: f64,
+    pub scouting: f64,
+    pub complement: f64
+}
+
+impl Default for Weights
+{
+    fn default() -> Weights
+    {
+        Weights { opr: 1.0, auton: 0.5, skills: 0.5, scouting: 0.5, complement: 0.5 }
+    }
+}
+
+impl Weights
+{
+    pub fn get(&self, metric: Metric) -> f64
+    {
+        match metric
+        {
+            Metric::Opr => self.opr,
+            Metric::Auton => self.auton,
+            Metric::Skills => self.skills,
+            Metric::Scouting => self.scouting,
+            Metric::Complement => self.complement
+        }
+    }
+
+    pub fn set(&mut self, metric: Metric, weight: f64)
+    {
+        match metric
+        {
+            Metric::Opr => self.opr = weight,
+            Metric::Auton => self.auton = weight,
+            Metric::Skills => self.skills = weight,
+            Metric::Scouting => self.scouting = weight,
+            Metric::Complement => self.complement = weight
+        }
+    }
+}
+
+impl fmt::Display for Weights
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let weights = METRICS.iter().map(|metric| format!("{} {}", metric.name(), self.get(*metric))).collect::<Vec<String>>();
+        write!(f, "{}", weights.join(", "))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PickStatus
+{
+    Available,
+    Picked,
+    Declined
+}
+
+impl fmt::Display for PickStatus
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            PickStatus::Available => write!(f, "available"),
+            PickStatus::Picked => write!(f, "picked"),
+            PickStatus::Declined => write!(f, "declined")
+        }
+    }
+}
+
+/// A team's raw numbers before they are weighed against the field
+#[derive(Default, Clone)]
+pub struct TeamMetrics
+{
+    pub opr: f64,
+    pub auton: f64,
+    pub skills: f64
+}
+
+/// One team's place on the pick list
+pub struct Candidate
+{
+    pub team: String,
+    pub status: PickStatus,
+    pub metrics: TeamMetrics,
+    pub scout_rating: Option<f64>,
+    /// Weighted sum of the metrics, each in standard deviations from the field average
+    pub score: f64
+}
+
+impl fmt::Display for Candidate
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let scout_rating = self.scout_rating.map(|rating| format!("{:.0}", rating)).unwrap_or_else(|| String::from("-"));
+        write!(f, "{:<7} {:>+6.2}  OPR {:>6.1}  auton {:>5.1}  skills {:>4.0}  scout {:>2}",
+            self.team, self.score, self.metrics.opr, self.metrics.auton, self.metrics.skills, scout_rating)?;
+        if self.status != PickStatus::Available
+        {
+            write!(f, "  ({})", self.status)?;
+        }
+        Ok(())
+    }
+}
+
+/// Picks, declines, scout ratings and weights for one competition's alliance selection
+///
+/// Saved as `vexscout/picklists/<sku>.json` under the config directory after
+/// every change; `save` and `load` with a path share it between laptops
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PickList
+{
+    pub sku: String,
+    #[serde(default)]
+    pub weights: Weights,
+    #[serde(default)]
+    statuses: BTreeMap<String, PickStatus>,
+    #[serde(default)]
+    scout_ratings: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub updated_at: u64
+}
+
+impl PickList
+{
+    pub fn new(sku: &str) -> PickList
+    {
+        PickList
+        {
+            sku: sku.to_ascii_uppercase(),
+            weights: Weights::default(),
+            statuses: BTreeMap::new(),
+            scout_ratings: BTreeMap::new(),
+            updated_at: 0
+        }
+    }
+
+    pub fn default_path(sku: &str) -> Option<PathBuf>
+    {
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("picklists").join(format!("{}.json", sku.to_ascii_uppercase())))
+    }
+
+    /// The competition's saved pick list, or an empty one if there is none yet
+    pub fn load_default(sku: &str) -> PickList
+    {
+        PickList::default_path(sku)
+            .and_then(|path| PickList::load(&path).ok())
+            .unwrap_or_else(|| PickList::new(sku))
+    }
+
+    pub fn load(path: &Path) -> Result<PickList, String>
+    {
+        let file = File::open(path).map_err(|error| format!("could not open {}: {}", path.display(), error))?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|error| format!("could not read {}: {}", path.display(), error))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String>
+    {
+        storage::save_json(path, self)
+    }
+
+    pub fn save_default(&mut self) -> Result<(), String>
+    {
+        self.updated_at = cache::now();
+        let path = PickList::default_path(&self.sku).ok_or_else(|| String::from("no config directory for pick lists"))?;
+        self.save(&path)
+    }
+
+    pub fn get_status(&self, team_name: &str) -> PickStatus
+    {
+        self.statuses.get(&team_name.to_ascii_uppercase()).copied().unwrap_or(PickStatus::Available)
+    }
+
+    pub fn set_status(&mut self, team_name: &str, status: PickStatus)
+    {
+        if status == PickStatus::Available
+        {
+            self.statuses.remove(&team_name.to_ascii_uppercase());
+        }
+        else
+        {
+            self.statuses.insert(team_name.to_ascii_uppercase(), status);
+        }
+    }
+
+    pub fn set_scout_rating(&mut self, team_name: &str, rating: f64)
+    {
+        self.scout_ratings.insert(team_name.to_ascii_uppercase(), rating);
+    }
+
+    /// Ranks every team but our own, best first
+    ///
+    /// Each metric is turned into standard deviations from the field average so
+    /// the weights compare like with like; teams without a scout rating count
+    /// as average. Complement weighs a candidate's OPR, auton and skills by how
+    /// likely a random team is to beat `own_team` at each, so it is zero for
+    /// everyone when our team is not at the competition.
+    pub fn rank(&self, metrics: &HashMap<String, TeamMetrics>, own_team: &str) -> Vec<Candidate>
+    {
+        let teams = metrics.keys().collect::<Vec<&String>>();
+        let standardize = |value_of: &dyn Fn(&String) -> Option<f64>| -> HashMap<String, f64>
+        {
+            let values = teams.iter().filter_map(|team| value_of(team)).collect::<Vec<f64>>();
+            let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+            let deviation = (values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / values.len().max(1) as f64).sqrt();
+            teams.iter()
+                .map(|team| (team.to_string(), value_of(team).map(|value| if deviation > 0.0 { (value - mean) / deviation } else { 0.0 }).unwrap_or(0.0)))
+                .collect()
+        };
+
+        let opr = standardize(&|team| Some(metrics[team].opr));
+        let auton = standardize(&|team| Some(metrics[team].auton));
+        let skills = standardize(&|team| Some(metrics[team].skills));
+        let scouting = standardize(&|team| self.scout_ratings.get(team).copied());
+
+        let own_team = own_team.to_ascii_uppercase();
+        let needs = if metrics.contains_key(&own_team)
+        {
+            [&opr, &auton, &skills].iter().map(|standardized| stats::normal_cdf(-standardized[&own_team])).collect::<Vec<f64>>()
+        }
+        else
+        {
+            vec![0.0; 3]
+        };
+        let total_need = needs.iter().sum::<f64>();
+
+        let mut candidates = teams.iter()
+            .filter(|team| ***team != own_team)
+            .map(|team|
+            {
+                let complement = if total_need > 0.0
+                {
+                    [&opr, &auton, &skills].iter().zip(&needs).map(|(standardized, need)| standardized[*team] * need).sum::<f64>() / total_need
+                }
+                else
+                {
+                    0.0
+                };
+                let score = self.weights.opr * opr[*team]
+                    + self.weights.auton * auton[*team]
+                    + self.weights.skills * skills[*team]
+                    + self.weights.scouting * scouting[*team]
+                    + self.weights.complement * complement;
+
+                Candidate
+                {
+                    team: team.to_string(),
+                    status: self.get_status(team),
+                    metrics: metrics[*team].clone(),
+                    scout_rating: self.scout_ratings.get(*team).copied(),
+                    score
+                }
+            })
+            .collect::<Vec<Candidate>>();
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.team.cmp(&b.team)));
+        candidates
+    }
+    /// The top `count` candidates nobody has picked and who have not declined
+    pub fn best(&self, metrics: &HashMap<String, TeamMetrics>, own_team: &str, count: usize) -> Vec<Candidate>
+    {
+        let mut candidates = self.rank(metrics, own_team);
+        candidates.retain(|candidate| candidate.status == PickStatus::Available);
+        candidates.truncate(count);
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn metrics(teams: &[(&str, f64, f64, f64)]) -> HashMap<String, TeamMetrics>
+    {
+        teams.iter().map(|(team, opr, auton, skills)| (team.to_string(), TeamMetrics { opr: *opr, auton: *auton, skills: *skills })).collect()
+    }
+
+    /// A pick list counting only `metric`
+    fn weighing(metric: Metric) -> PickList
+    {
+        let mut pick_list = PickList::new("RE-TEST");
+        for other_metric in &METRICS
+        {
+            pick_list.weights.set(*other_metric, if *other_metric == metric { 1.0 } else { 0.0 });
+        }
+        pick_list
+    }
+
+    fn scores(candidates: &[Candidate]) -> Vec<(&str, f64)>
+    {
+        candidates.iter().map(|candidate| (candidate.team.as_str(), candidate.score)).collect()
+    }
+
+    #[test]
+    fn metrics_are_in_deviations_from_the_field()
+    {
+        // mean 20 and standard deviation √(200 / 3) ≈ 8.165
+        let metrics = metrics(&[("1A", 10.0, 5.0, 0.0), ("2A", 20.0, 5.0, 0.0), ("3A", 30.0, 5.0, 0.0)]);
+        let candidates = weighing(Metric::Opr).rank(&metrics, "9Z");
+        let scores = scores(&candidates);
+        assert_eq!(scores.iter().map(|(team, _)| *team).collect::<Vec<&str>>(), vec!["3A", "2A", "1A"]);
+        assert!((scores[0].1 - 1.224745).abs() < 1e-6);
+        assert!(scores[1].1.abs() < 1e-12);
+        assert!((scores[2].1 + 1.224745).abs() < 1e-6);
+
+        // every team has the same auton, which says nothing about any of them
+        for candidate in weighing(Metric::Auton).rank(&metrics, "9Z")
+        {
+            assert_eq!(candidate.score, 0.0);
+        }
+    }
+
+    #[test]
+    fn unrated_teams_count_as_average()
+    {
+        let metrics = metrics(&[("1A", 0.0, 0.0, 0.0), ("2A", 0.0, 0.0, 0.0), ("3A", 0.0, 0.0, 0.0)]);
+        let mut pick_list = weighing(Metric::Scouting);
+        pick_list.set_scout_rating("1a", 9.0);
+        pick_list.set_scout_rating("2A", 3.0);
+
+        // ratings of 9 and 3 are one deviation either side of 6
+        let candidates = pick_list.rank(&metrics, "9Z");
+        assert_eq!(scores(&candidates), vec![("1A", 1.0), ("3A", 0.0), ("2A", -1.0)]);
+        assert_eq!(candidates[1].scout_rating, None);
+    }
+
+    #[test]
+    fn complement_favours_strength_where_we_are_weak()
+    {
+        // 1A is all auton and no OPR; 2A is the opposite and 3A is in between
+        let metrics = metrics(&[("1A", 0.0, 10.0, 0.0), ("2A", 10.0, 0.0, 0.0), ("3A", 5.0, 5.0, 0.0)]);
+        let pick_list = weighing(Metric::Complement);
+
+        let candidates = pick_list.rank(&metrics, "1a");
+        assert!(candidates.iter().all(|candidate| candidate.team != "1A"));
+        let scores = scores(&candidates);
+        assert_eq!(scores[0].0, "2A");
+        assert!((scores[0].1 - 0.636319).abs() < 1e-4);
+        assert!(scores[1].1.abs() < 1e-12);
+
+        // without our team there is nothing to complement
+        for candidate in pick_list.rank(&metrics, "9Z")
+        {
+            assert_eq!(candidate.score, 0.0);
+        }
+    }
+
+    #[test]
+    fn best_skips_picked_and_declined_teams()
+    {
+        let metrics = metrics(&[("1A", 40.0, 0.0, 0.0), ("2A", 30.0, 0.0, 0.0), ("3A", 20.0, 0.0, 0.0), ("4A", 10.0, 0.0, 0.0)]);
+        let mut pick_list = weighing(Metric::Opr);
+        pick_list.set_status("1A", PickStatus::Picked);
+        pick_list.set_status("3a", PickStatus::Declined);
+
+        let best = pick_list.best(&metrics, "9Z", 3);
+        assert_eq!(best.iter().map(|candidate| candidate.team.as_str()).collect::<Vec<&str>>(), vec!["2A", "4A"]);
+        assert_eq!(pick_list.best(&metrics, "9Z", 1).len(), 1);
+
+        // undoing a pick makes the team available again
+        pick_list.set_status("1A", PickStatus::Available);
+        assert_eq!(pick_list.best(&metrics, "9Z", 1)[0].team, "1A");
+        assert_eq!(pick_list.rank(&metrics, "9Z").len(), 4);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::model::{Match, Round};
+use crate::rating::{EloRatings, MatchRating, RatingModel, TrueSkillRatings};
+use crate::stats::{self, Prediction};
+
+/// Predicts matches at one event from only what was known before each of them
+///
+/// Expected scores always come from OPR over the event's earlier matches,
+/// since only OPR is measured in points; the win probability comes from the
+/// chosen model. Elo and TrueSkill also start from earlier events this season.
+pub struct Predictor
+{
+    model: RatingModel,
+    ratings: Option<Box<dyn MatchRating>>,
+    played: Vec<Match>
+}
+
+impl Predictor
+{
+    /// `prior_events` are earlier events' matches, in the order they were played
+    pub fn new(model: RatingModel, prior_events: &[Vec<Match>]) -> Predictor
+    {
+        let mut ratings: Option<Box<dyn MatchRating>> = match model
+        {
+            RatingModel::Opr => None,
+            RatingModel::Elo => Some(Box::new(EloRatings::default())),
+            RatingModel::TrueSkill => Some(Box::new(TrueSkillRatings::default()))
+        };
+
+        if let Some(ratings) = ratings.as_mut()
+        {
+            for matches in prior_events
+            {
+                ratings.add_event(matches);
+            }
+        }
+
+        Predictor { model, ratings, played: Vec::new() }
+    }
+
+    pub fn get_model(&self) -> RatingModel
+    {
+        self.model
+    }
+
+    /// Feeds the event's matches in schedule order up to, but not including, `match_struct`
+    pub fn add_matches_before(&mut self, matches: &[Match], match_struct: &Match)
+    {
+        let mut earlier = matches.iter()
+            .filter(|other_match| other_match.scored && other_match.round != Round::Practice)
+            .filter(|other_match| other_match.schedule_order() < match_struct.schedule_order())
+            .collect::<Vec<&Match>>();
+        earlier.sort_by_key(|other_match| other_match.schedule_order());
+
+        for other_match in earlier
+        {
+            self.add_match(other_match);
+        }
+    }
+
+    /// Feeds every scored match of the event, in schedule order
+    pub fn add_scored_matches(&mut self, matches: &[Match])
+    {
+        let mut scored = matches.iter()
+            .filter(|match_struct| match_struct.scored && match_struct.round != Round::Practice)
+            .collect::<Vec<&Match>>();
+        scored.sort_by_key(|match_struct| match_struct.schedule_order());
+
+        for match_struct in scored
+        {
+            self.add_match(match_struct);
+        }
+    }
+
+    /// Records a scored match so later predictions can use it
+    pub fn add_match(&mut self, match_struct: &Match)
+    {
+        if let Some(ratings) = self.ratings.as_mut()
+        {
+            ratings.add_match(match_struct);
+        }
+        self.played.push(match_struct.clone());
+    }
+
+    pub fn predict(&self, match_struct: &Match) -> Prediction
+    {
+        let mut contributions = stats::power_ratings(&self.played).into_iter()
+            .map(|rating| (rating.team, rating.opr))
+            .collect::<HashMap<String, f64>>();
+        let deviation = stats::score_deviation(&self.played, &contributions);
+
+        // teams that have not played yet are assumed to be average
+        let average = if contributions.is_empty() { 0.0 } else { contributions.values().sum::<f64>() / contributions.len() as f64 };
+        for team in match_struct.teams()
+        {
+            contributions.entry(team.to_ascii_uppercase()).or_insert(average);
+        }
+
+        let mut prediction = stats::predict(match_struct, &contributions, deviation);
+        if let Some(ratings) = self.ratings.as_ref()
+        {
+            prediction.red_win_probability = ratings.red_win_probability(match_struct);
+        }
+        prediction
+    }
+}
+
+/// Predictions grouped by how likely red was given to win
+struct Bucket
+{
+    predictions: u32,
+    predicted: f64,
+    /// Red wins, with ties counting half
+    red_wins: f64
+}
+
+/// How well a model's win probabilities matched what happened
+pub struct Calibration
+{
+    model: RatingModel,
+    predictions: u32,
+    /// Matches with a winner, and how many of those were called right
+    decided: u32,
+    correct: f64,
+    squared_error: f64,
+    buckets: Vec<Bucket>
+}
+
+/// Width of each calibration bucket, in percent
+const BUCKET_WIDTH: usize = 10;
+
+impl Calibration
+{
+    /// Replays an event's scored matches in schedule order, predicting each
+    /// before it is added to the model
+    pub fn backtest(model: RatingModel, prior_events: &[Vec<Match>], matches: &[Match]) -> Calibration
+    {
+        let mut predictor = Predictor::new(model, prior_events);
+        let mut calibration = Calibration
+        {
+            model,
+            predictions: 0,
+            decided: 0,
+            correct: 0.0,
+            squared_error: 0.0,
+            buckets: (0..100 / BUCKET_WIDTH).map(|_| Bucket { predictions: 0, predicted: 0.0, red_wins: 0.0 }).collect()
+        };
+
+        let mut matches = matches.iter()
+            .filter(|match_struct| match_struct.scored && match_struct.round != Round::Practice)
+            .collect::<Vec<&Match>>();
+        matches.sort_by_key(|match_struct| match_struct.schedule_order());
+
+        for match_struct in matches
+        {
+            let probability = predictor.predict(match_struct).red_win_probability;
+            calibration.add(probability, match_struct);
+            predictor.add_match(match_struct);
+        }
+        calibration
+    }
+
+    fn add(&mut self, probability: f64, match_struct: &Match)
+    {
+        let outcome = if match_struct.is_tie() { 0.5 } else if match_struct.red.score > match_struct.blue.score { 1.0 } else { 0.0 };
+
+        self.predictions += 1;
+        self.squared_error += (probability - outcome) * (probability - outcome);
+        if !match_struct.is_tie()
+        {
+            self.decided += 1;
+            // a coin flip is half right either way
+            self.correct += if probability == 0.5 { 0.5 } else if (probability > 0.5) == (outcome == 1.0) { 1.0 } else { 0.0 };
+        }
+
+        let index = ((probability * 100.0) as usize / BUCKET_WIDTH).min(self.buckets.len() - 1);
+        let bucket = &mut self.buckets[index];
+        bucket.predictions += 1;
+        bucket.predicted += probability;
+        bucket.red_wins += outcome;
+    }
+
+    pub fn get_accuracy(&self) -> f64
+    {
+        if self.decided == 0 { 0.0 } else { self.correct / self.decided as f64 }
+    }
+
+    /// Mean squared error of the win probabilities; 0.25 is a coin flip, lower is better
+    pub fn get_brier_score(&self) -> f64
+    {
+        if self.predictions == 0 { 0.0 } else { self.squared_error / self.predictions as f64 }
+    }
+}
+
+impl fmt::Display for Calibration
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}: {} matches, accuracy {:.1}%, Brier score {:.3}",
+            self.model.name(), self.predictions, 100.0 * self.get_accuracy(), self.get_brier_score())?;
+
+        for (index, bucket) in self.buckets.iter().enumerate().filter(|(_, bucket)| bucket.predictions > 0)
+        {
+            let count = bucket.predictions as f64;
+            write!(f, "\n  {:>3}-{:<3}% {:>4} matches  predicted {:>5.1}%  red won {:>5.1}%",
+                index * BUCKET_WIDTH, (index + 1) * BUCKET_WIDTH, bucket.predictions,
+                100.0 * bucket.predicted / count, 100.0 * bucket.red_wins / count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn calibration_scores_known_predictions()
+    {
+        let mut calibration = Calibration::backtest(RatingModel::Elo, &[], &[]);
+        let red_win = testing::qualification(1, &["1A"], &["2A"], Some((20, 10)));
+        let blue_win = testing::qualification(2, &["1A"], &["2A"], Some((10, 20)));
+        let tie = testing::qualification(3, &["1A"], &["2A"], Some((10, 10)));
+        calibration.add(0.8, &red_win);
+        calibration.add(0.3, &red_win);
+        calibration.add(0.5, &blue_win);
+        calibration.add(0.6, &tie);
+
+        // (0.04 + 0.49 + 0.25 + 0.01) / 4, and 1 + 0 + 0.5 right of the 3 decided
+        assert!((calibration.get_brier_score() - 0.1975).abs() < 1e-9);
+        assert!((calibration.get_accuracy() - 0.5).abs() < 1e-9);
+        assert_eq!(calibration.buckets[8].predictions, 1);
+        assert_eq!(calibration.buckets[6].red_wins, 0.5);
+    }
+
+    #[test]
+    fn backtest_predicts_before_adding_each_match()
+    {
+        // with nothing known, the first match is a coin flip
+        let matches = [testing::qualification(1, &["1A"], &["2A"], Some((20, 10)))];
+        let calibration = Calibration::backtest(RatingModel::Elo, &[], &matches);
+        assert_eq!(calibration.predictions, 1);
+        assert!((calibration.get_brier_score() - 0.25).abs() < 1e-9);
+        assert!((calibration.get_accuracy() - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use crate::model::{Color, Match, Ranking, Round};
+
+/// Rulesets that ship with VexScout; `vexscout/rulesets.json` under the
+/// config directory can add more or replace these by name
+const BUILT_IN_RULESETS: &str = include_str!("rulesets.json");
+
+/// Sort key putting teams in number order, e.g. 9A before 10A
+fn number_order(team: &str) -> (u64, &str)
+{
+    let digits = team.find(|character: char| !character.is_ascii_digit()).unwrap_or(team.len());
+    (team[..digits].parse().unwrap_or(u64::MAX), &team[digits..])
+}
+
+/// How a match adds to a team's strength of schedule points
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum SpRule
+{
+    /// The losing alliance's score, to both alliances
+    LosingScore,
+    /// The opposing alliance's score
+    OpponentScore,
+    /// The team's own alliance score
+    OwnScore
+}
+
+/// One step of the chain used to order teams with equal records
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Tiebreaker
+{
+    Wp,
+    Ap,
+    Sp,
+    /// Highest alliance score the team was part of
+    HighScore
+}
+
+/// A season's qualification ranking rules
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ruleset
+{
+    pub name: String,
+    /// Season names, as the data source spells them, this ruleset applies to
+    #[serde(default)]
+    pub seasons: Vec<String>,
+    pub win_points: u32,
+    pub tie_points: u32,
+    pub loss_points: u32,
+    pub strength_of_schedule: SpRule,
+    /// Applied in order; teams still tied after the last are ordered by number
+    pub tiebreakers: Vec<Tiebreaker>
+}
+
+/// A team's qualification results under a ruleset
+#[derive(Clone, Default)]
+pub struct Record
+{
+    pub wins: u16,
+    pub losses: u16,
+    pub ties: u16,
+    pub wp: u32,
+    /// Match results do not say who won autonomous, so AP always comes
+    /// from the official rankings
+    pub ap: f64,
+    pub sp: u32,
+    pub high_score: u16,
+    pub played: u16
+}
+
+impl Ruleset
+{
+    pub fn user_path() -> Option<PathBuf>
+    {
+        dirs::config_dir().map(|directory| directory.join("vexscout").join("rulesets.json"))
+    }
+
+    /// The user's rulesets followed by the built in ones
+    ///
+    /// A missing user file is fine; an unreadable one is reported and skipped
+    pub fn load_all() -> Vec<Ruleset>
+    {
+        let mut rulesets = Vec::new();
+        if let Some(file) = Ruleset::user_path().and_then(|path| File::open(path).ok())
+        {
+            match serde_json::from_reader::<_, Vec<Ruleset>>(BufReader::new(file))
+            {
+                Ok(user_rulesets) => rulesets.extend(user_rulesets),
+                Err(error) => println!("ignoring rulesets.json: {}", error)
+            }
+        }
+        rulesets.extend(Ruleset::built_in());
+        rulesets
+    }
+
+    /// The rulesets shipped with the program, without the user's
+    pub fn built_in() -> Vec<Ruleset>
+    {
+        serde_json::from_str(BUILT_IN_RULESETS).expect("built in rulesets are valid")
+    }
+
+    /// The ruleset with the given name, otherwise the first one listing the
+    /// season, otherwise the default
+    pub fn select<'a>(rulesets: &'a [Ruleset], name: &str, season: &str) -> Option<&'a Ruleset>
+    {
+        if !name.is_empty()
+        {
+            return rulesets.iter().find(|ruleset| ruleset.name.eq_ignore_ascii_case(name));
+        }
+        rulesets.iter()
+            .find(|ruleset| ruleset.seasons.iter().any(|other_season| other_season.eq_ignore_ascii_case(season)))
+            .or_else(|| rulesets.iter().find(|ruleset| ruleset.name == "default"))
+    }
+
+    pub fn add_result(&self, record: &mut Record, own_score: u16, opponent_score: u16)
+    {
+        match own_score.cmp(&opponent_score)
+        {
+            Ordering::Greater => {
+                record.wins += 1;
+                record.wp += self.win_points;
+            },
+            Ordering::Less => {
+                record.losses += 1;
+                record.wp += self.loss_points;
+            },
+            Ordering::Equal => {
+                record.ties += 1;
+                record.wp += self.tie_points;
+            }
+        }
+
+        record.sp += match self.strength_of_schedule
+        {
+            SpRule::LosingScore => own_score.min(opponent_score),
+            SpRule::OpponentScore => opponent_score,
+            SpRule::OwnScore => own_score
+        } as u32;
+        record.high_score = record.high_score.max(own_score);
+        record.played += 1;
+    }
+
+    /// Orders records best first by the tiebreak chain
+    pub fn compare(&self, a: &Record, b: &Record) -> Ordering
+    {
+        self.tiebreakers.iter()
+            .map(|tiebreaker| match tiebreaker
+            {
+                Tiebreaker::Wp => b.wp.cmp(&a.wp),
+                Tiebreaker::Ap => b.ap.total_cmp(&a.ap),
+                Tiebreaker::Sp => b.sp.cmp(&a.sp),
+                Tiebreaker::HighScore => b.high_score.cmp(&a.high_score)
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Every team's record from one division's scored qualification matches
+    pub fn get_records(&self, matches: &[Match], official: &[Ranking]) -> Vec<(String, Record)>
+    {
+        let mut records: HashMap<String, Record> = HashMap::new();
+        let qualifications = matches.iter().filter(|match_struct| match_struct.round == Round::Qualification);
+        for match_struct in qualifications
+        {
+            for color in &[Color::Red, Color::Blue]
+            {
+                for team in match_struct.alliance(*color).playing()
+                {
+                    let record = records.entry(team.to_ascii_uppercase()).or_default();
+                    if match_struct.scored
+                    {
+                        self.add_result(record, match_struct.alliance(*color).score, match_struct.alliance(color.opposite()).score);
+                    }
+                }
+            }
+        }
+
+        for ranking in official
+        {
+            if let Some(record) = records.get_mut(&ranking.team.to_ascii_uppercase())
+            {
+                record.ap = ranking.ap as f64;
+            }
+        }
+
+        let mut records = records.into_iter().collect::<Vec<(String, Record)>>();
+        records.sort_by(|a, b| a.0.cmp(&b.0));
+        records
+    }
+
+    /// Ranks one division from its scored qualification matches
+    pub fn compute_rankings(&self, matches: &[Match], official: &[Ranking]) -> Vec<ComputedRanking>
+    {
+        let mut records = self.get_records(matches, official);
+        records.sort_by(|a, b| self.compare(&a.1, &b.1).then_with(|| number_order(&a.0).cmp(&number_order(&b.0))));
+
+        records.into_iter()
+            .enumerate()
+            .map(|(index, (team, record))| ComputedRanking { rank: index as u16 + 1, team, record })
+            .collect()
+    }
+}
+
+pub struct ComputedRanking
+{
+    pub rank: u16,
+    pub team: String,
+    pub record: Record
+}
+
+impl fmt::Display for ComputedRanking
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let record = &self.record;
+        write!(f, "#{} {} {}-{}-{} WP {} AP {} SP {} high {}",
+            self.rank, self.team, record.wins, record.losses, record.ties,
+            record.wp, record.ap, record.sp, record.high_score)
+    }
+}
+
+/// Lines describing where computed rankings disagree with the official ones
+///
+/// A team with more official matches than scored ones points at results
+/// missing from the match feed; fewer points at rankings that lag behind
+pub fn describe_differences(computed: &[ComputedRanking], official: &[Ranking]) -> Vec<String>
+{
+    let mut differences = Vec::new();
+    for ranking in computed
+    {
+        let official_ranking = match official.iter().find(|other| other.team.eq_ignore_ascii_case(&ranking.team))
+        {
+            Some(official_ranking) => official_ranking,
+            None => {
+                differences.push(format!("{}: not in the official rankings", ranking.team));
+                continue;
+            }
+        };
+
+        let record = &ranking.record;
+        let official_played = official_ranking.wins + official_ranking.losses + official_ranking.ties;
+        let mut notes = Vec::new();
+        if ranking.rank != official_ranking.rank
+        {
+            notes.push(format!("rank #{} vs official #{}", ranking.rank, official_ranking.rank));
+        }
+        if (record.wins, record.losses, record.ties) != (official_ranking.wins, official_ranking.losses, official_ranking.ties)
+        {
+            notes.push(format!("record {}-{}-{} vs official {}-{}-{}", record.wins, record.losses, record.ties,
+                official_ranking.wins, official_ranking.losses, official_ranking.ties));
+        }
+        if record.wp != official_ranking.wp as u32
+        {
+            notes.push(format!("WP {} vs official {}", record.wp, official_ranking.wp));
+        }
+        if record.sp != official_ranking.sp as u32
+        {
+            notes.push(format!("SP {} vs official {}", record.sp, official_ranking.sp));
+        }
+        if official_played > record.played
+        {
+            notes.push(format!("{} scored match(es) missing from the match feed", official_played - record.played));
+        }
+        else if official_played < record.played
+        {
+            notes.push(format!("official rankings are {} match(es) behind", record.played - official_played));
+        }
+
+        if !notes.is_empty()
+        {
+            differences.push(format!("{}: {}", ranking.team, notes.join("; ")));
+        }
+    }
+
+    for official_ranking in official
+    {
+        if !computed.iter().any(|ranking| ranking.team.eq_ignore_ascii_case(&official_ranking.team))
+        {
+            differences.push(format!("{}: ranked officially but has no qualification matches", official_ranking.team));
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn ranks_by_the_tiebreak_chain_then_number()
+    {
+        let matches = vec![
+            testing::qualification(1, &["1A", "2A"], &["9A", "10A"], Some((30, 20))),
+            testing::qualification(2, &["1A", "9A"], &["2A", "10A"], Some((15, 15))),
+            testing::qualification(3, &["1A", "10A"], &["2A", "9A"], None)
+        ];
+        // autonomous points only come from the official rankings
+        let official = Ranking
+        {
+            sku: testing::SKU.to_owned(),
+            division: String::from("Div"),
+            team: String::from("2A"),
+            rank: 1,
+            wins: 1,
+            losses: 0,
+            ties: 1,
+            wp: 3,
+            ap: 4,
+            sp: 35,
+            max_score: 30,
+            opr: 0.0,
+            dpr: 0.0,
+            ccwm: 0.0
+        };
+
+        let rankings = testing::ruleset().compute_rankings(&matches, &[official]);
+        let order = rankings.iter().map(|ranking| (ranking.rank, ranking.team.as_str())).collect::<Vec<(u16, &str)>>();
+        // 9A and 10A are level on everything, so number order puts 9A first
+        assert_eq!(order, vec![(1, "2A"), (2, "1A"), (3, "9A"), (4, "10A")]);
+
+        let record = &rankings[1].record;
+        assert_eq!((record.wins, record.losses, record.ties), (1, 0, 1));
+        assert_eq!((record.wp, record.sp, record.high_score, record.played), (3, 35, 30, 2));
+        assert_eq!(rankings[3].record.wp, 1);
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::model::{Color, Event, Match, Round};
+use crate::stats;
+
+/// Rating every team starts the season with
+pub const INITIAL_ELO: f64 = 1500.0;
+
+/// TrueSkill's defaults: skill starts at 25 with a deviation of 25/3, one
+/// match's performance varies by half that, and skill drifts slightly between
+/// matches so ratings never become completely certain
+const INITIAL_MEAN: f64 = 25.0;
+const INITIAL_DEVIATION: f64 = INITIAL_MEAN / 3.0;
+const PERFORMANCE_DEVIATION: f64 = INITIAL_DEVIATION / 2.0;
+const SKILL_DRIFT: f64 = INITIAL_DEVIATION / 100.0;
+/// Ties are rare in VRC
+const TIE_PROBABILITY: f64 = 0.02;
+
+/// The ways VexScout can rate a team's strength
+#[derive(Clone, Copy, PartialEq)]
+pub enum RatingModel
+{
+    /// Least squares contributions at one event
+    Opr,
+    /// Season-long Elo
+    Elo,
+    /// Season-long Gaussian skill with uncertainty
+    TrueSkill
+}
+
+impl RatingModel
+{
+    pub fn parse(name: &str) -> Option<RatingModel>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "opr" => Some(RatingModel::Opr),
+            "elo" => Some(RatingModel::Elo),
+            "trueskill" | "bayes" | "bayesian" => Some(RatingModel::TrueSkill),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            RatingModel::Opr => "OPR",
+            RatingModel::Elo => "Elo",
+            RatingModel::TrueSkill => "TrueSkill"
+        }
+    }
+}
+
+/// Ratings built up one match at a time over a season
+pub trait MatchRating
+{
+    fn add_match(&mut self, match_struct: &Match);
+
+    /// Probability that red beats blue
+    fn red_win_probability(&self, match_struct: &Match) -> f64;
+
+    /// Feeds one event's matches in schedule order; unscored and practice matches are skipped
+    fn add_event(&mut self, matches: &[Match])
+    {
+        let mut matches = matches.iter()
+            .filter(|match_struct| match_struct.scored && match_struct.round != Round::Practice)
+            .collect::<Vec<&Match>>();
+        matches.sort_by_key(|match_struct| match_struct.schedule_order());
+
+        for match_struct in matches
+        {
+            self.add_match(match_struct);
+        }
+    }
+}
+
+/// Only the most recent season among `events` counts; the rest are dropped
+/// and the remaining events are returned in the order they were played
+pub fn season_events(mut events: Vec<Event>) -> Vec<Event>
+{
+    let latest_season = events.iter()
+        .max_by(|a, b| a.start.cmp(&b.start))
+        .map(|event| event.season.clone());
+    events.retain(|event| Some(&event.season) == latest_season.as_ref());
+    events.sort_by(|a, b| a.start.cmp(&b.start).then(a.sku.cmp(&b.sku)));
+    events.dedup_by(|a, b| a.sku.eq_ignore_ascii_case(&b.sku));
+    events
+}
+
+/// Largest change a single even match can make, before margin scaling
+const K_FACTOR: f64 = 12.0;
+
+/// A team's rating after one of its matches
+pub struct EloPoint
+{
+    pub sku: String,
+    pub match_name: String,
+    pub rating: f64
+}
+
+impl fmt::Display for EloPoint
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} {:<8} {:.1}", self.sku, self.match_name, self.rating)
+    }
+}
+
+/// Season-long Elo ratings along with every team's trajectory
+///
+/// An alliance is rated as the sum of its playing members, and every member
+/// gains or loses what the alliance does, scaled by the margin of victory
+#[derive(Default)]
+pub struct EloRatings
+{
+    ratings: HashMap<String, f64>,
+    history: HashMap<String, Vec<EloPoint>>
+}
+
+impl EloRatings
+{
+    pub fn get_rating(&self, team_name: &str) -> f64
+    {
+        self.ratings.get(&team_name.to_ascii_uppercase()).copied().unwrap_or(INITIAL_ELO)
+    }
+
+    pub fn get_history(&self, team_name: &str) -> &[EloPoint]
+    {
+        self.history.get(&team_name.to_ascii_uppercase()).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    fn alliance_rating(&self, match_struct: &Match, color: Color) -> f64
+    {
+        match_struct.alliance(color).playing().map(|team| self.get_rating(team)).sum()
+    }
+
+    /// Every rated team, best first
+    pub fn leaderboard(&self) -> Vec<(&String, f64)>
+    {
+        let mut leaderboard = self.ratings.iter().map(|(team, rating)| (team, *rating)).collect::<Vec<(&String, f64)>>();
+        leaderboard.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        leaderboard
+    }
+}
+
+impl MatchRating for EloRatings
+{
+    fn red_win_probability(&self, match_struct: &Match) -> f64
+    {
+        let difference = self.alliance_rating(match_struct, Color::Blue) - self.alliance_rating(match_struct, Color::Red);
+        1.0 / (1.0 + 10f64.powf(difference / 400.0))
+    }
+
+    fn add_match(&mut self, match_struct: &Match)
+    {
+        let expected = self.red_win_probability(match_struct);
+        let margin = match_struct.red.score as f64 - match_struct.blue.score as f64;
+        let actual = if margin > 0.0 { 1.0 } else if margin < 0.0 { 0.0 } else { 0.5 };
+
+        // FiveThirtyEight's margin of victory multiplier, damped when the
+        // favourite wins so strong teams do not run away with their rating
+        let multiplier = if margin == 0.0
+        {
+            1.0
+        }
+        else
+        {
+            let winner_advantage = (self.alliance_rating(match_struct, Color::Red) - self.alliance_rating(match_struct, Color::Blue)) * margin.signum();
+            (margin.abs() + 1.0).ln() * 2.2 / (winner_advantage.max(0.0) * 0.001 + 2.2)
+        };
+        let red_change = K_FACTOR * multiplier * (actual - expected);
+
+        for (color, change) in &[(Color::Red, red_change), (Color::Blue, -red_change)]
+        {
+            let teams = match_struct.alliance(*color).playing().map(|team| team.to_ascii_uppercase()).collect::<Vec<String>>();
+            for team in teams
+            {
+                let rating = self.get_rating(&team) + change;
+                self.ratings.insert(team.clone(), rating);
+                self.history.entry(team).or_default().push(EloPoint
+                {
+                    sku: match_struct.sku.clone(),
+                    match_name: match_struct.name(),
+                    rating
+                });
+            }
+        }
+    }
+}
+
+/// A team's skill as a normal distribution
+#[derive(Clone, Copy)]
+pub struct SkillEstimate
+{
+    pub mean: f64,
+    pub variance: f64,
+    /// Matches the estimate is based on
+    pub matches: u16
+}
+
+impl SkillEstimate
+{
+    pub fn deviation(&self) -> f64
+    {
+        self.variance.sqrt()
+    }
+
+    /// The range the team's true skill lies in with the given confidence, e.g. 0.95
+    pub fn interval(&self, confidence: f64) -> (f64, f64)
+    {
+        let half_width = stats::normal_quantile(0.5 + confidence / 2.0) * self.deviation();
+        (self.mean - half_width, self.mean + half_width)
+    }
+
+    /// A skill the team almost certainly has, used to rank teams so that a
+    /// lucky few matches do not put a team at the top
+    pub fn conservative(&self) -> f64
+    {
+        self.mean - 3.0 * self.deviation()
+    }
+}
+
+impl Default for SkillEstimate
+{
+    fn default() -> SkillEstimate
+    {
+        SkillEstimate { mean: INITIAL_MEAN, variance: INITIAL_DEVIATION * INITIAL_DEVIATION, matches: 0 }
+    }
+}
+
+impl fmt::Display for SkillEstimate
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let (low, high) = self.interval(0.95);
+        write!(f, "{:.1} ± {:.1} (95% {:.1} to {:.1}, {} matches)", self.mean, self.deviation(), low, high, self.matches)
+    }
+}
+
+/// TrueSkill-style ratings: every team's skill is a Gaussian, an alliance
+/// performs at the sum of its playing members' skills plus noise, and each
+/// result narrows the skills of everyone involved
+#[derive(Default)]
+pub struct TrueSkillRatings
+{
+    skills: HashMap<String, SkillEstimate>
+}
+
+impl TrueSkillRatings
+{
+    pub fn get_skill(&self, team_name: &str) -> SkillEstimate
+    {
+        self.skills.get(&team_name.to_ascii_uppercase()).copied().unwrap_or_default()
+    }
+
+    /// Mean and variance of an alliance's performance
+    fn alliance_performance(&self, match_struct: &Match, color: Color) -> (f64, f64, usize)
+    {
+        match_struct.alliance(color).playing()
+            .map(|team| self.get_skill(team))
+            .fold((0.0, 0.0, 0), |(mean, variance, players), skill|
+            {
+                (mean + skill.mean, variance + skill.variance + PERFORMANCE_DEVIATION * PERFORMANCE_DEVIATION, players + 1)
+            })
+    }
+
+    /// Every rated team, best conservative skill first
+    pub fn leaderboard(&self) -> Vec<(&String, SkillEstimate)>
+    {
+        let mut leaderboard = self.skills.iter().map(|(team, skill)| (team, *skill)).collect::<Vec<(&String, SkillEstimate)>>();
+        leaderboard.sort_by(|a, b| b.1.conservative().total_cmp(&a.1.conservative()).then(a.0.cmp(b.0)));
+        leaderboard
+    }
+}
+
+impl MatchRating for TrueSkillRatings
+{
+    fn red_win_probability(&self, match_struct: &Match) -> f64
+    {
+        let (red_mean, red_variance, _) = self.alliance_performance(match_struct, Color::Red);
+        let (blue_mean, blue_variance, _) = self.alliance_performance(match_struct, Color::Blue);
+        stats::normal_cdf((red_mean - blue_mean) / (red_variance + blue_variance).max(f64::EPSILON).sqrt())
+    }
+
+    fn add_match(&mut self, match_struct: &Match)
+    {
+        // skill may have drifted since the team's last match
+        let teams = match_struct.red.playing().chain(match_struct.blue.playing())
+            .map(|team| team.to_ascii_uppercase())
+            .collect::<Vec<String>>();
+        for team in &teams
+        {
+            self.skills.entry(team.clone()).or_default().variance += SKILL_DRIFT * SKILL_DRIFT;
+        }
+
+        let (red_mean, red_variance, red_players) = self.alliance_performance(match_struct, Color::Red);
+        let (blue_mean, blue_variance, blue_players) = self.alliance_performance(match_struct, Color::Blue);
+        let players = (red_players + blue_players) as f64;
+        let total_deviation = (red_variance + blue_variance).sqrt();
+        if total_deviation <= 0.0
+        {
+            return;
+        }
+
+        let tie_margin = stats::normal_quantile((TIE_PROBABILITY + 1.0) / 2.0) * players.sqrt() * PERFORMANCE_DEVIATION / total_deviation;
+        let red_advantage = (red_mean - blue_mean) / total_deviation;
+
+        // v moves the means and w shrinks the variances, from red's point of view
+        let (v, w) = match match_struct.winner()
+        {
+            Some(color) => {
+                let sign = if color == Color::Red { 1.0 } else { -1.0 };
+                let t = red_advantage * sign - tie_margin;
+                let v = stats::normal_pdf(t) / stats::normal_cdf(t).max(f64::MIN_POSITIVE);
+                (v * sign, v * (v + t))
+            },
+            None => {
+                let t = red_advantage;
+                let probability = (stats::normal_cdf(tie_margin - t) - stats::normal_cdf(-tie_margin - t)).max(f64::MIN_POSITIVE);
+                let v = (stats::normal_pdf(-tie_margin - t) - stats::normal_pdf(tie_margin - t)) / probability;
+                let w = v * v + ((tie_margin - t) * stats::normal_pdf(tie_margin - t) + (tie_margin + t) * stats::normal_pdf(tie_margin + t)) / probability;
+                (v, w)
+            }
+        };
+
+        for (color, direction) in &[(Color::Red, 1.0), (Color::Blue, -1.0)]
+        {
+            let teams = match_struct.alliance(*color).playing().map(|team| team.to_ascii_uppercase()).collect::<Vec<String>>();
+            for team in teams
+            {
+                let skill = self.skills.entry(team).or_default();
+                let share = skill.variance / total_deviation;
+                skill.mean += direction * share * v;
+                skill.variance *= (1.0 - skill.variance / (total_deviation * total_deviation) * w).max(f64::EPSILON);
+                skill.matches += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn elo_moves_by_the_margin_multiplier()
+    {
+        let mut ratings = EloRatings::default();
+        ratings.add_event(&[
+            testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 20))),
+            testing::qualification(2, &["1A", "3A"], &["2A", "4A"], None)
+        ]);
+
+        // an even match won by 10 moves each team by K / 2 * ln(11)
+        let change = K_FACTOR / 2.0 * 11f64.ln();
+        assert!((ratings.get_rating("1a") - (INITIAL_ELO + change)).abs() < 1e-9);
+        assert!((ratings.get_rating("4A") - (INITIAL_ELO - change)).abs() < 1e-9);
+        assert!((ratings.get_rating("1A") - 1514.387).abs() < 1e-3);
+        assert_eq!(ratings.get_history("2A").len(), 1);
+        assert_eq!(ratings.get_history("2A")[0].match_name, "Q1");
+
+        let rematch = testing::qualification(3, &["1A", "2A"], &["3A", "4A"], None);
+        assert!(ratings.red_win_probability(&rematch) > 0.5);
+    }
+
+    #[test]
+    fn elo_ties_between_equals_change_nothing()
+    {
+        let mut ratings = EloRatings::default();
+        ratings.add_match(&testing::qualification(1, &["1A"], &["2A"], Some((15, 15))));
+        assert_eq!(ratings.get_rating("1A"), INITIAL_ELO);
+        assert_eq!(ratings.get_rating("2A"), INITIAL_ELO);
+    }
+
+    #[test]
+    fn trueskill_matches_the_one_on_one_update()
+    {
+        // the standard two player update for a 2% draw chance: 29.243 ± 7.190 and 20.757 ± 7.190
+        let mut ratings = TrueSkillRatings::default();
+        ratings.add_match(&testing::qualification(1, &["1A"], &["2A"], Some((20, 10))));
+
+        let (winner, loser) = (ratings.get_skill("1A"), ratings.get_skill("2A"));
+        assert!((winner.mean - 29.2432).abs() < 1e-3, "winner {}", winner);
+        assert!((loser.mean - 20.7568).abs() < 1e-3, "loser {}", loser);
+        assert!((winner.deviation() - 7.1901).abs() < 1e-3);
+        assert!((loser.deviation() - 7.1901).abs() < 1e-3);
+        assert_eq!(winner.matches, 1);
+
+        let rematch = testing::qualification(2, &["1A"], &["2A"], None);
+        assert!(ratings.red_win_probability(&rematch) > 0.5);
+        assert_eq!(ratings.leaderboard()[0].0, "1A");
+    }
+}
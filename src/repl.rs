@@ -1,51 +1,263 @@
 
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 // use serde_json::Result;
 use std::io::{self, Write};
 use std::fmt;
 
-use regex::Regex;
+use crate::bracket::{self, Bracket};
+use crate::cache::{self, Cache, CachedSource};
+use crate::command::{self, Action, ArgumentKind, CommandSet, Invocation, team_name_is_valid};
+use crate::error::ReplError;
+use crate::line_editor::LineEditor;
+use crate::match_list::{self, MatchFilter, MatchList};
+use crate::model::{Color, Event, Match, Ranking, Round, SkillType};
+use crate::notes::NoteBook;
+use crate::picklist::{Candidate, PickList, PickStatus, TeamMetrics};
+use crate::offline::{self, EventPack, OfflineSource};
+use crate::predict::{Calibration, Predictor};
+use crate::ranking::{self, Ruleset};
+use crate::rating::{self, EloRatings, MatchRating, RatingModel, TrueSkillRatings};
+use crate::simulate::{self, TeamOutlook};
+use crate::skills::{self, EventSkills};
+use crate::robotevents::{self, RobotEventsClient};
+use crate::schedule::{self, ScheduleStrength};
+use crate::source::{DataSource, DataSourceKind, FixtureSource, Query};
+use crate::stats::{self, PowerRating, PowerStat};
+use crate::vexdb::{self, VexDbClient};
 
-use lazy_static::{lazy_static};
+/// The organization number of a team, e.g. `1234` for `1234A`
+fn organization_of(team_name: &str) -> &str
+{
+    team_name.trim_end_matches(|character: char| character.is_ascii_alphabetic())
+}
+
+/// Whether the team is one of the organization's lettered teams
+fn team_in_organization(team_name: &str, organization_name: &str) -> bool
+{
+    team_name.len() > organization_name.len()
+        && organization_of(team_name).eq_ignore_ascii_case(organization_name)
+}
+
+fn print_list<T: fmt::Display>(items: &[T])
+{
+    if items.is_empty()
+    {
+        println!("(none)");
+    }
+
+    for item in items
+    {
+        println!("{}", item);
+    }
+}
+
+/// Numbered pick list rows
+fn print_candidates(candidates: &[Candidate])
+{
+    if candidates.is_empty()
+    {
+        println!("(none)");
+    }
+
+    for (index, candidate) in candidates.iter().enumerate()
+    {
+        println!("{:>3}. {}", index + 1, candidate);
+    }
+}
+
+/// Lists matches from one team's point of view, e.g.
+/// `Q12      W 10-5  with 1234B vs 5678A 5678B`
+fn print_team_matches(matches: &[Match], team_name: &str)
+{
+    if matches.is_empty()
+    {
+        println!("(none)");
+    }
+
+    for match_struct in matches
+    {
+        let color = if let Some(color) = match_struct.alliance_of(team_name)
+        {
+            color
+        }
+        else
+        {
+            continue;
+        };
+
+        let result = if !match_struct.scored
+        {
+            String::from("-")
+        }
+        else if match_struct.is_tie()
+        {
+            String::from("T")
+        }
+        else if match_struct.won_by(team_name)
+        {
+            String::from("W")
+        }
+        else
+        {
+            String::from("L")
+        };
+
+        let score = if match_struct.scored
+        {
+            format!("{}-{}", match_struct.alliance(color).score, match_struct.alliance(color.opposite()).score)
+        }
+        else
+        {
+            String::from("unscored")
+        };
+
+        let partner = match_struct.partner_of(team_name).map(String::as_str).unwrap_or("-");
+        let opponents = match_struct.opponents_of(team_name).iter().map(|team| team.as_str()).collect::<Vec<&str>>();
+        let margin = match_struct.margin_for(team_name).filter(|_| match_struct.scored)
+            .map(|margin| format!(" ({:+})", margin))
+            .unwrap_or_default();
+
+        println!("{:<8} {:<4} {} {:<14} with {} vs {}",
+            match_struct.name(), color, result, score + &margin, partner, opponents.join(" "));
+    }
+}
+
+/// Prints the next page of a match list with the row numbers `select` takes
+fn print_match_page(match_list: &mut MatchList)
+{
+    let page = match_list.next_page();
+    let page_count = match_list.page_count();
+    let visible = match_list.visible();
+
+    if visible.is_empty()
+    {
+        println!("(none)");
+        return;
+    }
+
+    let start = page * match_list::PAGE_SIZE;
+    for (index, match_struct) in visible.iter().enumerate().skip(start).take(match_list::PAGE_SIZE)
+    {
+        println!("{:>3}. {}", index + 1, match_struct);
+    }
+    if page_count > 1
+    {
+        println!("page {}/{}", page + 1, page_count);
+    }
+}
+
+/// Division and round that queries inside a competition are narrowed to by
+/// `DivisionContext` and `RoundContext`
+#[derive(Default)]
+struct Scope
+{
+    division: Option<String>,
+    round: Option<Round>
+}
+
+impl Scope
+{
+    fn includes_match(&self, match_struct: &Match) -> bool
+    {
+        self.includes_division(match_struct) && self.round.is_none_or(|round| round == match_struct.round)
+    }
+
+    fn includes_division(&self, match_struct: &Match) -> bool
+    {
+        self.division.as_ref().is_none_or(|division| division.eq_ignore_ascii_case(&match_struct.division))
+    }
+
+    /// Rankings are per division; some data sources leave the division blank
+    fn includes_ranking(&self, ranking: &Ranking) -> bool
+    {
+        self.division.as_ref().is_none_or(|division| ranking.division.is_empty() || division.eq_ignore_ascii_case(&ranking.division))
+    }
+}
+
+/// Finds a division by its full name or an unambiguous prefix, e.g. `sci` for `Science`
+fn find_division<'a>(divisions: &'a [String], name: &str) -> Option<&'a String>
+{
+    let name = name.to_ascii_lowercase();
+    if let Some(division) = divisions.iter().find(|division| division.to_ascii_lowercase() == name)
+    {
+        return Some(division);
+    }
+
+    let mut candidates = divisions.iter().filter(|division| division.to_ascii_lowercase().starts_with(&name));
+    match (candidates.next(), candidates.next())
+    {
+        (Some(division), None) => Some(division),
+        _ => None
+    }
+}
+
+/// Teams shown by rating leaderboards
+const LEADERBOARD_SIZE: usize = 25;
+
+/// Best first, which for DPR means lowest first
+fn sort_power_ratings(ratings: &mut [PowerRating], stat: PowerStat)
+{
+    ratings.sort_by(|a, b|
+    {
+        let order = b.get(stat).total_cmp(&a.get(stat));
+        if stat.higher_is_better() { order } else { order.reverse() }
+    });
+}
 
-fn team_name_is_valid(name: &str) -> bool
+/// Prints power ratings as a leaderboard for one stat, or every stat by OPR
+fn print_power_ratings(ratings: &mut [PowerRating], stat: Option<PowerStat>)
 {
-    lazy_static! {
-        static ref REGEX_MATCHER: Regex = Regex::new(r"[0-9]+[a-zA-Z]").unwrap();
+    if let Some(stat) = stat
+    {
+        sort_power_ratings(ratings, stat);
+    }
+
+    for (index, rating) in ratings.iter().enumerate()
+    {
+        let caveat = if rating.is_reliable() { "" } else { " *" };
+        match stat
+        {
+            Some(stat) => println!("{:>3}. {:<7} {} {:>6.2}{}", index + 1, rating.team, stat.name(), rating.get(stat), caveat),
+            None => println!("{:>3}. {}{}", index + 1, rating, caveat)
+        }
+    }
+
+    if ratings.iter().any(|rating| !rating.is_reliable())
+    {
+        println!("* fewer than {} matches played", stats::MIN_RATED_MATCHES);
     }
-    REGEX_MATCHER.is_match(name)
 }
 
 pub struct ReplInterface
 {
     config: ReplConfiguration,
-    contexts: Vec<ProgramContext>
+    contexts: Vec<ProgramContext>,
+    source: Box<dyn DataSource>,
+    cache: Option<Cache>,
+    notes: NoteBook,
+    rulesets: Vec<Ruleset>,
+    /// `None` when input is not a terminal
+    editor: Option<LineEditor>,
+    /// The stack before its last change, for `back`
+    previous_contexts: Option<Vec<ProgramContext>>
 }
 
 impl ReplInterface
 {
-    pub fn new(configuration_file_path: &Option<&Path>) -> ReplInterface
+    /// In offline mode all data comes from event packs and the on-disk cache
+    pub fn new(configuration_file_path: &Option<&Path>, offline: bool) -> ReplInterface
     {
         let configuration = if let Some(path) = configuration_file_path {
             let file = std::fs::File::open(path);
             if let Ok(file) = file
             {
-                println!("File is ok!");
-                let json = serde_json::from_reader(file);
-                if let Ok(config) = json
-                {
-                    config
-                }
-                else
-                {
-                    ReplConfiguration::default()
-                }
+                serde_json::from_reader(file).unwrap_or_default()
             }
             else
             {
-                println!("File not ok!");
                 ReplConfiguration::default()
             }
         }
@@ -54,16 +266,89 @@ impl ReplInterface
             ReplConfiguration::default()
         };
 
-        ReplInterface
+        let is_fixture = *configuration.get_data_source() == DataSourceKind::Fixture;
+        let cache = match Cache::default_path()
         {
-            config: configuration,
-            contexts: Vec::<ProgramContext>::new() 
+            Some(path) if !is_fixture => {
+                Cache::open(&path).map_err(|error| println!("{}", error)).ok()
+            },
+            _ => None
+        };
+
+        if offline
+        {
+            let packs = EventPack::default_directory()
+                .map(|directory| EventPack::load_directory(&directory))
+                .unwrap_or_default();
+            println!("Offline mode: {} event pack(s) loaded", packs.len());
+
+            let source = Box::new(OfflineSource::new(packs, cache.clone(), *configuration.get_data_source()));
+            let mut repl_interface = ReplInterface::with_data_source(configuration, source);
+            repl_interface.cache = cache;
+            return repl_interface.with_user_files();
+        }
+
+        let source: Box<dyn DataSource> = match configuration.get_data_source()
+        {
+            DataSourceKind::VexDb => {
+                Box::new(VexDbClient::new(configuration.get_vexdb_base_url()))
+            },
+            DataSourceKind::RobotEvents => {
+                Box::new(RobotEventsClient::new(configuration.get_robotevents_base_url(), configuration.get_robotevents_token()))
+            },
+            DataSourceKind::Fixture => {
+                match FixtureSource::load(Path::new(configuration.get_fixture_path()))
+                {
+                    Ok(fixture) => Box::new(fixture),
+                    Err(error) => {
+                        println!("{}", error);
+                        Box::new(FixtureSource::default())
+                    }
+                }
+            }
+        };
+
+        let repl_interface = if let Some(cache) = cache
+        {
+            let cached_source = Box::new(CachedSource::new(cache.clone(), *configuration.get_data_source(), source));
+            let mut repl_interface = ReplInterface::with_data_source(configuration, cached_source);
+            repl_interface.cache = Some(cache);
+            repl_interface
         }
+        else
+        {
+            ReplInterface::with_data_source(configuration, source)
+        };
+        repl_interface.with_user_files()
+    }
+
+    /// Loads the user's notes and rulesets and sets up line editing
+    fn with_user_files(mut self) -> ReplInterface
+    {
+        self.notes = NoteBook::load();
+        self.rulesets = Ruleset::load_all();
+        self.editor = LineEditor::new(self.cache.clone());
+        self
     }
 
-    pub fn get_configuration(&self) -> &ReplConfiguration
+    /// Builds an interface around an already constructed data source,
+    /// e.g. a `FixtureSource` when no network is available
+    ///
+    /// Nothing is read from the config directory: notes start empty and only
+    /// the built in rulesets are known
+    pub fn with_data_source(configuration: ReplConfiguration, source: Box<dyn DataSource>) -> ReplInterface
     {
-        &self.config
+        ReplInterface
+        {
+            config: configuration,
+            contexts: Vec::<ProgramContext>::new(),
+            source,
+            cache: None,
+            notes: NoteBook::default(),
+            rulesets: Ruleset::built_in(),
+            editor: None,
+            previous_contexts: None
+        }
     }
 
     pub fn get_contexts(&self) -> &Vec<ProgramContext>
@@ -81,375 +366,1778 @@ impl ReplInterface
         self.contexts.push(context);
     }
 
-    pub fn prompt(&self) -> String
+    /// The configured ruleset, otherwise the one for the competition's season
+    fn get_ruleset(&self, sku: &str) -> Result<&Ruleset, ReplError>
     {
-        let mut user_input = String::new();
+        let season = self.source.get_events(Query::event(sku))?
+            .into_iter()
+            .next()
+            .map(|event| event.season)
+            .unwrap_or_default();
 
-        let contexts = self.get_contexts();
-        if contexts.len() != 0
+        if let Some(ruleset) = Ruleset::select(&self.rulesets, self.config.get_ruleset(), &season)
+        {
+            Ok(ruleset)
+        }
+        else
+        {
+            Err(ReplError::InvalidInput(format!("Unknown ruleset: {}", self.config.get_ruleset())))
+        }
+    }
+
+    /// Computes rankings for each division in scope and prints them, or how
+    /// they differ from the official rankings
+    fn print_computed_rankings(&self, sku: &str, diff: bool) -> Result<(), ReplError>
+    {
+        let ruleset = self.get_ruleset(sku)?;
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(Query::event(sku))?;
+        matches.retain(|match_struct| scope.includes_division(match_struct));
+        let mut rankings = self.source.get_rankings(Query::event(sku))?;
+        rankings.retain(|ranking| scope.includes_ranking(ranking));
+
+        let mut divisions = matches.iter().map(|match_struct| match_struct.division.clone()).collect::<Vec<String>>();
+        divisions.sort();
+        divisions.dedup();
+
+        println!("Ruleset: {}", ruleset.name);
+        for division in &divisions
+        {
+            let division_matches = matches.iter().filter(|match_struct| &match_struct.division == division).cloned().collect::<Vec<Match>>();
+            let division_rankings = rankings.iter()
+                .filter(|ranking| divisions.len() == 1 || ranking.division.is_empty() || ranking.division.eq_ignore_ascii_case(division))
+                .cloned()
+                .collect::<Vec<Ranking>>();
+            let computed = ruleset.compute_rankings(&division_matches, &division_rankings);
+
+            if divisions.len() > 1
+            {
+                println!("{}:", division);
+            }
+            if diff
+            {
+                let differences = ranking::describe_differences(&computed, &division_rankings);
+                if differences.is_empty()
+                {
+                    println!("Computed rankings match the official rankings");
+                }
+                for difference in differences
+                {
+                    println!("{}", difference);
+                }
+            }
+            else
+            {
+                print_list(&computed);
+            }
+        }
+        Ok(())
+    }
+
+    /// The division and round selected anywhere on the context stack
+    fn get_scope(&self) -> Scope
+    {
+        let mut scope = Scope::default();
+        for context in self.get_contexts()
+        {
+            match context
+            {
+                ProgramContext::DivisionContext(division_name) => scope.division = Some(division_name.clone()),
+                ProgramContext::RoundContext(round) => scope.round = Some(*round),
+                _ => ()
+            }
+        }
+        scope
+    }
+
+    /// OPR, DPR and CCWM from the scored qualifications in the current division
+    fn get_power_ratings(&self, sku: &str) -> Result<Vec<PowerRating>, ReplError>
+    {
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(Query::event(sku))?;
+        matches.retain(|match_struct| scope.includes_division(match_struct));
+
+        let ratings = stats::power_ratings(&matches);
+        if ratings.is_empty()
+        {
+            return Err(ReplError::InvalidInput(format!("No scored qualification matches at {}", sku)));
+        }
+        Ok(ratings)
+    }
+
+    /// Rates every scored match of the latest season among `events`
+    ///
+    /// Only these events are rated, so opponents' ratings reflect just the
+    /// matches they played at them. Events that fail to load are skipped.
+    fn get_season_ratings<R: MatchRating + Default>(&self, events: Vec<Event>) -> Result<(R, Vec<Event>), ReplError>
+    {
+        let events = rating::season_events(events);
+        if events.is_empty()
+        {
+            return Err(ReplError::InvalidInput(String::from("No events found")));
+        }
+
+        let mut ratings = R::default();
+        for event in &events
+        {
+            match self.source.get_matches(Query::event(&event.sku))
+            {
+                Ok(matches) => ratings.add_event(&matches),
+                Err(error) => println!("skipping {}: {}", event.sku, error)
+            }
+        }
+        Ok((ratings, events))
+    }
+
+    /// A team's season-long Elo or TrueSkill rating
+    fn print_team_rating(&self, team_name: &str, model: RatingModel) -> Result<(), ReplError>
+    {
+        let events = self.source.get_events(Query::team(team_name))?;
+
+        if model == RatingModel::TrueSkill
+        {
+            let (ratings, _) = self.get_season_ratings::<TrueSkillRatings>(events)?;
+            let leaderboard = ratings.leaderboard();
+            println!("{} skill {}", team_name, ratings.get_skill(team_name));
+            if let Some(place) = leaderboard.iter().position(|(team, _)| team.eq_ignore_ascii_case(team_name))
+            {
+                println!("#{} of {} teams at the same events", place + 1, leaderboard.len());
+            }
+            return Ok(());
+        }
+
+        let (elo, events) = self.get_season_ratings::<EloRatings>(events)?;
+
+        let leaderboard = elo.leaderboard();
+        let place = leaderboard.iter().position(|(team, _)| team.eq_ignore_ascii_case(team_name));
+        println!("{} Elo {:.1}", team_name, elo.get_rating(team_name));
+        if let Some(place) = place
+        {
+            println!("#{} of {} teams at the same events", place + 1, leaderboard.len());
+        }
+
+        // the trajectory, summarized as the rating after each event
+        let history = elo.get_history(team_name);
+        let mut previous = rating::INITIAL_ELO;
+        for event in &events
+        {
+            let points = history.iter().filter(|point| point.sku.eq_ignore_ascii_case(&event.sku)).collect::<Vec<_>>();
+            if let Some(last) = points.last()
+            {
+                println!("  {} {:<30} {:>7.1} ({:+.1} over {} matches)", event.start.get(..10).unwrap_or(&event.start), event.name, last.rating, last.rating - previous, points.len());
+                previous = last.rating;
+            }
+        }
+        Ok(())
+    }
+
+    /// Season-long Elo or TrueSkill leaderboard for the teams at a region's events
+    fn print_region_ratings(&self, region: &str, model: RatingModel) -> Result<(), ReplError>
+    {
+        let events = self.source.get_events(Query::region(region))?;
+
+        let (rated, event_count) = if model == RatingModel::TrueSkill
         {
-            for context in contexts
+            let (ratings, events) = self.get_season_ratings::<TrueSkillRatings>(events)?;
+            let leaderboard = ratings.leaderboard();
+            for (index, (team, skill)) in leaderboard.iter().take(LEADERBOARD_SIZE).enumerate()
             {
-                print!("{}", context);
+                println!("{:>3}. {:<7} {}", index + 1, team, skill);
             }
+            (leaderboard.len(), events.len())
         }
         else
         {
-            print!("> ");
+            let (elo, events) = self.get_season_ratings::<EloRatings>(events)?;
+            let leaderboard = elo.leaderboard();
+            for (index, (team, rating)) in leaderboard.iter().take(LEADERBOARD_SIZE).enumerate()
+            {
+                println!("{:>3}. {:<7} {:.1}", index + 1, team, rating);
+            }
+            (leaderboard.len(), events.len())
+        };
+        println!("{} teams rated by {} across {} events in {}", rated, model.name(), event_count, region);
+        Ok(())
+    }
+
+    /// A team's best skills runs this season and its runs at each event
+    fn print_team_skills(&self, team_name: &str) -> Result<(), ReplError>
+    {
+        let events = rating::season_events(self.source.get_events(Query::team(team_name))?);
+        let mut history = skills::event_skills(&self.source.get_skills(Query::team(team_name))?);
+        if !events.is_empty()
+        {
+            let position = |sku: &str| events.iter().position(|event| event.sku.eq_ignore_ascii_case(sku));
+            history.retain(|event| position(&event.sku).is_some());
+            history.sort_by_key(|event| position(&event.sku));
         }
 
-        let _ = io::stdout().flush();
-        io::stdin().read_line(&mut user_input)
-            .expect("Error getting input");
-        user_input
+        if history.is_empty()
+        {
+            println!("No skills runs this season for {}", team_name);
+            return Ok(());
+        }
+
+        let best = |score: &dyn Fn(&EventSkills) -> u16| history.iter()
+            .max_by_key(|event| score(event))
+            .map(|event| format!("{} at {}", score(event), event.sku))
+            .unwrap_or_default();
+        println!("Best driver {}, programming {}, combined {}",
+            best(&|event| event.driver), best(&|event| event.programming), best(&EventSkills::get_combined));
+        println!("Attempts: driver {}, programming {}",
+            history.iter().map(|event| event.driver_attempts).sum::<u16>(),
+            history.iter().map(|event| event.programming_attempts).sum::<u16>());
+        print_list(&history);
+        Ok(())
+    }
+
+    /// Season skills leaderboard for teams from a state or province, with the
+    /// combined score currently needed for one of `spots` World Championship spots
+    ///
+    /// Events or teams that fail to load are skipped.
+    fn print_region_skills(&self, region: &str, spots: usize) -> Result<(), ReplError>
+    {
+        let events = rating::season_events(self.source.get_events(Query::region(region))?);
+        if events.is_empty()
+        {
+            return Err(ReplError::InvalidInput(format!("No events found in {}", region)));
+        }
+
+        let mut season_skills = Vec::new();
+        let mut qualified = HashSet::new();
+        for event in &events
+        {
+            season_skills.extend(self.source.get_skills(Query::event(&event.sku)).unwrap_or_default());
+            let awards = self.source.get_awards(Query::event(&event.sku)).unwrap_or_default();
+            qualified.extend(awards.iter()
+                .filter(|award| award.qualifies.iter().any(|qualification| qualification.to_ascii_lowercase().contains("world")))
+                .map(|award| award.team.to_ascii_uppercase()));
+        }
+
+        // events draw teams from neighbouring regions too
+        let mut leaderboard = skills::leaderboard(&season_skills);
+        let numbers = leaderboard.iter().map(|event| event.team.clone()).collect::<Vec<String>>();
+        if let Ok(teams) = self.source.get_teams(&numbers)
+        {
+            let outside = teams.iter()
+                .filter(|team| !team.region.eq_ignore_ascii_case(region))
+                .map(|team| team.number.to_ascii_uppercase())
+                .collect::<HashSet<String>>();
+            leaderboard.retain(|event| !outside.contains(&event.team));
+        }
+
+        let line = skills::worlds_line(&leaderboard, &qualified, spots);
+        for (index, event) in leaderboard.iter().take(LEADERBOARD_SIZE).enumerate()
+        {
+            let in_line = line.iter().any(|other_event| other_event.team == event.team);
+            let mark = if qualified.contains(&event.team) { "Q" } else if in_line { "*" } else { "" };
+            println!("{:>3}. {} {}", index + 1, event, mark);
+        }
+        println!("{} teams across {} events in {}; Q qualified by award", leaderboard.len(), events.len(), region);
+        match line.last()
+        {
+            Some(last) if line.len() == spots => {
+                println!("* in line for one of {} skills spots; cutoff so far {} combined", spots, last.get_combined());
+            },
+            _ => println!("* in line; fewer than {} teams without a qualification, so every spot is open", spots)
+        }
+        Ok(())
     }
 
+    /// Matches from this season's earlier events for every team at `sku`, one
+    /// list per event in the order they were played
     ///
-    /// Error Codes
-    /// 0: No subcommand specified
-    /// 1: Invalid command
-    /// 2: Unknown Error
-    /// 3: Invalid input
-    /// 4: Missing argument
-    /// 9: Exit
-    pub fn eval(&mut self, input: String) -> Result<(), u8>
-    {
-        // println!("{}", input.chars().rev().skip(1).collect::<String>());
-        let mut words = input.trim().split_ascii_whitespace();
-
-        if let Some(command) = words.next()
-        {
-            let command = command.trim().to_ascii_lowercase();
-            let global = command.eq("global");
-            
-            if !global
+    /// Built from each team's own match history, so it only holds matches
+    /// that involve at least one of the event's teams. Teams whose history
+    /// fails to load are skipped.
+    fn get_prior_events(&self, sku: &str) -> Result<Vec<Vec<Match>>, ReplError>
+    {
+        let event = match self.source.get_events(Query::event(sku))?.into_iter().next()
+        {
+            Some(event) => event,
+            None => {
+                return Err(ReplError::InvalidInput(format!("No such competition: {}", sku)));
+            }
+        };
+
+        let mut teams = self.source.get_matches(Query::event(sku))?
+            .iter()
+            .flat_map(|match_struct| match_struct.teams().map(|team| team.to_ascii_uppercase()).collect::<Vec<String>>())
+            .collect::<Vec<String>>();
+        teams.sort();
+        teams.dedup();
+
+        let mut prior_events: Vec<Event> = Vec::new();
+        let mut matches_by_event: HashMap<String, Vec<Match>> = HashMap::new();
+        for team in &teams
+        {
+            let history = self.source.get_events(Query::team(team))
+                .and_then(|events| Ok((events, self.source.get_matches(Query::team(team))?)));
+            let (events, matches) = match history
+            {
+                Ok(history) => history,
+                Err(error) => {
+                    println!("skipping {}: {}", team, error);
+                    continue;
+                }
+            };
+
+            for other_event in events.into_iter().filter(|other_event| other_event.season == event.season && other_event.start < event.start)
             {
-                if command.eq_ignore_ascii_case("exit")
+                let event_matches = matches_by_event.entry(other_event.sku.to_ascii_uppercase()).or_default();
+                for match_struct in matches.iter().filter(|match_struct| match_struct.sku.eq_ignore_ascii_case(&other_event.sku))
+                {
+                    // partners and opponents at the same event share matches
+                    let key = (&match_struct.division, match_struct.schedule_order());
+                    if !event_matches.iter().any(|other_match| (&other_match.division, other_match.schedule_order()) == key)
+                    {
+                        event_matches.push(match_struct.clone());
+                    }
+                }
+                prior_events.push(other_event);
+            }
+        }
+
+        Ok(rating::season_events(prior_events).into_iter()
+            .filter_map(|prior_event| matches_by_event.remove(&prior_event.sku.to_ascii_uppercase()))
+            .collect())
+    }
+
+    /// Simulates the rest of qualifications in each division in scope
+    ///
+    /// Takes the `runs`, `seed` and `model` keywords of `simulate`; without a
+    /// seed the current time is used and printed so runs can be repeated
+    fn get_simulated_outlooks(&self, sku: &str, invocation: &Invocation) -> Result<Vec<TeamOutlook>, ReplError>
+    {
+        let runs = match invocation.get("runs")
+        {
+            Some(runs) => match runs.parse::<u32>()
+            {
+                Ok(runs) if runs > 0 => runs,
+                _ => {
+                    return Err(ReplError::InvalidInput(format!("runs must be between 1 and {}", u32::MAX)));
+                }
+            },
+            None => simulate::DEFAULT_RUNS
+        };
+        let seed = invocation.get_number("seed").unwrap_or_else(cache::now);
+        let model = invocation.get_model("model").unwrap_or(RatingModel::Opr);
+
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(Query::event(sku))?;
+        matches.retain(|match_struct| scope.includes_division(match_struct));
+        let rankings = self.source.get_rankings(Query::event(sku))?;
+        if !matches.iter().any(|match_struct| match_struct.round == Round::Qualification)
+        {
+            return Err(ReplError::InvalidInput(format!("No qualification matches at {}", sku)));
+        }
+
+        let prior_events = if model == RatingModel::Opr { Vec::new() } else { self.get_prior_events(sku)? };
+        let mut predictor = Predictor::new(model, &prior_events);
+        predictor.add_scored_matches(&matches);
+
+        let mut divisions = matches.iter().map(|match_struct| match_struct.division.clone()).collect::<Vec<String>>();
+        divisions.sort();
+        divisions.dedup();
+
+        let ruleset = self.get_ruleset(sku)?;
+        println!("{} runs with {} under the {} ruleset (seed {})", runs, model.name(), ruleset.name, seed);
+        let mut outlooks = Vec::new();
+        for division in divisions
+        {
+            let division_matches = matches.iter().filter(|match_struct| match_struct.division == division).cloned().collect::<Vec<Match>>();
+            outlooks.extend(simulate::simulate(ruleset, &division_matches, &rankings, &predictor, runs, seed));
+        }
+        Ok(outlooks)
+    }
+
+    /// Strength of schedule for every team in scope, rated by `model` (default OPR)
+    fn get_schedule_strengths(&self, sku: &str, model: Option<RatingModel>) -> Result<Vec<ScheduleStrength>, ReplError>
+    {
+        let model = model.unwrap_or(RatingModel::Opr);
+
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(Query::event(sku))?;
+        matches.retain(|match_struct| scope.includes_division(match_struct));
+        let prior_events = if model == RatingModel::Opr { Vec::new() } else { self.get_prior_events(sku)? };
+
+        let strengths = schedule::schedule_strengths(model, &prior_events, &matches);
+        if strengths.is_empty()
+        {
+            return Err(ReplError::InvalidInput(format!("No scored qualification matches at {}", sku)));
+        }
+        println!("Schedules rated by {}, hardest first", model.name());
+        Ok(strengths)
+    }
+
+    /// Replays a competition with each model, or just the one given
+    fn print_calibration(&self, sku: &str, model: Option<RatingModel>) -> Result<(), ReplError>
+    {
+        let sku = sku.to_ascii_uppercase();
+        let matches = self.source.get_matches(Query::event(&sku))?;
+        if !matches.iter().any(|match_struct| match_struct.scored)
+        {
+            return Err(ReplError::InvalidInput(format!("No scored matches at {}", sku)));
+        }
+
+        let models = match model
+        {
+            Some(model) => vec![model],
+            None => vec![RatingModel::Opr, RatingModel::Elo, RatingModel::TrueSkill]
+        };
+        let prior_events = if models.iter().any(|model| *model != RatingModel::Opr)
+        {
+            self.get_prior_events(&sku)?
+        }
+        else
+        {
+            Vec::new()
+        };
+
+        for model in models
+        {
+            println!("{}", Calibration::backtest(model, &prior_events, &matches));
+        }
+        Ok(())
+    }
+
+    fn print_team_info(&self, team_name: &str) -> Result<(), ReplError>
+    {
+        let teams = self.source.get_teams(&[team_name.to_owned()])?;
+        if let Some(team) = teams.first()
+        {
+            println!("{}", team);
+            Ok(())
+        }
+        else
+        {
+            Err(ReplError::InvalidInput(format!("No such team: {}", team_name)))
+        }
+    }
+
+    /// Loads every lettered team registered under an organization number
+    fn get_organization_teams(&self, organization_name: &str) -> Result<Vec<crate::model::Team>, ReplError>
+    {
+        let team_names = (b'A'..=b'Z')
+            .map(|letter| format!("{}{}", organization_name, letter as char))
+            .collect::<Vec<String>>();
+        Ok(self.source.get_teams(&team_names)?)
+    }
+
+    /// Teams whose matches `match` subcommands consider
+    ///
+    /// Falls back to the configured current team, and widens to the team's whole
+    /// organization when `match_load_default_to_organization` is set.
+    /// An empty list means every team.
+    fn get_match_targets(&self, team_name: Option<&str>) -> Vec<String>
+    {
+        let team_name = match team_name
+        {
+            Some(team_name) => team_name.to_owned(),
+            None if !self.config.get_current_team().is_empty() => self.config.get_current_team().to_ascii_uppercase(),
+            None => return Vec::new()
+        };
+
+        if *self.config.is_match_load_default_to_organization()
+        {
+            vec![organization_of(&team_name).to_owned()]
+        }
+        else
+        {
+            vec![team_name]
+        }
+    }
+
+    /// Handles `match load|next|prev|lookup <n>` inside a competition
+    fn eval_match_command(&mut self, sku: &str, team_name: Option<&str>, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let targets = self.get_match_targets(team_name);
+        let involves_target = |match_struct: &Match| targets.is_empty() || match_struct.teams().any(|team| targets.iter().any(|target|
+        {
+            team.eq_ignore_ascii_case(target) || team_in_organization(team, target)
+        }));
+
+        let query = match targets.as_slice()
+        {
+            [target] if team_name_is_valid(target) => Query::team_at_event(sku, target),
+            _ => Query::event(sku)
+        };
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(query)?;
+        matches.retain(|match_struct| scope.includes_match(match_struct));
+        matches.sort_by_key(|match_struct| match_struct.schedule_order());
+
+        let found = match invocation.action
+        {
+            Action::MatchLoad => {
+                let matches = matches.into_iter().filter(|match_struct| involves_target(match_struct)).collect::<Vec<Match>>();
+                let mut match_list = MatchList::new(matches);
+                print_match_page(&mut match_list);
+                self.add_context(ProgramContext::MatchListContext(match_list));
+                return Ok(());
+            },
+            Action::MatchNext => {
+                matches.into_iter().find(|match_struct| !match_struct.scored && involves_target(match_struct))
+            },
+            Action::MatchPrev => {
+                matches.into_iter().rev().find(|match_struct| match_struct.scored && involves_target(match_struct))
+            },
+            Action::MatchLookup => {
+                let number = if let Some(number) = invocation.get_number::<u16>("number")
+                {
+                    number
+                }
+                else
+                {
+                    return Err(ReplError::missing("number"));
+                };
+
+                let round = invocation.get_round("round").or(scope.round).unwrap_or(Round::Qualification);
+                let series = invocation.get_number::<u16>("series");
+
+                // lookup is by schedule position, so fetch the whole event when filtered
+                if query.team.is_some() || invocation.get("round").is_some()
+                {
+                    matches = self.source.get_matches(Query::event(sku))?;
+                    matches.retain(|match_struct| scope.includes_division(match_struct));
+                }
+                matches.retain(|match_struct| match_struct.round == round
+                    && match_struct.match_num == number
+                    && series.is_none_or(|series| match_struct.instance == series));
+                if matches.len() > 1
+                {
+                    return Err(ReplError::InvalidInput(format!(
+                        "{} {} is played in more than one series; give one, e.g. `match lookup {} round {} series 1`",
+                        round.abbreviation(), number, number, round.abbreviation().to_ascii_lowercase())));
+                }
+                matches.into_iter().next()
+            },
+            action => unreachable!("{:?} is not a match command", action)
+        };
+
+        if let Some(match_struct) = found
+        {
+            println!("{}", match_struct);
+            self.add_context(ProgramContext::MatchContext(Box::new(match_struct)));
+            Ok(())
+        }
+        else
+        {
+            Err(ReplError::InvalidInput(String::from("No matching match found")))
+        }
+    }
+
+    /// Every match at the match's event and division, in schedule order
+    fn get_schedule(&self, match_struct: &Match) -> Result<Vec<Match>, ReplError>
+    {
+        let mut matches = self.source.get_matches(Query::event(&match_struct.sku))?;
+        matches.retain(|other_match| other_match.division == match_struct.division);
+        matches.sort_by_key(|other_match| other_match.schedule_order());
+        Ok(matches)
+    }
+
+    /// Handles commands while a `MatchContext` is on top of the stack
+    fn eval_match_context_command(&mut self, match_struct: Match, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        match invocation.action
+        {
+            Action::MatchTeams => {
+                let rankings = self.source.get_rankings(Query::event(&match_struct.sku))?;
+                // not every source fills in the ranking's OPR, so work it out from the division's scores
+                let ratings = stats::power_ratings(&self.get_schedule(&match_struct)?);
+                for color in &[Color::Red, Color::Blue]
+                {
+                    println!("{}:", color);
+                    let alliance = match_struct.alliance(*color);
+                    for team in &alliance.teams
+                    {
+                        let sitting = if alliance.sitting.as_ref() == Some(team) { " (sitting)" } else { "" };
+                        let opr = ratings.iter()
+                            .find(|rating| rating.team.eq_ignore_ascii_case(team))
+                            .map(|rating| format!("  OPR {:.2}", rating.opr))
+                            .unwrap_or_default();
+                        match rankings.iter().find(|ranking| ranking.team.eq_ignore_ascii_case(team))
+                        {
+                            Some(ranking) => {
+                                println!("  {:<8} #{:<3} {}-{}-{}{}{}",
+                                    team, ranking.rank, ranking.wins, ranking.losses, ranking.ties, opr, sitting);
+                            },
+                            None => {
+                                println!("  {:<8} unranked{}{}", team, opr, sitting);
+                            }
+                        }
+                    }
+                }
+            },
+            Action::Predict => {
+                let model = invocation.get_model("model").unwrap_or(RatingModel::Opr);
+
+                let prior_events = if model == RatingModel::Opr
+                {
+                    Vec::new()
+                }
+                else
+                {
+                    self.get_prior_events(&match_struct.sku)?
+                };
+                let schedule = self.get_schedule(&match_struct)?;
+                let mut predictor = Predictor::new(model, &prior_events);
+                predictor.add_matches_before(&schedule, &match_struct);
+                let prediction = predictor.predict(&match_struct);
+
+                println!("{} prediction from matches before {}", predictor.get_model().name(), match_struct.name());
+                println!("red  {:>6.1}  {:>5.1}%", prediction.red_score, 100.0 * prediction.win_probability(Color::Red));
+                println!("blue {:>6.1}  {:>5.1}%", prediction.blue_score, 100.0 * prediction.win_probability(Color::Blue));
+                if match_struct.scored
+                {
+                    println!("actual {}-{}", match_struct.red.score, match_struct.blue.score);
+                }
+            },
+            Action::MatchHistory => {
+                let schedule = self.get_schedule(&match_struct)?;
+                for team in match_struct.teams()
+                {
+                    println!("{}:", team);
+                    let prior_matches = schedule.iter()
+                        .filter(|other_match| other_match.schedule_order() < match_struct.schedule_order())
+                        .filter(|other_match| other_match.scored && other_match.alliance_of(team).is_some())
+                        .cloned()
+                        .collect::<Vec<Match>>();
+                    print_team_matches(&prior_matches, team);
+                }
+            },
+            Action::Notes => {
+                if let Some(team_name) = invocation.get_team("team")
+                {
+                    if match_struct.alliance_of(&team_name).is_none()
+                    {
+                        return Err(ReplError::InvalidInput(format!("{} is not in {}", team_name, match_struct.name())));
+                    }
+                    let text = if let Some(text) = invocation.get_text("note")
+                    {
+                        text
+                    }
+                    else
+                    {
+                        return Err(ReplError::missing("note"));
+                    };
+
+                    self.notes.add_note(&team_name, &match_struct.sku, &match_struct.name(), &text);
+                    if let Err(error) = self.notes.save()
+                    {
+                        return Err(ReplError::File(error));
+                    }
+                }
+                else
+                {
+                    for team in match_struct.teams()
+                    {
+                        println!("{}:", team);
+                        print_list(self.notes.get_notes(team));
+                    }
+                }
+            },
+            Action::StepNext | Action::StepPrev => {
+                let schedule = self.get_schedule(&match_struct)?;
+                let current = match_struct.schedule_order();
+                let found = if invocation.action == Action::StepNext
+                {
+                    schedule.into_iter().find(|other_match| other_match.schedule_order() > current)
+                }
+                else
+                {
+                    schedule.into_iter().rev().find(|other_match| other_match.schedule_order() < current)
+                };
+
+                if let Some(other_match) = found
+                {
+                    println!("{}", other_match);
+                    self.get_contexts_mut().pop();
+                    self.add_context(ProgramContext::MatchContext(Box::new(other_match)));
+                }
+                else
+                {
+                    return Err(ReplError::InvalidInput(format!("No {} match in the schedule", invocation.get_name())));
+                }
+            },
+            action => unreachable!("{:?} is not a match context command", action)
+        }
+
+        Ok(())
+    }
+
+    /// Handles commands while a `MatchListContext` is on top of the stack
+    fn eval_match_list_command(&mut self, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let match_list = if let Some(ProgramContext::MatchListContext(match_list)) = self.get_contexts_mut().last_mut()
+        {
+            match_list
+        }
+        else
+        {
+            return Err(ReplError::NotInContext("match list"));
+        };
+
+        match invocation.action
+        {
+            Action::Filters => {
+                if match_list.get_filters().is_empty()
+                {
+                    println!("No filters");
+                }
+                print_list(match_list.get_filters());
+            },
+            Action::FilterClear => {
+                match_list.clear_filters();
+                print_match_page(match_list);
+            },
+            Action::FilterTeam | Action::FilterRound | Action::FilterUnscored | Action::FilterScored => {
+                let filter = match invocation.action
+                {
+                    Action::FilterTeam => MatchFilter::Team(invocation.get_team("team").ok_or_else(|| ReplError::missing("team"))?),
+                    Action::FilterRound => MatchFilter::Round(invocation.get_round("round").ok_or_else(|| ReplError::missing("round"))?),
+                    Action::FilterUnscored => MatchFilter::Unscored,
+                    _ => MatchFilter::Scored
+                };
+
+                match_list.add_filter(filter);
+                print_match_page(match_list);
+            },
+            Action::Sort => {
+                if let Some(sort) = invocation.get_sort("order")
+                {
+                    match_list.set_sort(sort);
+                    print_match_page(match_list);
+                }
+                else
+                {
+                    return Err(ReplError::missing("order"));
+                }
+            },
+            Action::Show => {
+                print_match_page(match_list);
+            },
+            Action::Select => {
+                let row = invocation.get_number::<usize>("row");
+                let selected = row.and_then(|row| match_list.select(row)).cloned();
+
+                if let Some(match_struct) = selected
+                {
+                    println!("{}", match_struct);
+                    self.add_context(ProgramContext::MatchContext(Box::new(match_struct)));
+                }
+                else
+                {
+                    return Err(ReplError::InvalidInput(String::from("Please enter a row number from `show`!")));
+                }
+            },
+            action => unreachable!("{:?} is not a match list command", action)
+        }
+
+        Ok(())
+    }
+
+    /// Raw pick list metrics for every team in scope at the competition
+    fn get_team_metrics(&self, sku: &str) -> Result<HashMap<String, TeamMetrics>, ReplError>
+    {
+        let scope = self.get_scope();
+        let mut matches = self.source.get_matches(Query::event(sku))?;
+        matches.retain(|match_struct| scope.includes_division(match_struct));
+        let mut rankings = self.source.get_rankings(Query::event(sku))?;
+        rankings.retain(|ranking| scope.includes_ranking(ranking));
+        let skills = self.source.get_skills(Query::event(sku))?;
+
+        let mut metrics: HashMap<String, TeamMetrics> = HashMap::new();
+        for team in matches.iter().filter(|match_struct| match_struct.round == Round::Qualification).flat_map(Match::teams)
+        {
+            metrics.entry(team.to_ascii_uppercase()).or_default();
+        }
+        for rating in stats::power_ratings(&matches)
+        {
+            metrics.entry(rating.team).or_default().opr = rating.opr;
+        }
+        for ranking in &rankings
+        {
+            let played = ranking.wins + ranking.losses + ranking.ties;
+            if played > 0
+            {
+                metrics.entry(ranking.team.to_ascii_uppercase()).or_default().auton = ranking.ap as f64 / played as f64;
+            }
+        }
+
+        // skills runs only count for teams competing in scope
+        let mut best_runs: HashMap<(String, SkillType), u16> = HashMap::new();
+        for skill in &skills
+        {
+            let best = best_runs.entry((skill.team.to_ascii_uppercase(), skill.skill_type)).or_default();
+            *best = (*best).max(skill.score);
+        }
+        for (team, team_metrics) in metrics.iter_mut()
+        {
+            let best = |skill_type| best_runs.get(&(team.clone(), skill_type)).copied().unwrap_or(0);
+            team_metrics.skills = (best(SkillType::Driver) + best(SkillType::Programming)).max(best(SkillType::Combined)) as f64;
+        }
+
+        if metrics.is_empty()
+        {
+            return Err(ReplError::InvalidInput(format!("No teams have played at {}", sku)));
+        }
+        Ok(metrics)
+    }
+
+    fn eval_pick_list_command(&mut self, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let mut pick_list = if let Some(ProgramContext::PickListContext(pick_list)) = self.get_contexts().last()
+        {
+            pick_list.clone()
+        }
+        else
+        {
+            return Err(ReplError::NotInContext("pick list"));
+        };
+
+        match invocation.action
+        {
+            Action::Candidates | Action::BestCandidates => {
+                let metrics = self.get_team_metrics(&pick_list.sku)?;
+                let candidates = if invocation.action == Action::BestCandidates
+                {
+                    pick_list.best(&metrics, self.config.get_current_team(), invocation.get_number("count").unwrap_or(3))
+                }
+                else
+                {
+                    pick_list.rank(&metrics, self.config.get_current_team())
+                };
+                print_candidates(&candidates);
+                return Ok(());
+            },
+            Action::SetPickStatus => {
+                let team_name = invocation.get_team("team").ok_or_else(|| ReplError::missing("team"))?;
+                let status = match invocation.get_name().as_str()
+                {
+                    "pick" => PickStatus::Picked,
+                    "decline" => PickStatus::Declined,
+                    _ => PickStatus::Available
+                };
+                pick_list.set_status(&team_name, status);
+                println!("{} is {}", team_name, status);
+            },
+            Action::Rate => {
+                match (invocation.get_team("team"), invocation.get_number::<f64>("rating"))
+                {
+                    (Some(team_name), Some(rating)) => pick_list.set_scout_rating(&team_name, rating),
+                    _ => return Err(ReplError::missing("rating"))
+                }
+            },
+            Action::Weights => {
+                println!("Weights: {}", pick_list.weights);
+                return Ok(());
+            },
+            Action::Weight => {
+                match (invocation.get_metric("metric"), invocation.get_number::<f64>("weight"))
+                {
+                    (Some(metric), Some(weight)) => pick_list.weights.set(metric, weight),
+                    _ => return Err(ReplError::missing("weight"))
+                }
+                println!("Weights: {}", pick_list.weights);
+            },
+            Action::SavePickList => {
+                let path = invocation.get("path").ok_or_else(|| ReplError::missing("path"))?;
+                if let Err(error) = pick_list.save(Path::new(path))
+                {
+                    return Err(ReplError::File(error));
+                }
+                println!("Saved to {}", path);
+                return Ok(());
+            },
+            Action::LoadPickList => {
+                let path = match invocation.get("path")
+                {
+                    Some(path) => Some(PathBuf::from(path)),
+                    None => PickList::default_path(&pick_list.sku)
+                };
+                match path.map(|path| PickList::load(&path))
+                {
+                    Some(Ok(loaded)) if loaded.sku.eq_ignore_ascii_case(&pick_list.sku) => pick_list = loaded,
+                    Some(Ok(loaded)) => {
+                        return Err(ReplError::InvalidInput(format!("That pick list is for {}, not {}", loaded.sku, pick_list.sku)));
+                    },
+                    Some(Err(error)) => {
+                        return Err(ReplError::File(error));
+                    },
+                    None => {
+                        return Err(ReplError::missing("path"));
+                    }
+                }
+                println!("Loaded the pick list for {}", pick_list.sku);
+            },
+            action => unreachable!("{:?} is not a pick list command", action)
+        }
+
+        if let Err(error) = pick_list.save_default()
+        {
+            println!("Could not save the pick list: {}", error);
+        }
+        if let Some(ProgramContext::PickListContext(current)) = self.get_contexts_mut().last_mut()
+        {
+            *current = pick_list;
+        }
+        Ok(())
+    }
+
+    /// Seeds the bracket from the elimination matches scheduled in the current division
+    fn import_bracket_matches(&self, bracket: &mut Bracket, scope: &Scope) -> Result<(), ReplError>
+    {
+        let mut matches = self.source.get_matches(Query::event(&bracket.sku))?;
+        matches.retain(|match_struct| match_struct.round.is_elimination() && scope.includes_division(match_struct));
+        if scope.division.is_none() && matches.iter().any(|match_struct| !match_struct.division.eq_ignore_ascii_case(&matches[0].division))
+        {
+            return Err(ReplError::InvalidInput(format!("{} has eliminations in several divisions; pick one with `division <name>` first", bracket.sku)));
+        }
+
+        let mut rankings = self.source.get_rankings(Query::event(&bracket.sku))?;
+        rankings.retain(|ranking| scope.includes_ranking(ranking));
+        bracket.import_matches(&matches, &rankings);
+        Ok(())
+    }
+
+    fn eval_bracket_command(&mut self, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let mut bracket = if let Some(ProgramContext::BracketContext(bracket)) = self.get_contexts().last()
+        {
+            bracket.clone()
+        }
+        else
+        {
+            return Err(ReplError::NotInContext("bracket"));
+        };
+
+        let scope = self.get_scope();
+        match invocation.action
+        {
+            Action::ProjectBracket => {
+                let model = invocation.get_model("model").unwrap_or(RatingModel::Opr);
+                if bracket.get_alliances().len() < 2
+                {
+                    return Err(ReplError::InvalidInput(String::from("A bracket needs at least two alliances")));
+                }
+
+                let mut matches = self.source.get_matches(Query::event(&bracket.sku))?;
+                matches.retain(|match_struct| scope.includes_division(match_struct));
+                let prior_events = if model == RatingModel::Opr { Vec::new() } else { self.get_prior_events(&bracket.sku)? };
+                let mut predictor = Predictor::new(model, &prior_events);
+                predictor.add_scored_matches(&matches);
+
+                let projection = bracket::project(&bracket, &matches, &predictor).map_err(ReplError::InvalidInput)?;
+                for round in &projection.rounds
+                {
+                    if let Some(series) = round.first()
+                    {
+                        println!("{}", series.round.abbreviation());
+                    }
+                    for series in round
+                    {
+                        println!("  {}", projection.describe(series));
+                    }
+                }
+                println!("Tournament win ({}, best of {}):", model.name(), bracket.series_length);
+                for (alliance, probability) in projection.get_title_chances()
+                {
+                    println!("  {:<30} {:>5.1}%", alliance.to_string(), 100.0 * probability);
+                }
+                return Ok(());
+            },
+            Action::Alliances => {
+                print_list(bracket.get_alliances());
+                return Ok(());
+            },
+            Action::SetAlliance => {
+                let seed = invocation.get_number::<u8>("seed").unwrap_or(0);
+                bracket.set_alliance(seed, invocation.get_words("teams").iter().map(|team| team.to_ascii_uppercase()).collect())
+                    .map_err(ReplError::InvalidInput)?;
+            },
+            Action::RemoveAlliance => {
+                let seed = invocation.get("seed").unwrap_or_default();
+                if !invocation.get_number::<u8>("seed").is_some_and(|seed| bracket.remove_alliance(seed))
+                {
+                    return Err(ReplError::InvalidInput(format!("No alliance seeded {}", seed)));
+                }
+            },
+            Action::ImportBracket => {
+                if let Some(path) = invocation.get("path")
+                {
+                    if let Err(error) = bracket.import_file(Path::new(path))
+                    {
+                        return Err(ReplError::File(error));
+                    }
+                }
+                else
+                {
+                    self.import_bracket_matches(&mut bracket, &scope)?;
+                    if bracket.get_alliances().is_empty()
+                    {
+                        println!("No elimination matches have been scheduled yet");
+                    }
+                }
+            },
+            Action::SeriesLength => {
+                match invocation.get_number::<u8>("length")
+                {
+                    Some(length) if length % 2 == 1 => bracket.series_length = length,
+                    _ => {
+                        return Err(ReplError::InvalidInput(String::from("Series length must be odd, e.g. 1 or 3")));
+                    }
+                }
+            },
+            action => unreachable!("{:?} is not a bracket command", action)
+        }
+
+        print_list(bracket.get_alliances());
+        if let Err(error) = bracket.save_default()
+        {
+            println!("Could not save the bracket: {}", error);
+        }
+        if let Some(ProgramContext::BracketContext(current)) = self.get_contexts_mut().last_mut()
+        {
+            *current = bracket;
+        }
+        Ok(())
+    }
+
+    fn eval_cache_command(&self, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let cache = if let Some(cache) = &self.cache
+        {
+            cache
+        }
+        else
+        {
+            return Err(ReplError::InvalidInput(String::from("The cache is not enabled")));
+        };
+
+        match invocation.action
+        {
+            Action::CacheStatus => {
+                for status in cache.status()
+                {
+                    let oldest = status.oldest.map(cache::format_age).unwrap_or_else(|| String::from("-"));
+                    println!("{:<16} {:>6} entries {:>6} stale   oldest {} (ttl {})",
+                        status.kind.name(), status.entries, status.stale, oldest, cache::format_age(status.kind.time_to_live()));
+                }
+                println!("{} bytes on disk", cache.size_on_disk());
+            },
+            Action::CacheClear => {
+                cache.clear()?;
+                println!("Cache cleared");
+            },
+            Action::CacheRefresh => {
+                if let Some(sku) = invocation.get_sku("sku")
+                {
+                    // entries are only replaced once they are refetched, so a failed refresh keeps them
+                    println!("Marked {} cached entries for {} as stale", cache.expire_event(&sku), sku);
+
+                    let query = Query::event(&sku);
+                    let fetched = [
+                        ("events", self.source.get_events(query).map(|events| events.len())),
+                        ("matches", self.source.get_matches(query).map(|matches| matches.len())),
+                        ("rankings", self.source.get_rankings(query).map(|rankings| rankings.len())),
+                        ("awards", self.source.get_awards(query).map(|awards| awards.len())),
+                        ("skills", self.source.get_skills(query).map(|skills| skills.len()))
+                    ];
+                    for (name, result) in &fetched
+                    {
+                        match result
+                        {
+                            Ok(count) => println!("{:<10} {}", name, count),
+                            Err(error) => println!("{:<10} {}", name, error)
+                        }
+                    }
+                }
+                else
+                {
+                    return Err(ReplError::missing("sku"));
+                }
+            },
+            action => unreachable!("{:?} is not a cache command", action)
+        }
+
+        Ok(())
+    }
+
+    fn eval_offline_command(&self, invocation: &Invocation) -> Result<(), ReplError>
+    {
+        let directory = if let Some(directory) = EventPack::default_directory()
+        {
+            directory
+        }
+        else
+        {
+            return Err(ReplError::File(String::from("Could not find a config directory to keep event packs in")));
+        };
+
+        match invocation.action
+        {
+            Action::OfflinePrepare => {
+                if let Some(sku) = invocation.get_sku("sku")
+                {
+                    let pack = offline::prepare(self.source.as_ref(), *self.config.get_data_source(), &sku)?;
+                    let path = directory.join(format!("{}.json", sku));
+                    pack.save(&path)?;
+                    println!("Saved {} entries to {}", pack.entries.len(), path.display());
+                }
+                else
+                {
+                    return Err(ReplError::missing("sku"));
+                }
+            },
+            Action::OfflineList => {
+                let packs = EventPack::load_directory(&directory);
+                if packs.is_empty()
+                {
+                    println!("No event packs in {}", directory.display());
+                }
+                for pack in packs
+                {
+                    println!("{:<20} {} entries, prepared {} ago",
+                        pack.sku, pack.entries.len(), cache::format_age(cache::now().saturating_sub(pack.created_at)));
+                }
+            },
+            action => unreachable!("{:?} is not an offline command", action)
+        }
+
+        Ok(())
+    }
+
+    fn get_prompt(&self) -> String
+    {
+        let contexts = self.get_contexts();
+        if contexts.is_empty()
+        {
+            return String::from("> ");
+        }
+        contexts.iter().map(|context| context.to_string()).collect()
+    }
+
+    /// Points tab completion at the current context stack
+    fn update_completions(&mut self)
+    {
+        let command_set = self.get_command_set();
+        let sku = self.get_competition_sku();
+        // divisions are only fetched again once a different competition is entered
+        let divisions = match (&self.editor, &sku)
+        {
+            (Some(editor), Some(sku)) if editor.get_helper().division_sku.as_ref() != Some(sku) => {
+                Some(self.source.get_divisions(sku).unwrap_or_default())
+            },
+            (Some(_), None) => Some(Vec::new()),
+            _ => None
+        };
+
+        if let Some(editor) = &mut self.editor
+        {
+            let helper = editor.get_helper_mut();
+            helper.command_set = command_set;
+            if let Some(divisions) = divisions
+            {
+                helper.division_sku = sku;
+                helper.divisions = divisions;
+            }
+        }
+    }
+
+    /// The next line of input, or `None` once input has ended
+    pub fn prompt(&mut self) -> Option<String>
+    {
+        let prompt = self.get_prompt();
+        self.update_completions();
+        if let Some(editor) = &mut self.editor
+        {
+            return editor.read_line(&prompt);
+        }
+
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+        let mut user_input = String::new();
+        match io::stdin().read_line(&mut user_input)
+        {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(user_input)
+        }
+    }
+
+    /// The competition sku of the innermost competition context
+    fn get_competition_sku(&self) -> Option<String>
+    {
+        self.get_contexts().iter().rev().find_map(|context| match context
+        {
+            ProgramContext::CompetitionContext(sku) => Some(sku.clone()),
+            _ => None
+        })
+    }
+
+    /// The team name of the innermost team context
+    fn get_team_name(&self) -> Option<String>
+    {
+        self.get_contexts().iter().rev().find_map(|context| match context
+        {
+            ProgramContext::TeamContext(team_name) => Some(team_name.clone()),
+            _ => None
+        })
+    }
+
+    fn get_organization_name(&self) -> Option<String>
+    {
+        self.get_contexts().iter().rev().find_map(|context| match context
+        {
+            ProgramContext::OrganizationContext(organization_name) => Some(organization_name.clone()),
+            _ => None
+        })
+    }
+
+    /// The commands the context stack accepts
+    fn get_command_set(&self) -> CommandSet
+    {
+        match self.get_contexts().last()
+        {
+            Some(ProgramContext::MatchContext(_)) => return CommandSet::Match,
+            Some(ProgramContext::MatchListContext(_)) => return CommandSet::MatchList,
+            Some(ProgramContext::PickListContext(_)) => return CommandSet::PickList,
+            Some(ProgramContext::BracketContext(_)) => return CommandSet::Bracket,
+            _ => ()
+        }
+
+        // division and round contexts only narrow queries, so commands are
+        // dispatched as if they were in the enclosing competition
+        let mut contexts = self.get_contexts().iter()
+            .filter(|context| !matches!(context, ProgramContext::DivisionContext(_) | ProgramContext::RoundContext(_)));
+        match (contexts.next(), contexts.next())
+        {
+            (Some(ProgramContext::ConfigContext), _) => CommandSet::Config,
+            (Some(ProgramContext::TeamContext(_)), Some(ProgramContext::StatsContext)) => CommandSet::TeamStats,
+            (Some(ProgramContext::TeamContext(_)), Some(ProgramContext::HistoryContext)) => CommandSet::TeamHistory,
+            (Some(ProgramContext::TeamContext(_)), _) => CommandSet::Team,
+            (Some(ProgramContext::OrganizationContext(_)), None) => CommandSet::Organization,
+            (Some(ProgramContext::OrganizationContext(_)), Some(_)) => CommandSet::OrganizationSubcontext,
+            (Some(ProgramContext::CompetitionContext(_)), Some(ProgramContext::TeamContext(_))) => CommandSet::CompetitionTeam,
+            (Some(ProgramContext::CompetitionContext(_)), _) => CommandSet::Competition,
+            (Some(ProgramContext::StatsContext), _) => CommandSet::Stats,
+            _ => CommandSet::Base
+        }
+    }
+
+    /// Runs one line of input in the current context
+    ///
+    /// Errors are left for the caller to print; `ReplError::Exit` means
+    /// `exit` was given with no context left to leave
+    pub fn eval(&mut self, input: String) -> Result<(), ReplError>
+    {
+        let words = command::tokenize(&input)?;
+        if words.is_empty()
+        {
+            return Ok(());
+        }
+
+        let command_set = self.get_command_set();
+        let invocation = command::parse(command_set, &words)?;
+        let snapshot = if invocation.action.changes_context() { Some((self.contexts.clone(), self.get_path())) } else { None };
+        let result = self.run(&invocation, command_set);
+        if let Some((contexts, path)) = snapshot
+        {
+            if self.get_path() != path
+            {
+                self.previous_contexts = Some(contexts);
+            }
+        }
+        result
+    }
+
+    /// The context stack as a path `cd` accepts, e.g. `/comp/RE-VRC-23-1234/team/1234A`
+    fn get_path(&self) -> String
+    {
+        let contexts = self.get_contexts();
+        let segments = (0..contexts.len())
+            .map(|index| contexts[index].get_path_segment(&contexts[..index]))
+            .collect::<Vec<String>>();
+        format!("/{}", segments.join("/"))
+    }
+
+    /// Enters each context along a path; on failure the stack is left as it was
+    ///
+    /// A leading slash starts from the top and `..` leaves a context
+    fn change_context(&mut self, path: &str) -> Result<(), ReplError>
+    {
+        let contexts = self.contexts.clone();
+        let result = self.follow_path(path);
+        if result.is_err()
+        {
+            self.contexts = contexts;
+        }
+        result
+    }
+
+    fn follow_path(&mut self, path: &str) -> Result<(), ReplError>
+    {
+        if path.starts_with('/')
+        {
+            self.contexts.clear();
+        }
+
+        let segments = path.split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        let mut index = 0;
+        while index < segments.len()
+        {
+            if segments[index] == ".."
+            {
+                if self.contexts.pop().is_none()
+                {
+                    return Err(ReplError::InvalidInput(String::from("Already at the top")));
+                }
+                index += 1;
+                continue;
+            }
+
+            let command_set = self.get_command_set();
+            let count = command::count_path_words(command_set, &segments[index..])?;
+            let words = &segments[index..segments.len().min(index + count)];
+            let invocation = command::parse(command_set, words)?;
+            let depth = self.contexts.len();
+            if invocation.action.enters_context()
+            {
+                self.run(&invocation, command_set)?;
+            }
+            // e.g. `match next` when every match has been played
+            if self.contexts.len() <= depth
+            {
+                return Err(ReplError::InvalidInput(format!("{} does not enter a context", words.join(" "))));
+            }
+            index += words.len();
+        }
+        Ok(())
+    }
+
+    fn eval_words(&mut self, words: &[String], command_set: CommandSet) -> Result<(), ReplError>
+    {
+        let invocation = command::parse(command_set, words)?;
+        self.run(&invocation, command_set)
+    }
+
+    /// Enters a team, or an organization when the name has no letter
+    ///
+    /// Only top level commands enter teams, and `global` runs those from an
+    /// empty stack, so the team never lands on top of another context
+    fn enter_team_or_organization(&mut self, name: &str) -> Result<(), ReplError>
+    {
+        if team_name_is_valid(name)
+        {
+            let team_name = name.to_ascii_uppercase();
+            self.print_team_info(&team_name)?;
+            self.add_context(ProgramContext::TeamContext(team_name));
+        }
+        else
+        {
+            self.add_context(ProgramContext::OrganizationContext(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Runs a parsed command; `command_set` is the set it was parsed against
+    fn run(&mut self, invocation: &Invocation, command_set: CommandSet) -> Result<(), ReplError>
+    {
+        let scope = self.get_scope();
+        let sku = self.get_competition_sku();
+        let team_name = self.get_team_name();
+        // inside a competition, commands are narrowed to a team entered with `team`
+        let competition_team = if command_set == CommandSet::CompetitionTeam { team_name.clone() } else { None };
+
+        match invocation.action
+        {
+            Action::Exit => {
+                if self.get_contexts_mut().pop().is_none()
+                {
+                    return Err(ReplError::Exit);
+                }
+            },
+            Action::Help => {
+                println!("{}", command::help(command_set, invocation.get_words("command"))?);
+            },
+            Action::Global => {
+                // a context entered from the top replaces the old stack, which `back` returns to
+                let contexts = std::mem::take(&mut self.contexts);
+                let result = self.eval_words(invocation.get_words("command"), CommandSet::Base);
+                if result.is_err() || self.contexts.is_empty()
                 {
-                    if self.get_contexts_mut().pop().is_some()
-                    {
-                        return Ok(());
-                    }
-                    else
+                    self.contexts = contexts;
+                }
+                return result;
+            },
+            Action::ChangeContext => {
+                match invocation.get("path")
+                {
+                    Some(path) => self.change_context(path)?,
+                    None => self.contexts.clear()
+                }
+            },
+            Action::Up => {
+                let count = invocation.get_number::<usize>("count").unwrap_or(1);
+                let depth = self.contexts.len().saturating_sub(count);
+                self.contexts.truncate(depth);
+            },
+            Action::Top => {
+                self.contexts.clear();
+            },
+            Action::PrintContexts => {
+                println!("{}", self.get_path());
+            },
+            Action::Back => {
+                self.contexts = self.previous_contexts.clone()
+                    .ok_or_else(|| ReplError::InvalidInput(String::from("Nothing to go back to")))?;
+            },
+            Action::At => {
+                let path = invocation.get("path").ok_or_else(|| ReplError::missing("path"))?;
+                let contexts = self.contexts.clone();
+                let result = self.follow_path(path).and_then(|()|
+                {
+                    let command_set = self.get_command_set();
+                    self.eval_words(invocation.get_words("command"), command_set)
+                });
+                self.contexts = contexts;
+                // `exit` there only leaves a context that is thrown away anyway
+                return match result
+                {
+                    Err(ReplError::Exit) => Ok(()),
+                    result => result
+                };
+            },
+            Action::Config => {
+                self.add_context(ProgramContext::ConfigContext);
+            },
+            Action::ConfigGet => {
+                let key = invocation.get("key").ok_or_else(|| ReplError::missing("key"))?;
+                if let Some(value) = self.config.get_value(key)
+                {
+                    println!("{} = {}", key.to_ascii_lowercase(), value);
+                }
+            },
+            Action::CacheStatus | Action::CacheClear | Action::CacheRefresh => {
+                self.eval_cache_command(invocation)?;
+            },
+            Action::OfflinePrepare | Action::OfflineList => {
+                self.eval_offline_command(invocation)?;
+            },
+            Action::Competition => {
+                let sku = invocation.get_sku("sku").ok_or_else(|| ReplError::missing("sku"))?;
+                let events = self.source.get_events(Query::event(&sku))?;
+                if let Some(event) = events.first()
+                {
+                    println!("{}", event);
+                    if event.divisions.len() > 1
                     {
-                        return Err(9);
+                        println!("Divisions: {}", event.divisions.join(", "));
                     }
+                    self.add_context(ProgramContext::CompetitionContext(sku));
                 }
-            }
-
-            let mut contexts = self.get_contexts().iter();
-            let first_context = {
-                if global
+                else
                 {
-                    &ProgramContext::BaseContext
+                    return Err(ReplError::InvalidInput(format!("No such competition: {}", sku)));
                 }
-                else
+            },
+            Action::Stats => {
+                let target = invocation.get_words("target");
+                let model = target.last()
+                    .and_then(|word| RatingModel::parse(word))
+                    .filter(|model| *model != RatingModel::Opr);
+                if let Some(position) = target.iter().position(|word| word.eq_ignore_ascii_case("skills"))
                 {
-                    if let Some(context) = contexts.next()
+                    // `stats <state> skills (spots <n>)`
+                    let region = target[..position].iter().chain(&target[position + 1..]).cloned().collect::<Vec<String>>();
+                    if region.is_empty()
                     {
-                        context
+                        return Err(ReplError::missing("state"));
                     }
-                    else
+                    self.print_region_skills(&region.join(" "), invocation.get_number("spots").unwrap_or(skills::DEFAULT_WORLDS_SPOTS))?;
+                }
+                else if let (Some(model), true) = (model, target.len() > 1)
+                {
+                    // `stats <state> elo`
+                    self.print_region_ratings(&target[..target.len() - 1].join(" "), model)?;
+                }
+                else if let [name] = target
+                {
+                    if !ArgumentKind::TeamOrOrganization.accepts(name)
                     {
-                        &ProgramContext::BaseContext
+                        return Err(ReplError::InvalidInput(format!("Invalid organization/team name: {}", name)));
                     }
+                    self.enter_team_or_organization(name)?;
+                    self.add_context(ProgramContext::StatsContext);
                 }
-            };
+                else if target.is_empty()
+                {
+                    self.add_context(ProgramContext::StatsContext);
+                }
+                else
+                {
+                    return Err(ReplError::InvalidInput(String::from("Usage: stats <team name> | <state> elo|trueskill | <state> skills")));
+                }
+            },
+            Action::Calibrate => {
+                let sku = invocation.get_sku("sku").ok_or_else(|| ReplError::missing("sku"))?;
+                self.print_calibration(&sku, invocation.get_model("model"))?;
+            },
+            Action::Team => {
+                let name = invocation.get("name").ok_or_else(|| ReplError::missing("name"))?;
+                self.enter_team_or_organization(name)?;
 
-            match first_context
-            {
-                ProgramContext::ConfigContext => {
+                // e.g. `team 1234A stats elo` runs `stats elo` for the team
+                let rest = invocation.get_words("command");
+                if !rest.is_empty()
+                {
+                    let command_set = self.get_command_set();
+                    return self.eval_words(rest, command_set);
+                }
+            },
+            Action::TeamInfo => {
+                self.print_team_info(&team_name.ok_or(ReplError::NotInContext("team"))?)?;
+            },
+            Action::TeamSkills => {
+                self.print_team_skills(&team_name.ok_or(ReplError::NotInContext("team"))?)?;
+            },
+            Action::TeamStats => {
+                self.add_context(ProgramContext::StatsContext);
 
-                },
-                ProgramContext::TeamContext(team_name) => {
-                    if let Some(secondary_context) = contexts.next()
+                // e.g. `stats elo` runs `elo` inside the new context
+                let rest = invocation.get_words("command");
+                if !rest.is_empty()
+                {
+                    return self.eval_words(rest, CommandSet::TeamStats);
+                }
+            },
+            Action::TeamHistory => {
+                self.add_context(ProgramContext::HistoryContext);
+            },
+            Action::Graph => {
+                println!("graphing!");
+            },
+            Action::TeamCompetitionRankings => {
+                let rankings = self.source.get_rankings(Query::team(&team_name.ok_or(ReplError::NotInContext("team"))?))?;
+                print_list(&rankings);
+            },
+            Action::SeasonRankings => {
+                // `stats> 1234A` names the team; inside a team it is the team itself
+                let team_name = invocation.get_team("team").or(team_name).ok_or(ReplError::NotInContext("team"))?;
+                let season_rankings = self.source.get_season_rankings(&team_name)?;
+                print_list(&season_rankings);
+            },
+            Action::TeamRating => {
+                let model = RatingModel::parse(&invocation.get_name()).unwrap_or(RatingModel::Elo);
+                self.print_team_rating(&team_name.ok_or(ReplError::NotInContext("team"))?, model)?;
+            },
+            Action::TeamEvents => {
+                let events = self.source.get_events(Query::team(&team_name.ok_or(ReplError::NotInContext("team"))?))?;
+                print_list(&events);
+            },
+            Action::TeamMatches => {
+                let team_name = team_name.ok_or(ReplError::NotInContext("team"))?;
+                let matches = self.source.get_matches(Query::team(&team_name))?;
+                print_team_matches(&matches, &team_name);
+            },
+            Action::TeamAwards => {
+                let awards = self.source.get_awards(Query::team(&team_name.ok_or(ReplError::NotInContext("team"))?))?;
+                print_list(&awards);
+            },
+            Action::TeamSkillRuns => {
+                let skills = self.source.get_skills(Query::team(&team_name.ok_or(ReplError::NotInContext("team"))?))?;
+                print_list(&skills);
+            },
+            Action::OrganizationList => {
+                let teams = self.get_organization_teams(&self.get_organization_name().ok_or(ReplError::NotInContext("organization"))?)?;
+                print_list(&teams);
+            },
+            Action::OrganizationCompetition => {
+                println!("competition stats");
+            },
+            Action::RegionRatings => {
+                let model = RatingModel::parse(&invocation.get_name()).unwrap_or(RatingModel::Elo);
+                self.print_region_ratings(&invocation.get_text("state").ok_or_else(|| ReplError::missing("state"))?, model)?;
+            },
+            Action::RegionSkills => {
+                let spots = invocation.get_number("spots").unwrap_or(skills::DEFAULT_WORLDS_SPOTS);
+                self.print_region_skills(&invocation.get_text("state").ok_or_else(|| ReplError::missing("state"))?, spots)?;
+            },
+            Action::PowerRatings => {
+                let stat = PowerStat::parse(&invocation.get_name());
+                let mut ratings = self.get_power_ratings(&invocation.get_sku("sku").ok_or_else(|| ReplError::missing("sku"))?)?;
+                print_power_ratings(&mut ratings, stat);
+            },
+            Action::CompetitionTeam => {
+                let sku = sku.ok_or(ReplError::NotInContext("competition"))?;
+                let team_name = invocation.get_team("team").ok_or_else(|| ReplError::missing("team"))?;
+                let mut rankings = self.source.get_rankings(Query::team_at_event(&sku, &team_name))?;
+                rankings.retain(|ranking| scope.includes_ranking(ranking));
+                print_list(&rankings);
+                self.add_context(ProgramContext::TeamContext(team_name));
+            },
+            Action::MatchLoad | Action::MatchNext | Action::MatchPrev | Action::MatchLookup => {
+                self.eval_match_command(&sku.ok_or(ReplError::NotInContext("competition"))?, competition_team.as_deref(), invocation)?;
+            },
+            Action::Rankings => {
+                let mut rankings = self.source.get_rankings(Query::event(&sku.ok_or(ReplError::NotInContext("competition"))?))?;
+                rankings.retain(|ranking| scope.includes_ranking(ranking));
+                print_list(&rankings);
+            },
+            Action::ComputedRankings | Action::RankingsDiff => {
+                self.print_computed_rankings(&sku.ok_or(ReplError::NotInContext("competition"))?, invocation.action == Action::RankingsDiff)?;
+            },
+            Action::CompetitionStats => {
+                let stat = invocation.get_stat("stat");
+                let mut ratings = self.get_power_ratings(&sku.ok_or(ReplError::NotInContext("competition"))?)?;
+                if let Some(team_name) = competition_team
+                {
+                    let rating_count = ratings.len();
+                    let stats_to_show = match stat
                     {
-                        match secondary_context
-                        {
-                            ProgramContext::StatsContext => {
-                                if let Some(subcommand) = words.next()
-                                {
-                                    match subcommand.to_ascii_lowercase().as_str()
-                                    {
-                                        "graph" => {
-                                            println!("graphing!");
-                                        },
-                                        "competition" => {
-                                            println!("competition stats");
-                                        },
-                                        _ => {
-                                            println!("invalid subcommand");
-                                        }
-                                    }
-                                }
-                            },
-                            ProgramContext::HistoryContext => {
-                                println!("team history");
-                            },
-                            _ => ()
-                        }
-                    }
-                    else
+                        Some(stat) => vec![stat],
+                        None => vec![PowerStat::Opr, PowerStat::Dpr, PowerStat::Ccwm]
+                    };
+                    for stat in stats_to_show
                     {
-                        match command.as_str()
+                        sort_power_ratings(&mut ratings, stat);
+                        let place = ratings.iter().position(|rating| rating.team.eq_ignore_ascii_case(&team_name));
+                        if let Some(place) = place
                         {
-                            "stats" => {
-                                self.add_context(ProgramContext::StatsContext);
-                            },
-                            "history" => {
-                                self.add_context(ProgramContext::HistoryContext);
-                            },
-                            _ => {
-                                println!("Invalid subcommand");
-                            }
+                            let rating = &ratings[place];
+                            println!("{} {:.2} (#{} of {}, {} matches)", stat.name(), rating.get(stat), place + 1, rating_count, rating.matches);
                         }
-                    }
-                },
-                ProgramContext::OrganizationContext(organization_name) => {
-                    if let Some(subcontext) = contexts.next()
-                    {
-                        match subcontext
+                        else
                         {
-                            ProgramContext::StatsContext => {
-
-                            },
-                            ProgramContext::HistoryContext => {
-
-                            },
-                            _ => ()
+                            return Err(ReplError::InvalidInput(format!("{} has no scored qualification matches", team_name)));
                         }
                     }
-                    else
+                }
+                else
+                {
+                    print_power_ratings(&mut ratings, stat);
+                }
+            },
+            Action::ScheduleStrength => {
+                let strengths = self.get_schedule_strengths(&sku.ok_or(ReplError::NotInContext("competition"))?, invocation.get_model("model"))?;
+                if let Some(team_name) = competition_team
+                {
+                    if let Some(strength) = strengths.iter().find(|strength| strength.team.eq_ignore_ascii_case(&team_name))
                     {
-                        match command.as_str()
-                        {
-                            "list" => {
-                                println!("listing all teams in {}", organization_name);
-                            },
-                            "graph" => {
-                                println!("graphing!");
-                            },
-                            "competition" => {
-                                println!("competition stats");
-                            },
-                            _ => {
-                                println!("invalid subcommand");
-                            }
-                        }
+                        println!("{}", strength);
+                        print_list(&strength.matches);
                     }
-                },
-                ProgramContext::CompetitionContext(competition_sku) => {
-                    if let Some(subcontext) = contexts.next()
+                    else
                     {
-                        match subcontext
-                        {
-                            ProgramContext::TeamContext(team_name) => {
-                                if let Some(subcommand) = words.next()
-                                {
-                                    let subcommand = subcommand.trim().to_ascii_lowercase();
-                                    
-                                    match subcommand.as_str()
-                                    {
-                                        "load" => {
-                                            println!("loading all matches for {}", team_name);
-                                        },
-                                        "next" => {
-                                            println!("finding next match for {}", team_name);
-                                        },
-                                        "prev" => {
-                                            println!("finding previous match for {}", team_name);
-                                        },
-                                        "lookup" => {
-                                            println!("looking up nth match for {}", team_name);
-                                        },
-                                        _ => ()
-                                    }
-                                }
-                                else
-                                {
-                                    return Err(0);
-                                }
-                            },
-                            ProgramContext::MatchContext(match_struct) => {
-                                println!("Match");
-                            },
-                            ProgramContext::MatchListContext(match_struct_list) => {
-                                println!("Multiple matches");
-                            },
-                            _ => ()
-                        }
+                        return Err(ReplError::InvalidInput(format!("{} has no scored qualification matches", team_name)));
                     }
-                    else
+                }
+                else
+                {
+                    print_list(&strengths);
+                }
+            },
+            Action::Simulate => {
+                let outlooks = self.get_simulated_outlooks(&sku.ok_or(ReplError::NotInContext("competition"))?, invocation)?;
+                if let Some(team_name) = competition_team
+                {
+                    if let Some(outlook) = outlooks.iter().find(|outlook| outlook.team.eq_ignore_ascii_case(&team_name))
                     {
-                        match command.as_str()
+                        println!("{}", outlook);
+                        for (rank, probability) in outlook.get_rank_probabilities()
                         {
-                            "team" => {
-                                if let Some(team_name) = words.next()
-                                {
-                                    // todo verify team name integrity
-                                    if team_name_is_valid(team_name)
-                                    {
-                                        println!("Valid team name: {}", team_name);
-                                        self.add_context(ProgramContext::TeamContext(team_name.to_owned()));
-                                    }
-                                    else
-                                    {
-                                        println!("Invalid team name: {}", team_name);
-                                        return Err(3);
-                                    }
-                                }
-                                else
-                                {
-                                    return Err(0);
-                                }
-                            },
-                            "match" => {
-                                println!("match");
-
-                                if let Some(subcommand) = words.next()
-                                {
-                                    let subcommand = subcommand.trim().to_ascii_lowercase();
-                                    
-                                    match subcommand.as_str()
-                                    {
-                                        "load" => {
-                                            println!("loading")
-                                        },
-                                        "next" => {
-                                            println!("finding next match");
-                                        },
-                                        "prev" => {
-                                            println!("finding previous match");
-                                        },
-                                        "lookup" => {
-                                            println!("looking up nth match");
-                                        },
-                                        _ => ()
-                                    }
-                                }
-                                else
-                                {
-                                    return Err(0);
-                                }
-                            },
-                            "wait" => {
-                                println!("wait");
-                            },
-                            _ => {
-                                println!("Invalid subcommand");
-                            }
+                            println!("  #{:<3} {:>5.1}% {}", rank, 100.0 * probability, "#".repeat((probability * 50.0).round() as usize));
                         }
                     }
-                },
-                ProgramContext::StatsContext => {
-                    if let Some(target) = words.next()
-                    {
-                        // figure out what it means; team name, org name...
-                        println!("target of stats is {}", target);
-                    }
                     else
                     {
-                        return Err(0);
+                        return Err(ReplError::InvalidInput(format!("{} has no qualification matches", team_name)));
                     }
-                },
-                ProgramContext::BaseContext => {
-                    let real_command = {
-                        if global
-                        {
-                            let command_maybe = words.next();
-                            if let Some(second_word) = command_maybe
-                            {
-                                // self.get_contexts_mut().clear();
-                                second_word
-                            }
-                            else
-                            {
-                                println!("Please enter a command after 'global'");
-                                return Err(1);
-                            }
-                        }
-                        else
-                        {
-                            &command
-                        }
-                    }.to_ascii_lowercase();
+                }
+                else
+                {
+                    print_list(&outlooks);
+                }
+            },
+            Action::TeamEventMatches => {
+                let team_name = competition_team.ok_or(ReplError::NotInContext("team"))?;
+                let mut matches = self.source.get_matches(Query::team_at_event(&sku.ok_or(ReplError::NotInContext("competition"))?, &team_name))?;
+                matches.retain(|match_struct| scope.includes_match(match_struct));
+                print_team_matches(&matches, &team_name);
+            },
+            Action::Bracket => {
+                let sku = sku.ok_or(ReplError::NotInContext("competition"))?;
+                let mut bracket = Bracket::load_default(&sku, scope.division.as_deref());
+                if bracket.get_alliances().is_empty()
+                {
+                    self.import_bracket_matches(&mut bracket, &scope)?;
+                }
+                print_list(bracket.get_alliances());
+                self.add_context(ProgramContext::BracketContext(bracket));
+            },
+            Action::PickList => {
+                let pick_list = PickList::load_default(&sku.ok_or(ReplError::NotInContext("competition"))?);
+                println!("Weights: {}", pick_list.weights);
+                self.add_context(ProgramContext::PickListContext(pick_list));
+            },
+            Action::Division => {
+                if let Some(division_name) = scope.division
+                {
+                    return Err(ReplError::InvalidInput(format!("Already in division {}; exit it first", division_name)));
+                }
 
-                    match real_command.as_str()
-                    {
-                        "config" => {
-                            println!("config!!");
-                            self.add_context(ProgramContext::ConfigContext);
-                        },
-                        "competition" | "comp" => {
-                            println!("competition!");
-                            if let Some(sku) = words.next()
-                            {
-                                // todo verify sku is valid
-                                self.add_context(ProgramContext::CompetitionContext(sku.to_owned()));
-                            }
-                        },
-                        "stats" | "team" => {    
-                            if let Some(team_or_organization_name) = words.next()
-                            {
-                                let last_char = team_or_organization_name.chars().last();
-                                if let Some(character) = last_char
-                                {
-                                    // todo clear or branch off from old contexts
-    
-                                    if character.is_alphabetic()
-                                    {
-                                        let team_name = team_or_organization_name.to_string();
-                                        
-                                        if team_name_is_valid(&team_name)
-                                        {
-                                            let team_context = ProgramContext::TeamContext(team_name.to_ascii_uppercase());
-                                            self.add_context(team_context);
-                                        }
-                                        else
-                                        {
-                                            println!("Invalid team name: {}", team_name);                                            
-                                            return Err(3);
-                                        }
-                                    }
-                                    // otherwise if characters are all digits
-                                    else if team_or_organization_name.chars().all(|x| !x.is_alphabetic())
-                                    {
-                                        let organization_context = ProgramContext::OrganizationContext(team_or_organization_name.to_string());
-                                        self.add_context(organization_context);
-                                    }
-                                    else
-                                    {
-                                        println!("Invalid organization/team name: {}", team_or_organization_name);
-                                        return Err(3);
-                                    }
-
-                                    if real_command.eq("stats")
-                                    {
-                                        self.add_context(ProgramContext::StatsContext);
-                                    }
-                                }
-                            }
-                            else if real_command.eq("stats") // enter stats mode
-                            {
-                                self.add_context(ProgramContext::StatsContext);
-                            }
-                            else
-                            {
-                                println!("Please enter a team name!");
-                                return Err(4);
-                            }
-                        },
-                        "exit" => {
-                            return Err(9);
-                        },
-                        _ => {
-                            println!("whatever");
-                        }
-                    }
-                },
-                _ => {
-                    panic!("Invalid base context!");
+                let name = invocation.get_text("name").ok_or_else(|| ReplError::missing("name"))?;
+                let divisions = self.source.get_divisions(&sku.ok_or(ReplError::NotInContext("competition"))?)?;
+                if let Some(division) = find_division(&divisions, &name)
+                {
+                    let division = division.clone();
+                    self.add_context(ProgramContext::DivisionContext(division));
+                }
+                else
+                {
+                    return Err(ReplError::InvalidInput(format!("No such division: {} (divisions: {})", name, divisions.join(", "))));
+                }
+            },
+            Action::Round => {
+                if let Some(round) = scope.round
+                {
+                    return Err(ReplError::InvalidInput(format!("Already in round {}; exit it first", round.abbreviation())));
                 }
+                self.add_context(ProgramContext::RoundContext(invocation.get_round("round").ok_or_else(|| ReplError::missing("round"))?));
+            },
+            Action::Divisions => {
+                let divisions = self.source.get_divisions(&sku.ok_or(ReplError::NotInContext("competition"))?)?;
+                print_list(&divisions);
+            },
+            Action::Awards => {
+                let awards = self.source.get_awards(Query::event(&sku.ok_or(ReplError::NotInContext("competition"))?))?;
+                print_list(&awards);
+            },
+            Action::Wait => {
+                println!("wait");
+            },
+            Action::MatchTeams | Action::Predict | Action::MatchHistory | Action::Notes | Action::StepNext | Action::StepPrev => {
+                let match_struct = match self.get_contexts().last()
+                {
+                    Some(ProgramContext::MatchContext(match_struct)) => match_struct.as_ref().clone(),
+                    _ => return Err(ReplError::NotInContext("match"))
+                };
+                self.eval_match_context_command(match_struct, invocation)?;
+            },
+            Action::Filters | Action::FilterTeam | Action::FilterRound | Action::FilterUnscored | Action::FilterScored
+                | Action::FilterClear | Action::Sort | Action::Show | Action::Select => {
+                self.eval_match_list_command(invocation)?;
+            },
+            Action::Candidates | Action::BestCandidates | Action::SetPickStatus | Action::Rate | Action::Weights
+                | Action::Weight | Action::SavePickList | Action::LoadPickList => {
+                self.eval_pick_list_command(invocation)?;
+            },
+            Action::ProjectBracket | Action::Alliances | Action::SetAlliance | Action::RemoveAlliance
+                | Action::ImportBracket | Action::SeriesLength => {
+                self.eval_bracket_command(invocation)?;
             }
         }
 
@@ -457,25 +2145,64 @@ impl ReplInterface
     }
 }
 
+fn default_data_source() -> DataSourceKind
+{
+    DataSourceKind::VexDb
+}
+
+fn default_vexdb_base_url() -> String
+{
+    vexdb::DEFAULT_BASE_URL.to_owned()
+}
+
+fn default_robotevents_base_url() -> String
+{
+    robotevents::DEFAULT_BASE_URL.to_owned()
+}
+
 // todo have option for VEXU support
 #[derive(Serialize, Deserialize)]
 pub struct ReplConfiguration
 {
     match_load_default_to_organization: bool,
-    current_team: String
+    current_team: String,
+    #[serde(default = "default_data_source")]
+    data_source: DataSourceKind,
+    #[serde(default = "default_vexdb_base_url")]
+    vexdb_base_url: String,
+    #[serde(default = "default_robotevents_base_url")]
+    robotevents_base_url: String,
+    // falls back to the ROBOTEVENTS_TOKEN environment variable when empty
+    #[serde(default)]
+    robotevents_token: String,
+    // json dump read by the fixture data source
+    #[serde(default)]
+    fixture_path: String,
+    // ranking ruleset by name; when empty it is chosen by the event's season
+    #[serde(default)]
+    ruleset: String
 }
 
-impl ReplConfiguration
+impl Default for ReplConfiguration
 {
     fn default() -> ReplConfiguration
     {
         ReplConfiguration
         {
             match_load_default_to_organization: false,
-            current_team: String::new()
+            current_team: String::new(),
+            data_source: default_data_source(),
+            vexdb_base_url: default_vexdb_base_url(),
+            robotevents_base_url: default_robotevents_base_url(),
+            robotevents_token: String::new(),
+            fixture_path: String::new(),
+            ruleset: String::new()
         }
     }
+}
 
+impl ReplConfiguration
+{
     pub fn get_current_team(&self) -> &String
     {
         &self.current_team
@@ -485,40 +2212,73 @@ impl ReplConfiguration
     {
         &self.match_load_default_to_organization
     }
-}
 
-struct Match
-{
-    team_list: Vec<String>,
+    pub fn get_data_source(&self) -> &DataSourceKind
+    {
+        &self.data_source
+    }
+
+    pub fn get_vexdb_base_url(&self) -> &String
+    {
+        &self.vexdb_base_url
+    }
+
+    pub fn get_robotevents_base_url(&self) -> &String
+    {
+        &self.robotevents_base_url
+    }
+
+    pub fn get_robotevents_token(&self) -> &String
+    {
+        &self.robotevents_token
+    }
 
-    round: u8,
-    match_num: u16,
-    division: String,
+    pub fn get_fixture_path(&self) -> &String
+    {
+        &self.fixture_path
+    }
 
-    red1: String,
-    red2: String,
-    blue1: String,
-    blue2: String,
+    pub fn get_ruleset(&self) -> &String
+    {
+        &self.ruleset
+    }
 
-    red_score: u16,
-    blue_score: u16,
+    /// Every key of the configuration file, as serialized
+    pub fn get_keys() -> Vec<String>
+    {
+        match serde_json::to_value(ReplConfiguration::default())
+        {
+            Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+            _ => Vec::new()
+        }
+    }
 
-    scored: bool
+    pub fn get_value(&self, key: &str) -> Option<serde_json::Value>
+    {
+        match serde_json::to_value(self)
+        {
+            Ok(serde_json::Value::Object(mut fields)) => fields.remove(&key.to_ascii_lowercase()),
+            _ => None
+        }
+    }
 }
 
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone)]
 pub enum ProgramContext
 {
     CompetitionContext(String), // sku
     DivisionContext(String), // division name
-    RoundContext(u16), // round number
-    MatchListContext(Vec<Match>),
-    MatchContext(Match), // match id
+    RoundContext(Round),
+    MatchListContext(MatchList),
+    PickListContext(PickList),
+    BracketContext(Bracket),
+    MatchContext(Box<Match>), // match id
     ConfigContext,
     StatsContext,
     HistoryContext, // team name
     TeamContext(String), // team name
-    OrganizationContext(String), // organization name
-    BaseContext
+    OrganizationContext(String) // organization name
 }
 
 impl fmt::Display for ProgramContext
@@ -539,15 +2299,21 @@ impl fmt::Display for ProgramContext
             ProgramContext::DivisionContext(division_name) => {
                 write!(f, "{}> ", division_name)
             },
-            ProgramContext::RoundContext(round_num) => {
-                write!(f, "{}> ", round_num)
+            ProgramContext::RoundContext(round) => {
+                write!(f, "{}> ", round.abbreviation())
             },
             ProgramContext::MatchContext(match_struct) => {
-                write!(f, "#{}> ", match_struct.match_num)
+                write!(f, "{}> ", match_struct.name())
             },
-            ProgramContext::MatchListContext(match_list) => {
+            ProgramContext::MatchListContext(_) => {
                 write!(f, "list> ")
             },
+            ProgramContext::PickListContext(_) => {
+                write!(f, "picklist> ")
+            },
+            ProgramContext::BracketContext(_) => {
+                write!(f, "bracket> ")
+            },
             ProgramContext::OrganizationContext(organization_name) => {
                 write!(f, "{}> ", organization_name)
             },
@@ -556,10 +2322,173 @@ impl fmt::Display for ProgramContext
             },
             ProgramContext::StatsContext => {
                 write!(f, "stats> ")
-            },
-            ProgramContext::BaseContext => {
-                panic!("Calling fmt on base context?");
             }
         }
     }
-}
\ No newline at end of file
+}
+
+impl ProgramContext
+{
+    /// The part of a `cd` path that enters this context, e.g. `comp/RE-VRC-23-1234`
+    ///
+    /// Elimination matches have no such path and show their name instead
+    pub fn get_path_segment(&self, parents: &[ProgramContext]) -> String
+    {
+        match self
+        {
+            ProgramContext::CompetitionContext(sku) => format!("comp/{}", sku),
+            ProgramContext::DivisionContext(division_name) => format!("division/{}", division_name),
+            ProgramContext::RoundContext(round) => format!("round/{}", round.abbreviation().to_ascii_lowercase()),
+            ProgramContext::MatchListContext(_) => String::from("match/load"),
+            ProgramContext::PickListContext(_) => String::from("picklist"),
+            ProgramContext::BracketContext(_) => String::from("bracket"),
+            ProgramContext::MatchContext(match_struct) => {
+                if let Some(ProgramContext::MatchListContext(match_list)) = parents.last()
+                {
+                    if let Some(row) = match_list.get_loaded_row(match_struct)
+                    {
+                        return format!("select/{}", row);
+                    }
+                    // stepped to a match the list never had
+                    return format!("../{}", lookup_path(match_struct, parents));
+                }
+                lookup_path(match_struct, parents)
+            },
+            ProgramContext::ConfigContext => String::from("config"),
+            ProgramContext::StatsContext => String::from("stats"),
+            ProgramContext::HistoryContext => String::from("history"),
+            ProgramContext::TeamContext(name) | ProgramContext::OrganizationContext(name) => format!("team/{}", name)
+        }
+    }
+}
+
+/// `match lookup` as path segments, naming the round unless the stack already selects it
+fn lookup_path(match_struct: &Match, parents: &[ProgramContext]) -> String
+{
+    let selected = parents.iter().rev()
+        .find_map(|context| if let ProgramContext::RoundContext(round) = context { Some(*round) } else { None })
+        .unwrap_or(Round::Qualification);
+    let mut path = format!("match/lookup/{}", match_struct.match_num);
+    if match_struct.round != selected
+    {
+        path.push_str(&format!("/round/{}", match_struct.round.abbreviation().to_ascii_lowercase()));
+    }
+    if match_struct.round.is_elimination()
+    {
+        path.push_str(&format!("/series/{}", match_struct.instance));
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::{self, SKU};
+
+    fn fixture() -> FixtureSource
+    {
+        let mut final_match = testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 20)));
+        final_match.round = Round::Final;
+        FixtureSource
+        {
+            teams: ["1A", "2A", "3A", "4A"].iter().map(|number| testing::team(number)).collect(),
+            events: vec![testing::event(SKU, "2024-01-20")],
+            matches: vec![
+                testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 20))),
+                testing::qualification(2, &["1A", "3A"], &["2A", "4A"], Some((10, 25))),
+                testing::qualification(3, &["1A", "4A"], &["2A", "3A"], None),
+                final_match
+            ],
+            ..FixtureSource::default()
+        }
+    }
+
+    fn repl() -> ReplInterface
+    {
+        ReplInterface::with_data_source(ReplConfiguration::default(), Box::new(fixture()))
+    }
+
+    fn eval(repl: &mut ReplInterface, input: &str) -> Result<(), ReplError>
+    {
+        repl.eval(input.to_owned())
+    }
+
+    #[test]
+    fn enters_and_leaves_contexts()
+    {
+        let mut repl = repl();
+        eval(&mut repl, "team 1a").unwrap();
+        assert_eq!(repl.get_path(), "/team/1A");
+        eval(&mut repl, "exit").unwrap();
+
+        eval(&mut repl, &format!("comp {}", SKU)).unwrap();
+        eval(&mut repl, "match lookup 2").unwrap();
+        assert_eq!(repl.get_path(), format!("/comp/{}/match/lookup/2", SKU));
+        eval(&mut repl, "next").unwrap();
+        assert_eq!(repl.get_path(), format!("/comp/{}/match/lookup/3", SKU));
+        eval(&mut repl, "up 2").unwrap();
+        assert_eq!(repl.get_path(), "/");
+        eval(&mut repl, "back").unwrap();
+        assert_eq!(repl.get_path(), format!("/comp/{}/match/lookup/3", SKU));
+        eval(&mut repl, "top").unwrap();
+        assert!(matches!(eval(&mut repl, "exit"), Err(ReplError::Exit)));
+    }
+
+    #[test]
+    fn returns_typed_errors()
+    {
+        let mut repl = repl();
+        assert!(matches!(eval(&mut repl, "tema 1A"), Err(ReplError::UnknownCommand { suggestion: Some(_), .. })));
+        assert!(matches!(eval(&mut repl, "back"), Err(ReplError::InvalidInput(_))));
+        assert!(matches!(eval(&mut repl, "alliances"), Err(ReplError::UnknownCommand { .. })));
+
+        eval(&mut repl, &format!("comp {}", SKU)).unwrap();
+        assert!(matches!(eval(&mut repl, "match lookup 9"), Err(ReplError::InvalidInput(_))));
+        assert!(matches!(eval(&mut repl, "match lookup"), Err(ReplError::MissingArgument { .. })));
+        // a failed command leaves the stack alone
+        assert_eq!(repl.get_path(), format!("/comp/{}", SKU));
+        assert!(matches!(eval(&mut repl, "cd match/lookup/9"), Err(ReplError::InvalidInput(_))));
+        assert_eq!(repl.get_path(), format!("/comp/{}", SKU));
+    }
+
+    #[test]
+    fn pwd_paths_work_with_cd()
+    {
+        let mut repl = repl();
+        let paths = [
+            format!("/comp/{}/match/load/select/2", SKU),
+            format!("/comp/{}/match/lookup/1/round/f/series/1", SKU),
+            format!("/comp/{}/round/f/match/lookup/1/series/1", SKU),
+            format!("/comp/{}/team/2A/match/lookup/3", SKU)
+        ];
+        for path in &paths
+        {
+            eval(&mut repl, &format!("cd {}", path)).unwrap();
+            assert_eq!(&repl.get_path(), path);
+            eval(&mut repl, "top").unwrap();
+        }
+
+        eval(&mut repl, &format!("comp {}", SKU)).unwrap();
+        eval(&mut repl, "match load").unwrap();
+        eval(&mut repl, "sort margin").unwrap();
+        eval(&mut repl, "select 1").unwrap();
+        // the path selects the row of a freshly loaded list, not the sorted one
+        assert_eq!(repl.get_path(), format!("/comp/{}/match/load/select/2", SKU));
+    }
+
+    #[test]
+    fn brackets_need_a_division_at_multi_division_events()
+    {
+        let mut source = fixture();
+        let mut other_final = testing::qualification(1, &["5A", "6A"], &["7A", "8A"], Some((30, 20)));
+        other_final.round = Round::Final;
+        other_final.division = String::from("Other");
+        source.matches.push(other_final);
+        let mut repl = ReplInterface::with_data_source(ReplConfiguration::default(), Box::new(source));
+
+        eval(&mut repl, &format!("comp {}", SKU)).unwrap();
+        assert!(matches!(eval(&mut repl, "bracket"), Err(ReplError::InvalidInput(_))));
+        assert_eq!(repl.get_path(), format!("/comp/{}", SKU));
+    }
+}
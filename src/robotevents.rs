@@ -0,0 +1,676 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::model::{Alliance, Award, Event, Match, Ranking, Round, Skill, SkillType, Team};
+use crate::source::{DataSource, Query, SourceError};
+
+pub const DEFAULT_BASE_URL: &str = "https://www.robotevents.com/api/v2";
+
+/// Environment variable consulted when no token is configured
+pub const TOKEN_ENVIRONMENT_VARIABLE: &str = "ROBOTEVENTS_TOKEN";
+
+/// The largest page size RobotEvents will honor
+const PAGE_SIZE: usize = 250;
+
+#[derive(Deserialize)]
+struct Page<T>
+{
+    meta: PageMeta,
+    data: Vec<T>
+}
+
+#[derive(Deserialize)]
+struct PageMeta
+{
+    current_page: u32,
+    last_page: u32
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+struct IdInfo
+{
+    id: u32,
+    name: String,
+    code: Option<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Location
+{
+    venue: Option<String>,
+    city: Option<String>,
+    region: Option<String>,
+    country: Option<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawTeam
+{
+    id: u32,
+    number: String,
+    team_name: String,
+    robot_name: Option<String>,
+    organization: Option<String>,
+    location: Location,
+    registered: bool,
+    program: IdInfo,
+    grade: Option<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawEvent
+{
+    id: u32,
+    sku: String,
+    name: String,
+    start: Option<String>,
+    end: Option<String>,
+    season: IdInfo,
+    program: IdInfo,
+    location: Location,
+    divisions: Vec<IdInfo>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct AllianceTeam
+{
+    team: IdInfo,
+    sitting: bool
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawAlliance
+{
+    color: String,
+    score: i32,
+    teams: Vec<AllianceTeam>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawMatch
+{
+    event: IdInfo,
+    division: IdInfo,
+    round: u8,
+    instance: u16,
+    matchnum: u16,
+    field: Option<String>,
+    scheduled: Option<String>,
+    started: Option<String>,
+    scored: bool,
+    alliances: Vec<RawAlliance>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawRanking
+{
+    event: IdInfo,
+    division: IdInfo,
+    team: IdInfo,
+    rank: u16,
+    wins: u16,
+    losses: u16,
+    ties: u16,
+    wp: u16,
+    ap: u16,
+    sp: u16,
+    high_score: u16
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TeamWinner
+{
+    team: IdInfo
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawAward
+{
+    event: IdInfo,
+    title: String,
+    qualifications: Vec<String>,
+    #[serde(rename = "teamWinners")]
+    team_winners: Vec<TeamWinner>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawSkill
+{
+    event: IdInfo,
+    team: IdInfo,
+    #[serde(rename = "type")]
+    skill_type: String,
+    rank: u16,
+    score: u16,
+    attempts: u16
+}
+
+fn sku_of(event: &IdInfo) -> String
+{
+    event.code.clone().unwrap_or_default()
+}
+
+impl From<RawTeam> for Team
+{
+    fn from(raw: RawTeam) -> Team
+    {
+        Team
+        {
+            number: raw.number,
+            program: raw.program.code.unwrap_or(raw.program.name),
+            team_name: raw.team_name,
+            robot_name: raw.robot_name.unwrap_or_default(),
+            organisation: raw.organization.unwrap_or_default(),
+            city: raw.location.city.unwrap_or_default(),
+            region: raw.location.region.unwrap_or_default(),
+            country: raw.location.country.unwrap_or_default(),
+            grade: raw.grade.unwrap_or_default(),
+            registered: raw.registered
+        }
+    }
+}
+
+impl From<RawEvent> for Event
+{
+    fn from(raw: RawEvent) -> Event
+    {
+        Event
+        {
+            sku: raw.sku,
+            name: raw.name,
+            program: raw.program.code.unwrap_or(raw.program.name),
+            season: raw.season.name,
+            venue: raw.location.venue.unwrap_or_default(),
+            city: raw.location.city.unwrap_or_default(),
+            region: raw.location.region.unwrap_or_default(),
+            country: raw.location.country.unwrap_or_default(),
+            start: raw.start.unwrap_or_default(),
+            end: raw.end.unwrap_or_default(),
+            divisions: raw.divisions.into_iter().map(|division| division.name).collect()
+        }
+    }
+}
+
+impl From<RawAlliance> for Alliance
+{
+    fn from(raw: RawAlliance) -> Alliance
+    {
+        Alliance
+        {
+            sitting: raw.teams.iter().find(|team| team.sitting).map(|team| team.team.name.clone()),
+            teams: raw.teams.into_iter().map(|team| team.team.name).collect(),
+            score: raw.score.max(0) as u16
+        }
+    }
+}
+
+impl From<RawMatch> for Match
+{
+    fn from(raw: RawMatch) -> Match
+    {
+        let mut red = Alliance::default();
+        let mut blue = Alliance::default();
+        for alliance in raw.alliances
+        {
+            if alliance.color == "red"
+            {
+                red = Alliance::from(alliance);
+            }
+            else
+            {
+                blue = Alliance::from(alliance);
+            }
+        }
+
+        Match
+        {
+            sku: sku_of(&raw.event),
+            division: raw.division.name,
+            round: Round::from_robotevents_code(raw.round),
+            instance: raw.instance,
+            match_num: raw.matchnum,
+            field: raw.field.unwrap_or_default(),
+            scheduled: raw.scheduled,
+            started: raw.started,
+            red,
+            blue,
+            scored: raw.scored
+        }
+    }
+}
+
+impl From<RawRanking> for Ranking
+{
+    fn from(raw: RawRanking) -> Ranking
+    {
+        Ranking
+        {
+            sku: sku_of(&raw.event),
+            division: raw.division.name,
+            team: raw.team.name,
+            rank: raw.rank,
+            wins: raw.wins,
+            losses: raw.losses,
+            ties: raw.ties,
+            wp: raw.wp,
+            ap: raw.ap,
+            sp: raw.sp,
+            max_score: raw.high_score,
+            opr: 0.0,
+            dpr: 0.0,
+            ccwm: 0.0
+        }
+    }
+}
+
+impl From<RawSkill> for Skill
+{
+    fn from(raw: RawSkill) -> Skill
+    {
+        let skill_type = match raw.skill_type.as_str()
+        {
+            "driver" => SkillType::Driver,
+            "programming" => SkillType::Programming,
+            _ => SkillType::Combined
+        };
+
+        Skill
+        {
+            sku: sku_of(&raw.event),
+            team: raw.team.name,
+            skill_type,
+            rank: raw.rank,
+            score: raw.score,
+            attempts: raw.attempts
+        }
+    }
+}
+
+/// Awards can have several winning teams, so each winner becomes its own `Award`
+fn flatten_award(raw: RawAward) -> Vec<Award>
+{
+    let sku = sku_of(&raw.event);
+    let title = raw.title;
+    let qualifications = raw.qualifications;
+    raw.team_winners.into_iter()
+        .map(|winner| Award
+        {
+            sku: sku.clone(),
+            name: title.clone(),
+            team: winner.team.name,
+            qualifies: qualifications.clone()
+        })
+        .collect()
+}
+
+/// Client for the RobotEvents v2 API
+///
+/// Team numbers and event skus are resolved to RobotEvents ids on first use
+/// and remembered for the lifetime of the client.
+pub struct RobotEventsClient
+{
+    base_url: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+    team_ids: RefCell<HashMap<String, u32>>,
+    events: RefCell<HashMap<String, (u32, Vec<IdInfo>)>>
+}
+
+impl RobotEventsClient
+{
+    /// Falls back to the `ROBOTEVENTS_TOKEN` environment variable when `token` is empty
+    pub fn new(base_url: &str, token: &str) -> RobotEventsClient
+    {
+        let token = if token.is_empty()
+        {
+            std::env::var(TOKEN_ENVIRONMENT_VARIABLE).ok()
+        }
+        else
+        {
+            Some(token.to_owned())
+        };
+
+        RobotEventsClient
+        {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            token,
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(15))
+                .build(),
+            team_ids: RefCell::new(HashMap::new()),
+            events: RefCell::new(HashMap::new())
+        }
+    }
+
+    /// Fetches every page of a paginated endpoint
+    fn get<T: DeserializeOwned>(&self, path: &str, parameters: &[(&str, String)]) -> Result<Vec<T>, SourceError>
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let mut results = Vec::<T>::new();
+        let mut page_number = 1;
+
+        loop
+        {
+            let mut request = self.agent.get(&url)
+                .set("Accept", "application/json");
+            if let Some(token) = &self.token
+            {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            for (key, value) in parameters
+            {
+                request = request.query(key, value);
+            }
+            request = request
+                .query("page", &page_number.to_string())
+                .query("per_page", &PAGE_SIZE.to_string());
+
+            let response = match request.call()
+            {
+                Ok(response) => response,
+                Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => {
+                    return Err(SourceError::Unauthorized);
+                },
+                Err(ureq::Error::Status(code, response)) => {
+                    return Err(SourceError::Api(format!("RobotEvents returned {} for {}", code, response.get_url())));
+                },
+                Err(error) => {
+                    return Err(SourceError::Network(error.to_string()));
+                }
+            };
+            let page: Page<T> = serde_json::from_reader(response.into_reader())
+                .map_err(|error| SourceError::Parse(error.to_string()))?;
+
+            results.extend(page.data);
+
+            if page.meta.current_page >= page.meta.last_page
+            {
+                return Ok(results);
+            }
+            page_number = page.meta.current_page + 1;
+        }
+    }
+
+    fn get_raw_teams(&self, numbers: &[String]) -> Result<Vec<RawTeam>, SourceError>
+    {
+        if numbers.is_empty()
+        {
+            return Ok(Vec::new());
+        }
+
+        let parameters = numbers.iter()
+            .map(|number| ("number[]", number.clone()))
+            .collect::<Vec<(&str, String)>>();
+        let teams = self.get::<RawTeam>("/teams", &parameters)?;
+
+        let mut team_ids = self.team_ids.borrow_mut();
+        for team in &teams
+        {
+            team_ids.insert(team.number.to_ascii_uppercase(), team.id);
+        }
+        Ok(teams)
+    }
+
+    fn get_team_id(&self, number: &str) -> Result<u32, SourceError>
+    {
+        let number = number.to_ascii_uppercase();
+        if let Some(id) = self.team_ids.borrow().get(&number)
+        {
+            return Ok(*id);
+        }
+
+        self.get_raw_teams(std::slice::from_ref(&number))?;
+        self.team_ids.borrow().get(&number).copied()
+            .ok_or_else(|| SourceError::Api(format!("no team {} on RobotEvents", number)))
+    }
+
+    /// Returns the event's id and divisions
+    fn get_event_info(&self, sku: &str) -> Result<(u32, Vec<IdInfo>), SourceError>
+    {
+        let sku = sku.to_ascii_uppercase();
+        if let Some(info) = self.events.borrow().get(&sku)
+        {
+            return Ok(info.clone());
+        }
+
+        let events = self.get::<RawEvent>("/events", &[("sku[]", sku.clone())])?;
+        let event = events.into_iter().next()
+            .ok_or_else(|| SourceError::Api(format!("no event {} on RobotEvents", sku)))?;
+        let info = (event.id, event.divisions);
+        self.events.borrow_mut().insert(sku, info.clone());
+        Ok(info)
+    }
+
+    /// The `team[]` filter accepted by event sub-resources
+    fn team_filter(&self, query: Query) -> Result<Vec<(&'static str, String)>, SourceError>
+    {
+        match query.team
+        {
+            Some(team) => Ok(vec![("team[]", self.get_team_id(team)?.to_string())]),
+            None => Ok(Vec::new())
+        }
+    }
+}
+
+impl DataSource for RobotEventsClient
+{
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        Ok(self.get_raw_teams(numbers)?.into_iter().map(Team::from).collect())
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        let mut parameters = Vec::new();
+        if let Some(sku) = query.sku
+        {
+            parameters.push(("sku[]", sku.to_owned()));
+        }
+        if let Some(team) = query.team
+        {
+            parameters.push(("team[]", self.get_team_id(team)?.to_string()));
+        }
+        if let Some(region) = query.region
+        {
+            parameters.push(("region", region.to_owned()));
+        }
+
+        let events = self.get::<RawEvent>("/events", &parameters)?;
+        let mut cached_events = self.events.borrow_mut();
+        for event in &events
+        {
+            cached_events.insert(event.sku.to_ascii_uppercase(), (event.id, event.divisions.clone()));
+        }
+        Ok(events.into_iter().map(Event::from).collect())
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        let raw_matches = if let Some(sku) = query.sku
+        {
+            let (event_id, divisions) = self.get_event_info(sku)?;
+            let parameters = self.team_filter(query)?;
+            let mut raw_matches = Vec::new();
+            for division in divisions
+            {
+                let path = format!("/events/{}/divisions/{}/matches", event_id, division.id);
+                raw_matches.extend(self.get::<RawMatch>(&path, &parameters)?);
+            }
+            raw_matches
+        }
+        else if let Some(team) = query.team
+        {
+            self.get::<RawMatch>(&format!("/teams/{}/matches", self.get_team_id(team)?), &[])?
+        }
+        else
+        {
+            return Err(SourceError::Unsupported("listing every match"));
+        };
+
+        // match payloads only name the event, so fill in the sku we asked for
+        Ok(raw_matches.into_iter()
+            .map(Match::from)
+            .map(|mut match_struct|
+            {
+                if let Some(sku) = query.sku
+                {
+                    match_struct.sku = sku.to_ascii_uppercase();
+                }
+                match_struct
+            })
+            .collect())
+    }
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        let raw_rankings = if let Some(sku) = query.sku
+        {
+            let (event_id, divisions) = self.get_event_info(sku)?;
+            let parameters = self.team_filter(query)?;
+            let mut raw_rankings = Vec::new();
+            for division in divisions
+            {
+                let path = format!("/events/{}/divisions/{}/rankings", event_id, division.id);
+                raw_rankings.extend(self.get::<RawRanking>(&path, &parameters)?);
+            }
+            raw_rankings
+        }
+        else if let Some(team) = query.team
+        {
+            self.get::<RawRanking>(&format!("/teams/{}/rankings", self.get_team_id(team)?), &[])?
+        }
+        else
+        {
+            return Err(SourceError::Unsupported("listing every ranking"));
+        };
+
+        Ok(raw_rankings.into_iter().map(Ranking::from).collect())
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        let raw_awards = if let Some(sku) = query.sku
+        {
+            let (event_id, _) = self.get_event_info(sku)?;
+            self.get::<RawAward>(&format!("/events/{}/awards", event_id), &self.team_filter(query)?)?
+        }
+        else if let Some(team) = query.team
+        {
+            self.get::<RawAward>(&format!("/teams/{}/awards", self.get_team_id(team)?), &[])?
+        }
+        else
+        {
+            return Err(SourceError::Unsupported("listing every award"));
+        };
+
+        Ok(raw_awards.into_iter().flat_map(flatten_award).collect())
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        let raw_skills = if let Some(sku) = query.sku
+        {
+            let (event_id, _) = self.get_event_info(sku)?;
+            self.get::<RawSkill>(&format!("/events/{}/skills", event_id), &self.team_filter(query)?)?
+        }
+        else if let Some(team) = query.team
+        {
+            self.get::<RawSkill>(&format!("/teams/{}/skills", self.get_team_id(team)?), &[])?
+        }
+        else
+        {
+            return Err(SourceError::Unsupported("listing every skills run"));
+        };
+
+        Ok(raw_skills.into_iter().map(Skill::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    const EVENT: &str = r#"{"meta": {"current_page": 1, "last_page": 1}, "data": [
+        {"id": 7, "sku": "RE-VRC-23-1234", "name": "Event", "season": {"id": 181, "name": "VRC 2023-2024: Over Under"},
+         "program": {"id": 1, "name": "VEX Robotics Competition", "code": "VRC"}, "location": {"city": "Austin"},
+         "divisions": [{"id": 1, "name": "Division 1"}]}
+    ]}"#;
+
+    fn serve_event() -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>)
+    {
+        testing::serve(|path|
+        {
+            if path.starts_with("/events?")
+            {
+                return EVENT.to_owned();
+            }
+            let (page, team) = if path.contains("page=1&") { (1, "1A") } else { (2, "2A") };
+            format!(r#"{{"meta": {{"current_page": {}, "last_page": 2}}, "data": [
+                {{"event": {{"id": 7, "name": "Event", "code": "RE-VRC-23-1234"}}, "division": {{"id": 1, "name": "Division 1"}},
+                  "round": 6, "instance": {}, "matchnum": 1, "field": null, "scheduled": null, "started": "2024-01-20T10:00:00-06:00",
+                  "scored": true, "alliances": [
+                    {{"color": "blue", "score": -1, "teams": [{{"team": {{"id": 20, "name": "3A"}}, "sitting": false}}]}},
+                    {{"color": "red", "score": 55, "teams": [{{"team": {{"id": 10, "name": "{}"}}, "sitting": false}},
+                                                          {{"team": {{"id": 11, "name": "4A"}}, "sitting": true}}]}}
+                  ]}}
+            ]}}"#, page, page, team)
+        })
+    }
+
+    #[test]
+    fn parses_matches_across_pages()
+    {
+        let (base_url, requests) = serve_event();
+        let client = RobotEventsClient::new(&base_url, "token");
+
+        let matches = client.get_matches(Query::event("re-vrc-23-1234")).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].sku, "RE-VRC-23-1234");
+        assert_eq!(matches[0].division, "Division 1");
+        assert_eq!(matches[0].name(), "R16 1-1");
+        assert_eq!(matches[1].name(), "R16 2-1");
+        assert_eq!(matches[0].red.teams, vec!["1A", "4A"]);
+        assert_eq!(matches[1].red.teams, vec!["2A", "4A"]);
+        assert_eq!(matches[0].red.sitting.as_deref(), Some("4A"));
+        // RobotEvents sends -1 for an alliance that has not scored
+        assert_eq!((matches[0].red.score, matches[0].blue.score), (55, 0));
+        assert_eq!(matches[0].field, "");
+        assert!(matches[0].started.is_some());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].starts_with("/events/7/divisions/1/matches?page=1&"));
+        assert!(requests[2].starts_with("/events/7/divisions/1/matches?page=2&"));
+    }
+
+    #[test]
+    fn remembers_event_ids()
+    {
+        let (base_url, requests) = serve_event();
+        let client = RobotEventsClient::new(&base_url, "token");
+
+        let events = client.get_events(Query::event("RE-VRC-23-1234")).unwrap();
+        assert_eq!(events[0].season, "VRC 2023-2024: Over Under");
+        assert_eq!(events[0].program, "VRC");
+        assert_eq!(events[0].divisions, vec!["Division 1"]);
+
+        client.get_matches(Query::event("RE-VRC-23-1234")).unwrap();
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.iter().filter(|path| path.starts_with("/events?")).count(), 1);
+    }
+}
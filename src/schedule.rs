@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::model::{Color, Match, Round};
+use crate::predict::Predictor;
+use crate::rating::{EloRatings, MatchRating, RatingModel, TrueSkillRatings};
+use crate::stats;
+
+/// One qualification match from a team's point of view
+pub struct ScheduledMatch
+{
+    pub name: String,
+    pub partners: Vec<String>,
+    pub opponents: Vec<String>,
+    pub win_probability: f64,
+    /// 1 for a win, 0.5 for a tie and 0 for a loss
+    pub outcome: f64
+}
+
+impl fmt::Display for ScheduledMatch
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let result = if self.outcome == 1.0 { "won" } else if self.outcome == 0.0 { "lost" } else { "tied" };
+        write!(f, "{:<8} with {:<16} vs {:<16} {:>5.1}% to win, {}",
+            self.name, self.partners.join(" "), self.opponents.join(" "), 100.0 * self.win_probability, result)
+    }
+}
+
+/// How hard a team's qualification schedule was, and how its results compare
+pub struct ScheduleStrength
+{
+    pub team: String,
+    pub matches: Vec<ScheduledMatch>,
+    partner_rating: f64,
+    opponent_rating: f64
+}
+
+impl ScheduleStrength
+{
+    /// Average rating of the team's partners
+    pub fn get_partner_rating(&self) -> f64
+    {
+        self.partner_rating
+    }
+
+    /// Average rating of the team's opponents
+    pub fn get_opponent_rating(&self) -> f64
+    {
+        self.opponent_rating
+    }
+
+    /// Wins a team would expect from this schedule, with ties counting half
+    pub fn get_expected_wins(&self) -> f64
+    {
+        self.matches.iter().map(|match_struct| match_struct.win_probability).sum()
+    }
+
+    pub fn get_actual_wins(&self) -> f64
+    {
+        self.matches.iter().map(|match_struct| match_struct.outcome).sum()
+    }
+
+    /// Wins above what the schedule predicted; positive is lucky
+    pub fn get_luck(&self) -> f64
+    {
+        self.get_actual_wins() - self.get_expected_wins()
+    }
+}
+
+impl fmt::Display for ScheduleStrength
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{:<7} partners {:>7.1}  opponents {:>7.1}  expected {:>4.1} wins  won {:>4.1}  luck {:>+5.1}",
+            self.team, self.get_partner_rating(), self.get_opponent_rating(),
+            self.get_expected_wins(), self.get_actual_wins(), self.get_luck())
+    }
+}
+
+/// Rates every team at the event with the model, knowing all of its scored matches
+fn get_team_ratings(model: RatingModel, prior_events: &[Vec<Match>], matches: &[Match]) -> HashMap<String, f64>
+{
+    match model
+    {
+        RatingModel::Opr => {
+            stats::power_ratings(matches).into_iter().map(|rating| (rating.team, rating.opr)).collect()
+        },
+        RatingModel::Elo => {
+            let mut ratings = EloRatings::default();
+            for event_matches in prior_events.iter().map(Vec::as_slice).chain(std::iter::once(matches))
+            {
+                ratings.add_event(event_matches);
+            }
+            matches.iter().flat_map(Match::teams).map(|team| (team.to_ascii_uppercase(), ratings.get_rating(team))).collect()
+        },
+        RatingModel::TrueSkill => {
+            let mut ratings = TrueSkillRatings::default();
+            for event_matches in prior_events.iter().map(Vec::as_slice).chain(std::iter::once(matches))
+            {
+                ratings.add_event(event_matches);
+            }
+            matches.iter().flat_map(Match::teams).map(|team| (team.to_ascii_uppercase(), ratings.get_skill(team).mean)).collect()
+        }
+    }
+}
+
+/// Strength of schedule for every team with a scored qualification match
+///
+/// Ratings use every scored match at the event, so a schedule is judged by
+/// how strong partners and opponents turned out to be. Each win probability
+/// only knows the matches before it, as in `Calibration::backtest`, so a result
+/// never predicts itself. Sorted hardest schedule first, by opponent minus
+/// partner rating.
+pub fn schedule_strengths(model: RatingModel, prior_events: &[Vec<Match>], matches: &[Match]) -> Vec<ScheduleStrength>
+{
+    let ratings = get_team_ratings(model, prior_events, matches);
+    let rating_of = |team: &String| ratings.get(&team.to_ascii_uppercase()).copied().unwrap_or(0.0);
+    let mut predictor = Predictor::new(model, prior_events);
+
+    let mut scored = matches.iter()
+        .filter(|match_struct| match_struct.scored && match_struct.round != Round::Practice)
+        .collect::<Vec<&Match>>();
+    scored.sort_by_key(|match_struct| match_struct.schedule_order());
+
+    let mut schedules: HashMap<String, Vec<ScheduledMatch>> = HashMap::new();
+    for match_struct in scored
+    {
+        let red_win_probability = predictor.predict(match_struct).red_win_probability;
+        predictor.add_match(match_struct);
+        if match_struct.round != Round::Qualification
+        {
+            continue;
+        }
+        let red_outcome = if match_struct.is_tie() { 0.5 } else if match_struct.red.score > match_struct.blue.score { 1.0 } else { 0.0 };
+        for color in &[Color::Red, Color::Blue]
+        {
+            let alliance = match_struct.alliance(*color).playing().cloned().collect::<Vec<String>>();
+            let opponents = match_struct.alliance(color.opposite()).playing().cloned().collect::<Vec<String>>();
+            let (win_probability, outcome) = match color
+            {
+                Color::Red => (red_win_probability, red_outcome),
+                Color::Blue => (1.0 - red_win_probability, 1.0 - red_outcome)
+            };
+
+            for team in &alliance
+            {
+                schedules.entry(team.to_ascii_uppercase()).or_default().push(ScheduledMatch
+                {
+                    name: match_struct.name(),
+                    partners: alliance.iter().filter(|partner| *partner != team).cloned().collect(),
+                    opponents: opponents.clone(),
+                    win_probability,
+                    outcome
+                });
+            }
+        }
+    }
+
+    let average = |teams: &mut dyn Iterator<Item = &String>|
+    {
+        let (total, count) = teams.fold((0.0, 0), |(total, count), team| (total + rating_of(team), count + 1));
+        if count == 0 { 0.0 } else { total / count as f64 }
+    };
+
+    let mut strengths = schedules.into_iter()
+        .map(|(team, matches)| ScheduleStrength
+        {
+            partner_rating: average(&mut matches.iter().flat_map(|match_struct| match_struct.partners.iter())),
+            opponent_rating: average(&mut matches.iter().flat_map(|match_struct| match_struct.opponents.iter())),
+            team,
+            matches
+        })
+        .collect::<Vec<ScheduleStrength>>();
+    strengths.sort_by(|a, b|
+    {
+        let difficulty = |strength: &ScheduleStrength| strength.get_opponent_rating() - strength.get_partner_rating();
+        difficulty(b).total_cmp(&difficulty(a)).then(a.team.cmp(&b.team))
+    });
+    strengths
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn luck_is_actual_minus_expected_wins()
+    {
+        // with nothing known beforehand the match is a coin flip; 1A wins it
+        // by 10 and ends 28.8 Elo ahead
+        let matches = [testing::qualification(1, &["1A"], &["2A"], Some((20, 10)))];
+        let strengths = schedule_strengths(RatingModel::Elo, &[], &matches);
+        assert_eq!(strengths.len(), 2);
+
+        // 2A faced the stronger opponent, so has the harder schedule
+        let (harder, easier) = (&strengths[0], &strengths[1]);
+        assert_eq!(harder.team, "2A");
+        assert!((harder.get_opponent_rating() - 1514.387).abs() < 1e-3);
+        assert!((easier.get_opponent_rating() - 1485.613).abs() < 1e-3);
+        assert_eq!(easier.get_partner_rating(), 0.0);
+
+        assert!((easier.get_expected_wins() - 0.5).abs() < 1e-9);
+        assert_eq!(easier.get_actual_wins(), 1.0);
+        assert!((easier.get_luck() - 0.5).abs() < 1e-9);
+        assert!((harder.get_luck() + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn predicts_from_earlier_matches_only()
+    {
+        // the rematch is predicted from the first match alone: 28.8 Elo, a 54.1% favourite
+        let matches = [
+            testing::qualification(2, &["1A"], &["2A"], Some((20, 10))),
+            testing::qualification(1, &["1A"], &["2A"], Some((20, 10)))
+        ];
+        let strengths = schedule_strengths(RatingModel::Elo, &[], &matches);
+        let winner = strengths.iter().find(|strength| strength.team == "1A").unwrap();
+        let probabilities = winner.matches.iter().map(|match_struct| match_struct.win_probability).collect::<Vec<f64>>();
+        assert!((probabilities[0] - 0.5).abs() < 1e-9);
+        assert!((probabilities[1] - 0.5413).abs() < 1e-4);
+        assert!((winner.get_luck() - 0.9587).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ties_count_half_a_win()
+    {
+        let matches = [testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((15, 15)))];
+        for strength in schedule_strengths(RatingModel::Elo, &[], &matches)
+        {
+            assert_eq!(strength.get_actual_wins(), 0.5);
+            assert!(strength.get_luck().abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::model::{Color, Match, Ranking, Round};
+use crate::predict::Predictor;
+use crate::ranking::{Record, Ruleset};
+use crate::stats;
+
+/// Simulations run when `simulate` is not given a count
+pub const DEFAULT_RUNS: u32 = 5000;
+
+/// Most alliances an elimination bracket holds
+const MAX_ALLIANCES: usize = 16;
+
+/// An unscored match, reduced to what drawing its scores needs
+struct RemainingMatch
+{
+    red: Vec<usize>,
+    blue: Vec<usize>,
+    red_mean: f64,
+    blue_mean: f64,
+    deviation: f64
+}
+
+/// How one team finished across every simulation
+pub struct TeamOutlook
+{
+    pub team: String,
+    pub current_rank: usize,
+    /// How often the team finished at each rank, starting from first
+    rank_counts: Vec<u32>,
+    top_eight: u32,
+    captain: u32,
+    runs: u32
+}
+
+impl TeamOutlook
+{
+    pub fn get_rank_probabilities(&self) -> Vec<(usize, f64)>
+    {
+        self.rank_counts.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| (index + 1, *count as f64 / self.runs as f64))
+            .collect()
+    }
+
+    pub fn get_mean_rank(&self) -> f64
+    {
+        self.rank_counts.iter().enumerate().map(|(index, count)| (index + 1) as f64 * *count as f64).sum::<f64>() / self.runs as f64
+    }
+
+    /// The rank the team finishes at or above in the given share of simulations
+    pub fn get_percentile_rank(&self, share: f64) -> usize
+    {
+        let mut seen = 0;
+        for (index, count) in self.rank_counts.iter().enumerate()
+        {
+            seen += count;
+            if seen as f64 >= share * self.runs as f64
+            {
+                return index + 1;
+            }
+        }
+        self.rank_counts.len()
+    }
+
+    pub fn get_top_eight_probability(&self) -> f64
+    {
+        self.top_eight as f64 / self.runs as f64
+    }
+
+    pub fn get_captain_probability(&self) -> f64
+    {
+        self.captain as f64 / self.runs as f64
+    }
+}
+
+impl fmt::Display for TeamOutlook
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{:<7} now #{:<3} mean {:>5.1}  80% #{}-#{}  top 8 {:>5.1}%  captain {:>5.1}%",
+            self.team, self.current_rank, self.get_mean_rank(),
+            self.get_percentile_rank(0.1), self.get_percentile_rank(0.9),
+            100.0 * self.get_top_eight_probability(), 100.0 * self.get_captain_probability())
+    }
+}
+
+/// Plays out one division's unscored qualification matches `runs` times
+///
+/// Scores are drawn from the predictor's score model, shifted so the win
+/// probability matches the predictor's model. Teams are ranked by the
+/// ruleset's tiebreak chain then a coin flip, and alliance captains are chosen
+/// in rank order with each captain picking the highest OPR team still available.
+/// Autonomous points are projected from each team's official total at its
+/// current rate. The same seed always gives the same result.
+pub fn simulate(ruleset: &Ruleset, matches: &[Match], rankings: &[Ranking], predictor: &Predictor, runs: u32, seed: u64) -> Vec<TeamOutlook>
+{
+    let qualifications = matches.iter()
+        .filter(|match_struct| match_struct.round == Round::Qualification)
+        .collect::<Vec<&Match>>();
+
+    let (teams, current): (Vec<String>, Vec<Record>) = ruleset.get_records(matches, rankings).into_iter().unzip();
+    let index_of = teams.iter().enumerate().map(|(index, team)| (team.clone(), index)).collect::<HashMap<String, usize>>();
+    let indices = |match_struct: &Match, color: Color| match_struct.alliance(color).playing()
+        .filter_map(|team| index_of.get(&team.to_ascii_uppercase()).copied())
+        .collect::<Vec<usize>>();
+
+    let mut remaining = Vec::new();
+    for match_struct in qualifications.iter().filter(|match_struct| !match_struct.scored)
+    {
+        let (red, blue) = (indices(match_struct, Color::Red), indices(match_struct, Color::Blue));
+        let prediction = predictor.predict(match_struct);
+        // move both means so their gap gives the model's win probability
+        let margin_deviation = prediction.score_deviation * std::f64::consts::SQRT_2;
+        let margin = margin_deviation * stats::normal_quantile(prediction.red_win_probability.clamp(0.001, 0.999));
+        let middle = (prediction.red_score + prediction.blue_score) / 2.0;
+        remaining.push(RemainingMatch
+        {
+            red,
+            blue,
+            red_mean: middle + margin / 2.0,
+            blue_mean: middle - margin / 2.0,
+            deviation: prediction.score_deviation
+        });
+    }
+
+    let ap_rates = current.iter()
+        .map(|record| if record.played > 0 { record.ap / record.played as f64 } else { 0.0 })
+        .collect::<Vec<f64>>();
+
+    let strengths = stats::power_ratings(matches).into_iter()
+        .map(|rating| (rating.team, rating.opr))
+        .collect::<HashMap<String, f64>>();
+    let strength_of = |team: usize| strengths.get(&teams[team]).copied().unwrap_or(0.0);
+    let alliance_count = MAX_ALLIANCES.min(teams.len() / 2);
+
+    let no_coin_flips = vec![0.0; teams.len()];
+    let current_order = rank(ruleset, &current, &no_coin_flips);
+    let mut outlooks = teams.iter()
+        .map(|team| TeamOutlook
+        {
+            team: team.clone(),
+            current_rank: 0,
+            rank_counts: vec![0; teams.len()],
+            top_eight: 0,
+            captain: 0,
+            runs
+        })
+        .collect::<Vec<TeamOutlook>>();
+    for (place, team) in current_order.iter().enumerate()
+    {
+        outlooks[*team].current_rank = place + 1;
+    }
+
+    let mut random = ChaCha8Rng::seed_from_u64(seed);
+    for _ in 0..runs
+    {
+        let mut standings = current.clone();
+        for remaining_match in &remaining
+        {
+            let red_score = sample_score(&mut random, remaining_match.red_mean, remaining_match.deviation);
+            let blue_score = sample_score(&mut random, remaining_match.blue_mean, remaining_match.deviation);
+            for (alliance, own_score, opponent_score) in &[(&remaining_match.red, red_score, blue_score), (&remaining_match.blue, blue_score, red_score)]
+            {
+                for team in alliance.iter()
+                {
+                    ruleset.add_result(&mut standings[*team], *own_score, *opponent_score);
+                    standings[*team].ap += ap_rates[*team];
+                }
+            }
+        }
+
+        let coin_flips = (0..teams.len()).map(|_| random.gen::<f64>()).collect::<Vec<f64>>();
+        let order = rank(ruleset, &standings, &coin_flips);
+        for (place, team) in order.iter().enumerate()
+        {
+            outlooks[*team].rank_counts[place] += 1;
+            if place < 8
+            {
+                outlooks[*team].top_eight += 1;
+            }
+        }
+
+        // alliance selection: the best ranked team left captains and takes the best OPR left
+        let mut available = order.clone();
+        for _ in 0..alliance_count
+        {
+            if available.is_empty()
+            {
+                break;
+            }
+            let captain = available.remove(0);
+            outlooks[captain].captain += 1;
+
+            let pick = available.iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| strength_of(**a).total_cmp(&strength_of(**b)))
+                .map(|(position, _)| position);
+            if let Some(position) = pick
+            {
+                available.remove(position);
+            }
+        }
+    }
+
+    outlooks.sort_by(|a, b| a.get_mean_rank().total_cmp(&b.get_mean_rank()).then(a.team.cmp(&b.team)));
+    outlooks
+}
+
+/// Team indices from first to last by the tiebreak chain, then the coin flip
+fn rank(ruleset: &Ruleset, standings: &[Record], coin_flips: &[f64]) -> Vec<usize>
+{
+    let mut order = (0..standings.len()).collect::<Vec<usize>>();
+    order.sort_by(|a, b| ruleset.compare(&standings[*a], &standings[*b]).then(coin_flips[*b].total_cmp(&coin_flips[*a])));
+    order
+}
+
+/// A normally distributed score, by the Box-Muller transform, rounded and never negative
+fn sample_score(random: &mut ChaCha8Rng, mean: f64, deviation: f64) -> u16
+{
+    let uniform = 1.0 - random.gen::<f64>();
+    let angle = 2.0 * std::f64::consts::PI * random.gen::<f64>();
+    let normal = (-2.0 * uniform.ln()).sqrt() * angle.cos();
+    (mean + deviation * normal).round().max(0.0) as u16
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::rating::RatingModel;
+    use crate::testing;
+
+    fn outlooks(seed: u64) -> Vec<(String, Vec<u32>, u32, u32)>
+    {
+        let matches = vec![
+            testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 70))),
+            testing::qualification(2, &["1A", "3A"], &["2A", "4A"], Some((40, 60))),
+            testing::qualification(3, &["1A", "4A"], &["2A", "3A"], Some((50, 50))),
+            testing::qualification(4, &["1A", "2A"], &["3A", "4A"], None),
+            testing::qualification(5, &["1A", "3A"], &["2A", "4A"], None),
+            testing::qualification(6, &["1A", "4A"], &["2A", "3A"], None)
+        ];
+        let mut predictor = Predictor::new(RatingModel::Opr, &[]);
+        predictor.add_scored_matches(&matches);
+
+        simulate(&testing::ruleset(), &matches, &[], &predictor, 300, seed).into_iter()
+            .map(|outlook| (outlook.team, outlook.rank_counts, outlook.top_eight, outlook.captain))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_outlooks()
+    {
+        let first = outlooks(42);
+        assert_eq!(first, outlooks(42));
+        assert_eq!(first.len(), 4);
+        for (_, rank_counts, top_eight, _) in &first
+        {
+            assert_eq!(rank_counts.iter().sum::<u32>(), 300);
+            // four teams all make the top eight
+            assert_eq!(*top_eight, 300);
+        }
+    }
+}
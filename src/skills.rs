@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::model::{Skill, SkillType};
+
+/// Skills spots assumed for a region when `spots` is not given
+pub const DEFAULT_WORLDS_SPOTS: usize = 2;
+
+/// A team's best runs at one event
+#[derive(Clone, Default)]
+pub struct EventSkills
+{
+    pub sku: String,
+    pub team: String,
+    pub driver: u16,
+    pub driver_attempts: u16,
+    pub programming: u16,
+    pub programming_attempts: u16,
+    /// Some data sources only report the combined score
+    combined: u16
+}
+
+impl EventSkills
+{
+    pub fn get_combined(&self) -> u16
+    {
+        self.combined.max(self.driver + self.programming)
+    }
+
+    /// Skills standings order: combined score, then programming, then driver
+    fn sort_key(&self) -> (u16, u16, u16)
+    {
+        (self.get_combined(), self.programming, self.driver)
+    }
+}
+
+impl fmt::Display for EventSkills
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{:<7} {:<14} combined {:>4}  driver {:>3} ({} attempts)  programming {:>3} ({} attempts)",
+            self.team, self.sku, self.get_combined(),
+            self.driver, self.driver_attempts, self.programming, self.programming_attempts)
+    }
+}
+
+/// Every team's best runs at each event, in the order the skills were given
+pub fn event_skills(skills: &[Skill]) -> Vec<EventSkills>
+{
+    let mut order: Vec<(String, String)> = Vec::new();
+    let mut by_event: HashMap<(String, String), EventSkills> = HashMap::new();
+    for skill in skills
+    {
+        let key = (skill.team.to_ascii_uppercase(), skill.sku.to_ascii_uppercase());
+        let event = by_event.entry(key.clone()).or_insert_with(||
+        {
+            order.push(key.clone());
+            EventSkills { sku: key.1.clone(), team: key.0.clone(), ..EventSkills::default() }
+        });
+
+        match skill.skill_type
+        {
+            SkillType::Driver => {
+                event.driver = event.driver.max(skill.score);
+                event.driver_attempts += skill.attempts;
+            },
+            SkillType::Programming => {
+                event.programming = event.programming.max(skill.score);
+                event.programming_attempts += skill.attempts;
+            },
+            SkillType::Combined => {
+                event.combined = event.combined.max(skill.score);
+            }
+        }
+    }
+
+    order.into_iter().filter_map(|key| by_event.remove(&key)).collect()
+}
+
+/// Each team's best event, best team first
+pub fn leaderboard(skills: &[Skill]) -> Vec<EventSkills>
+{
+    let mut best: HashMap<String, EventSkills> = HashMap::new();
+    for event in event_skills(skills)
+    {
+        match best.get(&event.team)
+        {
+            Some(current) if current.sort_key() >= event.sort_key() => (),
+            _ => {
+                best.insert(event.team.clone(), event);
+            }
+        }
+    }
+
+    let mut leaderboard = best.into_values().collect::<Vec<EventSkills>>();
+    leaderboard.sort_by(|a, b| b.sort_key().cmp(&a.sort_key()).then(a.team.cmp(&b.team)));
+    leaderboard
+}
+
+/// Teams in line for `spots` skills spots, best first
+///
+/// Teams already qualified through an award do not use up a spot, so the
+/// line skips them; the last team in a full line sets the cutoff
+pub fn worlds_line<'a>(leaderboard: &'a [EventSkills], qualified: &HashSet<String>, spots: usize) -> Vec<&'a EventSkills>
+{
+    leaderboard.iter()
+        .filter(|event| !qualified.contains(&event.team))
+        .take(spots)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn skill(team: &str, sku: &str, skill_type: SkillType, score: u16) -> Skill
+    {
+        Skill { sku: sku.to_owned(), team: team.to_owned(), skill_type, rank: 0, score, attempts: 1 }
+    }
+
+    #[test]
+    fn worlds_line_skips_qualified_teams()
+    {
+        let skills = vec![
+            skill("1A", "RE-1", SkillType::Driver, 50),
+            skill("1A", "RE-1", SkillType::Programming, 30),
+            skill("1A", "RE-2", SkillType::Driver, 60),
+            skill("1A", "RE-2", SkillType::Programming, 10),
+            skill("2A", "RE-1", SkillType::Driver, 40),
+            skill("2A", "RE-1", SkillType::Programming, 45),
+            skill("3A", "RE-2", SkillType::Driver, 80),
+            skill("4A", "RE-2", SkillType::Combined, 90)
+        ];
+
+        let leaderboard = leaderboard(&skills);
+        let standings = leaderboard.iter().map(|event| (event.team.as_str(), event.get_combined())).collect::<Vec<(&str, u16)>>();
+        // 1A and 3A both have 80; 1A's programming breaks the tie
+        assert_eq!(standings, vec![("4A", 90), ("2A", 85), ("1A", 80), ("3A", 80)]);
+        assert_eq!(leaderboard[2].sku, "RE-1");
+        assert_eq!(leaderboard[2].driver_attempts, 1);
+
+        let qualified = std::iter::once(String::from("2A")).collect::<HashSet<String>>();
+        let line = worlds_line(&leaderboard, &qualified, 2).iter().map(|event| event.team.as_str()).collect::<Vec<&str>>();
+        assert_eq!(line, vec!["4A", "1A"]);
+        assert_eq!(worlds_line(&leaderboard, &qualified, 10).len(), 3);
+    }
+}
@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::model::{Award, Event, Match, Ranking, SeasonRanking, Skill, Team};
+
+#[derive(Debug)]
+pub enum SourceError
+{
+    Network(String),
+    Parse(String),
+    Api(String),
+    Unauthorized,
+    Unsupported(&'static str),
+    NotAvailableOffline(String)
+}
+
+impl fmt::Display for SourceError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            SourceError::Network(message) => {
+                write!(f, "network error: {}", message)
+            },
+            SourceError::Parse(message) => {
+                write!(f, "unexpected response: {}", message)
+            },
+            SourceError::Api(message) => {
+                write!(f, "data source error: {}", message)
+            },
+            SourceError::Unauthorized => {
+                write!(f, "data source rejected the API token; set `robotevents_token` or ROBOTEVENTS_TOKEN")
+            },
+            SourceError::Unsupported(what) => {
+                write!(f, "{} is not supported by this data source", what)
+            },
+            SourceError::NotAvailableOffline(what) => {
+                write!(f, "{} is not available offline; run `offline prepare <sku>` while connected", what)
+            }
+        }
+    }
+}
+
+/// Filters shared by every data source query
+///
+/// Unset fields are not filtered on
+#[derive(Default, Clone, Copy)]
+pub struct Query<'a>
+{
+    pub sku: Option<&'a str>,
+    pub team: Option<&'a str>,
+    /// State or province, as spelled by the data source, e.g. `Texas`
+    pub region: Option<&'a str>
+}
+
+impl<'a> Query<'a>
+{
+    pub fn event(sku: &'a str) -> Query<'a>
+    {
+        Query { sku: Some(sku), ..Query::default() }
+    }
+
+    pub fn team(team: &'a str) -> Query<'a>
+    {
+        Query { team: Some(team), ..Query::default() }
+    }
+
+    pub fn team_at_event(sku: &'a str, team: &'a str) -> Query<'a>
+    {
+        Query { sku: Some(sku), team: Some(team), ..Query::default() }
+    }
+
+    pub fn region(region: &'a str) -> Query<'a>
+    {
+        Query { region: Some(region), ..Query::default() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSourceKind
+{
+    VexDb,
+    RobotEvents,
+    Fixture
+}
+
+impl DataSourceKind
+{
+    /// The name used in config files, also prefixed to cache keys
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DataSourceKind::VexDb => "vexdb",
+            DataSourceKind::RobotEvents => "robotevents",
+            DataSourceKind::Fixture => "fixture"
+        }
+    }
+}
+
+/// Everything the REPL needs to know about teams, events and matches
+///
+/// Commands only ever talk to a `DataSource`, so the same code runs against
+/// VexDB, RobotEvents or an in-memory `FixtureSource`
+pub trait DataSource
+{
+    /// Looks up each of the given team numbers, skipping ones that do not exist
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>;
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>;
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>;
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>;
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>;
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>;
+
+    fn get_divisions(&self, sku: &str) -> Result<Vec<String>, SourceError>
+    {
+        Ok(self.get_events(Query::event(sku))?
+            .into_iter()
+            .next()
+            .map(|event| event.divisions)
+            .unwrap_or_default())
+    }
+
+    fn get_season_rankings(&self, _team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        Err(SourceError::Unsupported("season rankings"))
+    }
+}
+
+fn matches_query(query: &Query, sku: &str, teams: &[&str]) -> bool
+{
+    let sku_matches = query.sku.is_none_or(|wanted| wanted.eq_ignore_ascii_case(sku));
+    let team_matches = query.team.is_none_or(|wanted| teams.iter().any(|team| wanted.eq_ignore_ascii_case(team)));
+    sku_matches && team_matches
+}
+
+/// In-memory data source, loaded from a JSON dump or built by hand
+///
+/// Never touches the network, which makes it the data source to drive the
+/// REPL with when no connection is available
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct FixtureSource
+{
+    pub teams: Vec<Team>,
+    pub events: Vec<Event>,
+    pub matches: Vec<Match>,
+    pub rankings: Vec<Ranking>,
+    pub awards: Vec<Award>,
+    pub skills: Vec<Skill>,
+    pub season_rankings: Vec<SeasonRanking>
+}
+
+impl FixtureSource
+{
+    pub fn load(path: &Path) -> Result<FixtureSource, SourceError>
+    {
+        let file = File::open(path)
+            .map_err(|error| SourceError::Api(format!("could not open {}: {}", path.display(), error)))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| SourceError::Parse(error.to_string()))
+    }
+}
+
+impl DataSource for FixtureSource
+{
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        Ok(self.teams.iter()
+            .filter(|team| numbers.iter().any(|number| number.eq_ignore_ascii_case(&team.number)))
+            .cloned()
+            .collect())
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        Ok(self.events.iter()
+            .filter(|event| query.sku.is_none_or(|sku| sku.eq_ignore_ascii_case(&event.sku)))
+            .filter(|event| query.region.is_none_or(|region| region.eq_ignore_ascii_case(&event.region)))
+            .filter(|event| query.team.is_none_or(|team|
+            {
+                let attended = |sku: &str, other_team: &str| sku == event.sku && team.eq_ignore_ascii_case(other_team);
+                self.rankings.iter().any(|ranking| attended(&ranking.sku, &ranking.team))
+                    || self.matches.iter().any(|match_struct| match_struct.teams().any(|other_team| attended(&match_struct.sku, other_team)))
+            }))
+            .cloned()
+            .collect())
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        Ok(self.matches.iter()
+            .filter(|match_struct|
+            {
+                let teams = match_struct.teams().map(String::as_str).collect::<Vec<&str>>();
+                matches_query(&query, &match_struct.sku, &teams)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        Ok(self.rankings.iter()
+            .filter(|ranking| matches_query(&query, &ranking.sku, &[&ranking.team]))
+            .cloned()
+            .collect())
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        Ok(self.awards.iter()
+            .filter(|award| matches_query(&query, &award.sku, &[&award.team]))
+            .cloned()
+            .collect())
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        Ok(self.skills.iter()
+            .filter(|skill| matches_query(&query, &skill.sku, &[&skill.team]))
+            .cloned()
+            .collect())
+    }
+
+    fn get_season_rankings(&self, team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        Ok(self.season_rankings.iter()
+            .filter(|season_ranking| season_ranking.team.eq_ignore_ascii_case(team))
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::model::{Color, Match, Round};
+
+/// Standard normal cumulative distribution function
+pub fn normal_cdf(x: f64) -> f64
+{
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Standard normal probability density function
+pub fn normal_pdf(x: f64) -> f64
+{
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Inverse of `normal_cdf` by bisection, for probabilities strictly between 0 and 1
+pub fn normal_quantile(probability: f64) -> f64
+{
+    let (mut low, mut high) = (-10.0, 10.0);
+    for _ in 0..100
+    {
+        let middle = (low + high) / 2.0;
+        if normal_cdf(middle) < probability
+        {
+            low = middle;
+        }
+        else
+        {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Abramowitz and Stegun 7.1.26, accurate to about 1.5e-7
+fn erf(x: f64) -> f64
+{
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let polynomial = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - polynomial * (-x * x).exp())
+}
+
+pub struct Prediction
+{
+    pub red_score: f64,
+    pub blue_score: f64,
+    /// Standard deviation of each alliance's score
+    pub score_deviation: f64,
+    pub red_win_probability: f64
+}
+
+impl Prediction
+{
+    pub fn win_probability(&self, color: Color) -> f64
+    {
+        match color
+        {
+            Color::Red => self.red_win_probability,
+            Color::Blue => 1.0 - self.red_win_probability
+        }
+    }
+}
+
+/// Sum of the playing teams' contributions; unknown teams contribute nothing
+pub fn expected_score(match_struct: &Match, color: Color, contributions: &HashMap<String, f64>) -> f64
+{
+    match_struct.alliance(color).playing()
+        .map(|team| contributions.get(&team.to_ascii_uppercase()).copied().unwrap_or(0.0))
+        .sum()
+}
+
+/// Standard deviation of alliance scores around their expected values,
+/// estimated from every scored match
+///
+/// Contributions fitted to these same matches explain some of the noise away,
+/// so each one costs a degree of freedom
+pub fn score_deviation(matches: &[Match], contributions: &HashMap<String, f64>) -> f64
+{
+    let scored = matches.iter().filter(|match_struct| match_struct.scored).collect::<Vec<&Match>>();
+    let residuals = scored.iter()
+        .flat_map(|match_struct| [Color::Red, Color::Blue].iter()
+            .map(|color| match_struct.alliance(*color).score as f64 - expected_score(match_struct, *color, contributions))
+            .collect::<Vec<f64>>())
+        .collect::<Vec<f64>>();
+
+    let mut fitted_teams = scored.iter()
+        .flat_map(|match_struct| match_struct.teams())
+        .map(|team| team.to_ascii_uppercase())
+        .filter(|team| contributions.contains_key(team))
+        .collect::<Vec<String>>();
+    fitted_teams.sort();
+    fitted_teams.dedup();
+
+    if residuals.len() < fitted_teams.len() + 2
+    {
+        // without enough data, assume scores are about as noisy as a typical alliance
+        return 10.0;
+    }
+
+    let mean_square = residuals.iter().map(|residual| residual * residual).sum::<f64>() / (residuals.len() - fitted_teams.len() - 1) as f64;
+    mean_square.sqrt().max(1.0)
+}
+
+/// Predicts a match assuming each alliance's score is normally distributed
+/// around the sum of its teams' contributions
+pub fn predict(match_struct: &Match, contributions: &HashMap<String, f64>, deviation: f64) -> Prediction
+{
+    let red_score = expected_score(match_struct, Color::Red, contributions);
+    let blue_score = expected_score(match_struct, Color::Blue, contributions);
+
+    // the difference of two independent alliance scores
+    let margin_deviation = deviation * std::f64::consts::SQRT_2;
+
+    Prediction
+    {
+        red_score,
+        blue_score,
+        score_deviation: deviation,
+        red_win_probability: normal_cdf((red_score - blue_score) / margin_deviation)
+    }
+}
+
+/// Ratings based on fewer matches than this are flagged as unreliable
+pub const MIN_RATED_MATCHES: u16 = 3;
+
+/// Pulls a singular system (e.g. two teams that were always partners) towards
+/// the average team instead of failing; small enough not to move other ratings
+const RIDGE: f64 = 1e-3;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PowerStat
+{
+    Opr,
+    Dpr,
+    Ccwm
+}
+
+impl PowerStat
+{
+    pub fn parse(name: &str) -> Option<PowerStat>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "opr" => Some(PowerStat::Opr),
+            "dpr" => Some(PowerStat::Dpr),
+            "ccwm" => Some(PowerStat::Ccwm),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            PowerStat::Opr => "OPR",
+            PowerStat::Dpr => "DPR",
+            PowerStat::Ccwm => "CCWM"
+        }
+    }
+
+    /// Lower DPR is better, since it is the points a team lets opponents score
+    pub fn higher_is_better(&self) -> bool
+    {
+        *self != PowerStat::Dpr
+    }
+}
+
+pub struct PowerRating
+{
+    pub team: String,
+    pub opr: f64,
+    pub dpr: f64,
+    pub ccwm: f64,
+    /// Scored qualification matches the team played in
+    pub matches: u16
+}
+
+impl PowerRating
+{
+    pub fn get(&self, stat: PowerStat) -> f64
+    {
+        match stat
+        {
+            PowerStat::Opr => self.opr,
+            PowerStat::Dpr => self.dpr,
+            PowerStat::Ccwm => self.ccwm
+        }
+    }
+
+    pub fn is_reliable(&self) -> bool
+    {
+        self.matches >= MIN_RATED_MATCHES
+    }
+}
+
+impl fmt::Display for PowerRating
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{:<7} OPR {:>6.2} DPR {:>6.2} CCWM {:>6.2} ({} matches)", self.team, self.opr, self.dpr, self.ccwm, self.matches)
+    }
+}
+
+/// OPR, DPR and CCWM from every scored qualification match, sorted by OPR
+///
+/// Each alliance score is modelled as the sum of its playing teams' OPRs (and
+/// the opponents' score as the sum of their DPRs), solved by least squares.
+/// CCWM is the same fit on the winning margin, which works out to OPR - DPR.
+pub fn power_ratings(matches: &[Match]) -> Vec<PowerRating>
+{
+    let mut team_indices: HashMap<String, usize> = HashMap::new();
+    let mut teams: Vec<String> = Vec::new();
+    let mut rows: Vec<Vec<usize>> = Vec::new();
+    let mut scored: Vec<f64> = Vec::new();
+    let mut allowed: Vec<f64> = Vec::new();
+
+    let qualifications = matches.iter()
+        .filter(|match_struct| match_struct.scored && match_struct.round == Round::Qualification);
+    for match_struct in qualifications
+    {
+        for color in &[Color::Red, Color::Blue]
+        {
+            let row = match_struct.alliance(*color).playing()
+                .map(|team|
+                {
+                    let team = team.to_ascii_uppercase();
+                    *team_indices.entry(team.clone()).or_insert_with(||
+                    {
+                        teams.push(team);
+                        teams.len() - 1
+                    })
+                })
+                .collect::<Vec<usize>>();
+
+            if !row.is_empty()
+            {
+                rows.push(row);
+                scored.push(match_struct.alliance(*color).score as f64);
+                allowed.push(match_struct.alliance(color.opposite()).score as f64);
+            }
+        }
+    }
+
+    let opr = solve_least_squares(&rows, &scored, teams.len());
+    let dpr = solve_least_squares(&rows, &allowed, teams.len());
+
+    let mut appearances = vec![0u16; teams.len()];
+    for index in rows.iter().flatten()
+    {
+        appearances[*index] += 1;
+    }
+
+    let mut ratings = teams.into_iter()
+        .enumerate()
+        .map(|(index, team)| PowerRating
+        {
+            team,
+            opr: opr[index],
+            dpr: dpr[index],
+            ccwm: opr[index] - dpr[index],
+            matches: appearances[index]
+        })
+        .collect::<Vec<PowerRating>>();
+    ratings.sort_by(|a, b| b.opr.total_cmp(&a.opr));
+    ratings
+}
+
+/// Least squares fit of `targets` where each row sums the unknowns it lists,
+/// shrunk slightly towards the average so every system has a solution
+fn solve_least_squares(rows: &[Vec<usize>], targets: &[f64], unknowns: usize) -> Vec<f64>
+{
+    if unknowns == 0
+    {
+        return Vec::new();
+    }
+
+    let entries = rows.iter().map(Vec::len).sum::<usize>();
+    let average = targets.iter().sum::<f64>() / entries as f64;
+
+    // normal equations (AᵀA + λI) y = Aᵀ(b - A·average), solved for the offsets from average
+    let mut normal = vec![vec![0.0; unknowns]; unknowns];
+    let mut right = vec![0.0; unknowns];
+    for (row, target) in rows.iter().zip(targets)
+    {
+        let residual = target - average * row.len() as f64;
+        for i in row
+        {
+            right[*i] += residual;
+            for j in row
+            {
+                normal[*i][*j] += 1.0;
+            }
+        }
+    }
+    for (i, normal_row) in normal.iter_mut().enumerate()
+    {
+        normal_row[i] += RIDGE;
+    }
+
+    cholesky_solve(normal, right).into_iter()
+        .map(|offset| average + offset)
+        .collect()
+}
+
+/// Solves a symmetric positive definite system in place
+fn cholesky_solve(mut matrix: Vec<Vec<f64>>, mut right: Vec<f64>) -> Vec<f64>
+{
+    let size = right.len();
+
+    // lower triangle becomes L where L·Lᵀ = matrix
+    for j in 0..size
+    {
+        let diagonal = matrix[j][j] - (0..j).map(|k| matrix[j][k] * matrix[j][k]).sum::<f64>();
+        matrix[j][j] = diagonal.max(f64::EPSILON).sqrt();
+        for i in j + 1..size
+        {
+            let sum = (0..j).map(|k| matrix[i][k] * matrix[j][k]).sum::<f64>();
+            matrix[i][j] = (matrix[i][j] - sum) / matrix[j][j];
+        }
+    }
+
+    // forward substitution with L, then back substitution with Lᵀ
+    for i in 0..size
+    {
+        let sum = (0..i).map(|k| matrix[i][k] * right[k]).sum::<f64>();
+        right[i] = (right[i] - sum) / matrix[i][i];
+    }
+    for i in (0..size).rev()
+    {
+        let sum = (i + 1..size).map(|k| matrix[k][i] * right[k]).sum::<f64>();
+        right[i] = (right[i] - sum) / matrix[i][i];
+    }
+    right
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn power_ratings_recover_additive_scores()
+    {
+        // every alliance scores exactly the sum of 10, 20, 30 and 40 point teams
+        let mut final_match = testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((200, 0)));
+        final_match.round = Round::Final;
+        let matches = vec![
+            testing::qualification(1, &["1A", "2A"], &["3A", "4A"], Some((30, 70))),
+            testing::qualification(2, &["1A", "3A"], &["2A", "4A"], Some((40, 60))),
+            testing::qualification(3, &["1A", "4A"], &["2A", "3A"], Some((50, 50))),
+            testing::qualification(4, &["1A", "2A"], &["3A", "4A"], None),
+            final_match
+        ];
+
+        let ratings = power_ratings(&matches);
+        assert_eq!(ratings.iter().map(|rating| rating.team.as_str()).collect::<Vec<&str>>(), vec!["4A", "3A", "2A", "1A"]);
+        for (rating, expected) in ratings.iter().zip(&[40.0, 30.0, 20.0, 10.0])
+        {
+            assert!((rating.opr - expected).abs() < 0.01, "{} OPR {}", rating.team, rating.opr);
+            assert!((rating.ccwm - (rating.opr - rating.dpr)).abs() < 1e-9);
+            assert_eq!(rating.matches, 3);
+            assert!(rating.is_reliable());
+        }
+        // every point scored is a point allowed, so the DPRs add up like the OPRs
+        let dpr_total = ratings.iter().map(|rating| rating.dpr).sum::<f64>();
+        assert!((dpr_total - 100.0).abs() < 0.01);
+    }
+}
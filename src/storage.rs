@@ -0,0 +1,17 @@
+//! Files kept under `vexscout` in the config directory
+
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Writes `value` to `path` as JSON, creating the directories above it
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String>
+{
+    if let Some(parent) = path.parent()
+    {
+        let _ = fs::create_dir_all(parent);
+    }
+    let file = File::create(path).map_err(|error| format!("could not write {}: {}", path.display(), error))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), value).map_err(|error| format!("could not write {}: {}", path.display(), error))
+}
@@ -0,0 +1,124 @@
+//! Helpers shared by the unit tests
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::model::{Alliance, Event, Match, Round, Team};
+use crate::ranking::{Ruleset, SpRule, Tiebreaker};
+
+/// The competition the helpers put their matches at
+pub const SKU: &str = "RE-TEST";
+
+/// Answers HTTP requests on a local port with the JSON `respond` gives for
+/// each request's path and query
+///
+/// Returns the base url to point a client at and every path requested so far
+pub fn serve<F>(respond: F) -> (String, Arc<Mutex<Vec<String>>>)
+    where F: Fn(&str) -> String + Send + 'static
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind a local port");
+    let base_url = format!("http://{}", listener.local_addr().expect("local address"));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let recorded = Arc::clone(&requests);
+    thread::spawn(move ||
+    {
+        for stream in listener.incoming().flatten()
+        {
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err()
+            {
+                continue;
+            }
+            // the headers are not needed, but have to be read past
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|length| length > 2)
+            {
+                header.clear();
+            }
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_owned();
+            let body = respond(&path);
+            recorded.lock().unwrap().push(path);
+            let _ = write!(&stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body);
+        }
+    });
+
+    (base_url, requests)
+}
+
+pub fn team(number: &str) -> Team
+{
+    Team
+    {
+        number: number.to_owned(),
+        program: String::from("VRC"),
+        team_name: format!("Team {}", number),
+        robot_name: String::new(),
+        organisation: String::new(),
+        city: String::from("Austin"),
+        region: String::from("Texas"),
+        country: String::from("United States"),
+        grade: String::from("High School"),
+        registered: true
+    }
+}
+
+/// An event with a single division, `Div`
+pub fn event(sku: &str, start: &str) -> Event
+{
+    Event
+    {
+        sku: sku.to_owned(),
+        name: format!("Event {}", sku),
+        program: String::from("VRC"),
+        season: String::from("Test season"),
+        venue: String::new(),
+        city: String::from("Austin"),
+        region: String::from("Texas"),
+        country: String::from("United States"),
+        start: start.to_owned(),
+        end: start.to_owned(),
+        divisions: vec![String::from("Div")]
+    }
+}
+
+/// A qualification match at `SKU`, scored when `score` is given as (red, blue)
+pub fn qualification(number: u16, red: &[&str], blue: &[&str], score: Option<(u16, u16)>) -> Match
+{
+    let (red_score, blue_score) = score.unwrap_or_default();
+    let alliance = |teams: &[&str], score| Alliance { teams: teams.iter().map(|team| team.to_string()).collect(), sitting: None, score };
+    Match
+    {
+        sku: SKU.to_owned(),
+        division: String::from("Div"),
+        round: Round::Qualification,
+        instance: 1,
+        match_num: number,
+        field: String::new(),
+        scheduled: None,
+        started: None,
+        red: alliance(red, red_score),
+        blue: alliance(blue, blue_score),
+        scored: score.is_some()
+    }
+}
+
+/// The built in `default` ruleset, without reading the user's rulesets
+pub fn ruleset() -> Ruleset
+{
+    Ruleset
+    {
+        name: String::from("default"),
+        seasons: Vec::new(),
+        win_points: 2,
+        tie_points: 1,
+        loss_points: 0,
+        strength_of_schedule: SpRule::LosingScore,
+        tiebreakers: vec![Tiebreaker::Wp, Tiebreaker::Ap, Tiebreaker::Sp, Tiebreaker::HighScore]
+    }
+}
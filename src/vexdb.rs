@@ -0,0 +1,481 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::model::{Alliance, Award, Event, Match, Ranking, Round, SeasonRanking, Skill, SkillType, Team};
+use crate::source::{DataSource, Query, SourceError};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.vexdb.io/v1";
+
+/// VexDB caps every response at 5000 results, so larger queries are paged
+const PAGE_SIZE: usize = 5000;
+
+#[derive(Deserialize)]
+struct Response<T>
+{
+    status: u8,
+    #[serde(default = "Vec::new")]
+    result: Vec<T>,
+    #[serde(default)]
+    error_code: i32,
+    #[serde(default)]
+    error_text: String
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawTeam
+{
+    number: String,
+    program: String,
+    team_name: String,
+    robot_name: String,
+    organisation: String,
+    city: String,
+    region: String,
+    country: String,
+    grade: String,
+    is_registered: u8
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawEvent
+{
+    sku: String,
+    program: String,
+    name: String,
+    loc_venue: String,
+    loc_city: String,
+    loc_region: String,
+    loc_country: String,
+    season: String,
+    start: String,
+    end: String,
+    divisions: Vec<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawMatch
+{
+    sku: String,
+    division: String,
+    round: u8,
+    instance: u16,
+    matchnum: u16,
+    field: String,
+    scheduled: String,
+    red1: String,
+    red2: String,
+    red3: String,
+    redsit: String,
+    blue1: String,
+    blue2: String,
+    blue3: String,
+    bluesit: String,
+    redscore: u16,
+    bluescore: u16,
+    scored: u8
+}
+
+fn alliance(teams: [String; 3], sitting: String, score: u16) -> Alliance
+{
+    Alliance
+    {
+        teams: teams.iter().filter(|team| !team.is_empty()).cloned().collect(),
+        sitting: Some(sitting).filter(|sitting| !sitting.is_empty()),
+        score
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawRanking
+{
+    sku: String,
+    division: String,
+    team: String,
+    rank: u16,
+    wins: u16,
+    losses: u16,
+    ties: u16,
+    wp: u16,
+    ap: u16,
+    sp: u16,
+    max_score: u16,
+    opr: f64,
+    dpr: f64,
+    ccwm: f64
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawAward
+{
+    sku: String,
+    name: String,
+    team: String,
+    qualifies: Vec<String>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawSkill
+{
+    sku: String,
+    team: String,
+    #[serde(rename = "type")]
+    skill_type: u8,
+    rank: u16,
+    score: u16,
+    attempts: u16
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawSeasonRanking
+{
+    team: String,
+    program: String,
+    season: String,
+    vrating_rank: u32,
+    vrating: f64
+}
+
+impl From<RawTeam> for Team
+{
+    fn from(raw: RawTeam) -> Team
+    {
+        Team
+        {
+            number: raw.number,
+            program: raw.program,
+            team_name: raw.team_name,
+            robot_name: raw.robot_name,
+            organisation: raw.organisation,
+            city: raw.city,
+            region: raw.region,
+            country: raw.country,
+            grade: raw.grade,
+            registered: raw.is_registered != 0
+        }
+    }
+}
+
+impl From<RawEvent> for Event
+{
+    fn from(raw: RawEvent) -> Event
+    {
+        Event
+        {
+            sku: raw.sku,
+            name: raw.name,
+            program: raw.program,
+            season: raw.season,
+            venue: raw.loc_venue,
+            city: raw.loc_city,
+            region: raw.loc_region,
+            country: raw.loc_country,
+            start: raw.start,
+            end: raw.end,
+            divisions: raw.divisions
+        }
+    }
+}
+
+impl From<RawMatch> for Match
+{
+    fn from(raw: RawMatch) -> Match
+    {
+        Match
+        {
+            sku: raw.sku,
+            division: raw.division,
+            round: Round::from_vexdb_code(raw.round),
+            instance: raw.instance,
+            match_num: raw.matchnum,
+            field: raw.field,
+            scheduled: Some(raw.scheduled).filter(|scheduled| !scheduled.is_empty()),
+            started: None,
+            red: alliance([raw.red1, raw.red2, raw.red3], raw.redsit, raw.redscore),
+            blue: alliance([raw.blue1, raw.blue2, raw.blue3], raw.bluesit, raw.bluescore),
+            scored: raw.scored != 0
+        }
+    }
+}
+
+impl From<RawRanking> for Ranking
+{
+    fn from(raw: RawRanking) -> Ranking
+    {
+        Ranking
+        {
+            sku: raw.sku,
+            division: raw.division,
+            team: raw.team,
+            rank: raw.rank,
+            wins: raw.wins,
+            losses: raw.losses,
+            ties: raw.ties,
+            wp: raw.wp,
+            ap: raw.ap,
+            sp: raw.sp,
+            max_score: raw.max_score,
+            opr: raw.opr,
+            dpr: raw.dpr,
+            ccwm: raw.ccwm
+        }
+    }
+}
+
+impl From<RawAward> for Award
+{
+    fn from(raw: RawAward) -> Award
+    {
+        Award
+        {
+            sku: raw.sku,
+            name: raw.name,
+            team: raw.team,
+            qualifies: raw.qualifies
+        }
+    }
+}
+
+impl From<RawSkill> for Skill
+{
+    fn from(raw: RawSkill) -> Skill
+    {
+        let skill_type = match raw.skill_type
+        {
+            0 => SkillType::Driver,
+            1 => SkillType::Programming,
+            _ => SkillType::Combined
+        };
+
+        Skill
+        {
+            sku: raw.sku,
+            team: raw.team,
+            skill_type,
+            rank: raw.rank,
+            score: raw.score,
+            attempts: raw.attempts
+        }
+    }
+}
+
+impl From<RawSeasonRanking> for SeasonRanking
+{
+    fn from(raw: RawSeasonRanking) -> SeasonRanking
+    {
+        SeasonRanking
+        {
+            team: raw.team,
+            program: raw.program,
+            season: raw.season,
+            vrating_rank: raw.vrating_rank,
+            vrating: raw.vrating
+        }
+    }
+}
+
+fn parameters<'a>(query: Query<'a>) -> Vec<(&'static str, &'a str)>
+{
+    let mut parameters = Vec::new();
+    if let Some(sku) = query.sku
+    {
+        parameters.push(("sku", sku));
+    }
+    if let Some(team) = query.team
+    {
+        parameters.push(("team", team));
+    }
+    if let Some(region) = query.region
+    {
+        parameters.push(("region", region));
+    }
+    parameters
+}
+
+/// Client for the VexDB v1 API
+pub struct VexDbClient
+{
+    base_url: String,
+    agent: ureq::Agent
+}
+
+impl VexDbClient
+{
+    pub fn new(base_url: &str) -> VexDbClient
+    {
+        VexDbClient
+        {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(15))
+                .build()
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, endpoint: &str, parameters: &[(&str, &str)]) -> Result<Vec<T>, SourceError>
+    {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        let mut results = Vec::<T>::new();
+
+        loop
+        {
+            let limit_start = results.len().to_string();
+            let limit_number = PAGE_SIZE.to_string();
+
+            let mut request = self.agent.get(&url);
+            for (key, value) in parameters
+            {
+                request = request.query(key, value);
+            }
+            request = request
+                .query("limit_start", &limit_start)
+                .query("limit_number", &limit_number);
+
+            let response = request.call()
+                .map_err(|error| SourceError::Network(error.to_string()))?;
+            let response: Response<T> = serde_json::from_reader(response.into_reader())
+                .map_err(|error| SourceError::Parse(error.to_string()))?;
+
+            if response.status != 1
+            {
+                return Err(SourceError::Api(format!("VexDB error {}: {}", response.error_code, response.error_text)));
+            }
+
+            let page_length = response.result.len();
+            results.extend(response.result);
+
+            // `size` only counts the rows in this page, so a short page is the only sign of the end
+            if page_length < PAGE_SIZE
+            {
+                return Ok(results);
+            }
+        }
+    }
+}
+
+impl DataSource for VexDbClient
+{
+    fn get_teams(&self, numbers: &[String]) -> Result<Vec<Team>, SourceError>
+    {
+        let mut teams = Vec::new();
+        for number in numbers
+        {
+            teams.extend(self.get::<RawTeam>("get_teams", &[("team", number)])?.into_iter().map(Team::from));
+        }
+        Ok(teams)
+    }
+
+    fn get_events(&self, query: Query) -> Result<Vec<Event>, SourceError>
+    {
+        Ok(self.get::<RawEvent>("get_events", &parameters(query))?.into_iter().map(Event::from).collect())
+    }
+
+    fn get_matches(&self, query: Query) -> Result<Vec<Match>, SourceError>
+    {
+        Ok(self.get::<RawMatch>("get_matches", &parameters(query))?.into_iter().map(Match::from).collect())
+    }
+
+    /// Without an event, only the current season's rankings are returned
+    fn get_rankings(&self, query: Query) -> Result<Vec<Ranking>, SourceError>
+    {
+        let mut parameters = parameters(query);
+        if query.sku.is_none()
+        {
+            parameters.push(("season", "current"));
+        }
+        Ok(self.get::<RawRanking>("get_rankings", &parameters)?.into_iter().map(Ranking::from).collect())
+    }
+
+    fn get_awards(&self, query: Query) -> Result<Vec<Award>, SourceError>
+    {
+        Ok(self.get::<RawAward>("get_awards", &parameters(query))?.into_iter().map(Award::from).collect())
+    }
+
+    fn get_skills(&self, query: Query) -> Result<Vec<Skill>, SourceError>
+    {
+        Ok(self.get::<RawSkill>("get_skills", &parameters(query))?.into_iter().map(Skill::from).collect())
+    }
+
+    fn get_season_rankings(&self, team: &str) -> Result<Vec<SeasonRanking>, SourceError>
+    {
+        Ok(self.get::<RawSeasonRanking>("get_season_rankings", &[("team", team), ("season", "current")])?
+            .into_iter().map(SeasonRanking::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn parses_matches()
+    {
+        let (base_url, requests) = testing::serve(|_| String::from(r#"{"status": 1, "size": 2, "result": [
+            {"sku": "RE-VRC-17-3805", "division": "Science", "round": 3, "instance": 2, "matchnum": 1, "field": "Field 1",
+             "scheduled": "2018-04-26 09:00:00", "red1": "1A", "red2": "2A", "red3": "3A", "redsit": "3A",
+             "blue1": "4A", "blue2": "5A", "blue3": "", "bluesit": "", "redscore": 42, "bluescore": 17, "scored": 1},
+            {"sku": "RE-VRC-17-3805", "division": "Science", "round": 1, "instance": 1, "matchnum": 12,
+             "red1": "1A", "red2": "5A", "blue1": "2A", "blue2": "4A", "scored": 0}
+        ]}"#));
+        let client = VexDbClient::new(&base_url);
+
+        let matches = client.get_matches(Query::event("RE-VRC-17-3805")).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].name(), "SF 2-1");
+        assert_eq!(matches[0].red.teams, vec!["1A", "2A", "3A"]);
+        assert_eq!(matches[0].red.sitting.as_deref(), Some("3A"));
+        assert_eq!(matches[0].blue.teams, vec!["4A", "5A"]);
+        assert_eq!(matches[0].blue.sitting, None);
+        assert_eq!((matches[0].red.score, matches[0].blue.score), (42, 17));
+        assert_eq!(matches[0].scheduled.as_deref(), Some("2018-04-26 09:00:00"));
+        assert!(matches[0].scored);
+        assert_eq!(matches[1].name(), "Q12");
+        assert_eq!(matches[1].scheduled, None);
+        assert!(!matches[1].scored);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("/get_matches?sku=RE-VRC-17-3805&"));
+    }
+
+    #[test]
+    fn pages_until_a_short_page()
+    {
+        let (base_url, requests) = testing::serve(|path|
+        {
+            // VexDB's `size` is only the length of the page, not of the whole result
+            let length = if path.contains("limit_start=0&") { PAGE_SIZE } else { 3 };
+            let rows = vec![r#"{"sku": "RE-VRC-17-0001", "name": "Event"}"#; length].join(",");
+            format!(r#"{{"status": 1, "size": {}, "result": [{}]}}"#, length, rows)
+        });
+        let client = VexDbClient::new(&base_url);
+
+        let events = client.get_events(Query::region("Texas")).unwrap();
+        assert_eq!(events.len(), PAGE_SIZE + 3);
+        assert_eq!(events[0].sku, "RE-VRC-17-0001");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains(&format!("limit_start={}&", PAGE_SIZE)));
+    }
+
+    #[test]
+    fn reports_api_errors()
+    {
+        let (base_url, _) = testing::serve(|_| String::from(r#"{"status": 0, "error_code": 4, "error_text": "Invalid parameter"}"#));
+        let client = VexDbClient::new(&base_url);
+
+        match client.get_rankings(Query::event("RE-VRC-17-3805"))
+        {
+            Err(SourceError::Api(message)) => assert_eq!(message, "VexDB error 4: Invalid parameter"),
+            other => panic!("expected an API error, got {:?}", other.map(|rankings| rankings.len()))
+        }
+    }
+}